@@ -0,0 +1,8 @@
+pub mod extents;
+pub mod limits;
+pub mod statistics;
+pub mod safety;
+pub mod lint;
+pub mod cycle_time;
+pub mod tools;
+pub mod operations;
@@ -0,0 +1,97 @@
+use crate::analysis::extents::position;
+use crate::analysis::statistics::distance;
+use crate::structs::machine_profile::MachineProfile;
+use crate::structs::motion_mode::Motion;
+use crate::structs::program::Program;
+use serde::{Deserialize, Serialize};
+
+/// 加工時間の見積り（分）
+/// 加減速は考慮せず、移動距離を送り速度で割って求める
+#[derive(Debug, Default, PartialEq, Clone, Copy, Serialize, Deserialize)]
+pub struct CycleTime {
+    rapid: f32,
+    feed: f32,
+    tool_change: f32,
+}
+
+impl CycleTime {
+    /// 機械の設定の早送り速度と工具交換の時間からプログラムの加工時間を見積もる
+    /// 早送りは各軸が独立して移動し、最も時間のかかる軸の時間とする
+    /// 送り速度が指令されていない切削送りは時間に含めない
+    pub fn new(program: &Program, profile: &MachineProfile) -> Self {
+        let rapid_rates = profile.get_axes().to_array().map(|axis| axis.get_rapid_rate());
+        let mut cycle_time = Self::default();
+        program.moves().for_each(|(start, block)| {
+            let end = block.get_state();
+            if end.get_motion() == Motion::Rapid {
+                let (start, end) = (position(&start), position(&end));
+                cycle_time.rapid += (0..3)
+                    .map(|axis| (end[axis] - start[axis]).abs() / rapid_rates[axis])
+                    .fold(0.0, f32::max);
+            } else if end.get_feed() > 0.0 {
+                cycle_time.feed += distance(&start, &end) / end.get_feed();
+            }
+        });
+        let tool_changes = program.tool_changes().count();
+        cycle_time.tool_change = tool_changes as f32 * profile.get_tool_change_time() / 60.0;
+        cycle_time
+    }
+
+    /// 早送りの時間を返す
+    pub fn get_rapid(&self) -> f32 {
+        self.rapid
+    }
+
+    /// 切削送りの時間を返す
+    pub fn get_feed(&self) -> f32 {
+        self.feed
+    }
+
+    /// 工具交換の時間を返す
+    pub fn get_tool_change(&self) -> f32 {
+        self.tool_change
+    }
+
+    /// 合計の時間を返す
+    pub fn total(&self) -> f32 {
+        self.rapid + self.feed + self.tool_change
+    }
+}
+
+impl std::fmt::Display for CycleTime {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Rapid : {}", minutes(self.rapid))?;
+        writeln!(f, "Feed : {}", minutes(self.feed))?;
+        writeln!(f, "Tool change : {}", minutes(self.tool_change))?;
+        writeln!(f, "Total : {}", minutes(self.total()))
+    }
+}
+
+/// 分を時:分:秒の文字列にする
+pub fn minutes(value: f32) -> String {
+    let seconds = (value * 60.0).round() as u64;
+    format!("{}:{:02}:{:02}", seconds / 3600, seconds / 60 % 60, seconds % 60)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cycle_time_new() {
+        let source = "G00X3000.0Z-2400.0\nG01X3100.0F100.\nY50.0F50.\nG01X0.F0\nT2M06";
+        let result = CycleTime::new(&Program::new(source).unwrap(), &MachineProfile::default());
+        assert_eq!(result.get_rapid(), 0.1);
+        assert_eq!(result.get_feed(), 2.0);
+        assert_eq!(result.get_tool_change(), 0.05);
+        assert!((result.total() - 2.15).abs() < 1.0e-6);
+    }
+
+    #[test]
+    fn cycle_time_minutes() {
+        let datas = [(0.0, "0:00:00"), (1.5, "0:01:30"), (125.25, "2:05:15")];
+        datas.iter().for_each(|&(value, expected)| {
+            assert_eq!(minutes(value), expected);
+        })
+    }
+}
@@ -0,0 +1,121 @@
+use crate::structs::arc::Arc;
+use crate::structs::program::Program;
+use crate::structs::state::State;
+use serde::{Deserialize, Serialize};
+
+/// 軸のアドレス
+pub const AXES: [char; 3] = ['X', 'Y', 'Z'];
+
+/// 各軸の最小値と最大値
+#[derive(Debug, PartialEq, Clone, Copy, Serialize, Deserialize)]
+pub struct Extents {
+    min: [f32; 3],
+    max: [f32; 3],
+}
+
+impl Extents {
+    /// 1点からインスタンス化
+    pub fn new(point: [f32; 3]) -> Self {
+        Self { min: point, max: point }
+    }
+
+    /// 移動の軌跡が通過する範囲からインスタンス化
+    /// 直線移動は終点のみ、円弧は始点、終点と通過する象限点を含める
+    pub fn of_move(start: &State, end: &State) -> Self {
+        match Arc::new(start, end) {
+            Some(arc) => {
+                let (min, max) = arc.extents();
+                Self { min: [min.0, min.1, min.2], max: [max.0, max.1, max.2] }
+            },
+            None => Self::new(position(end)),
+        }
+    }
+
+    /// プログラム全体の移動の範囲を返す
+    /// 移動がない場合はNoneを返す
+    pub fn of_program(program: &Program) -> Option<Self> {
+        program.moves()
+            .map(|(start, block)| Self::of_move(&start, &block.get_state()))
+            .reduce(|mut extents, other| {
+                extents.include(&other);
+                extents
+            })
+    }
+
+    /// 他の範囲を含むように広げる
+    pub fn include(&mut self, other: &Extents) {
+        (0..3).for_each(|axis| {
+            self.min[axis] = self.min[axis].min(other.min[axis]);
+            self.max[axis] = self.max[axis].max(other.max[axis]);
+        });
+    }
+
+    /// 各軸を平行移動した範囲を返す
+    pub fn translated(&self, offset: [f32; 3]) -> Self {
+        let mut extents = *self;
+        (0..3).for_each(|axis| {
+            extents.min[axis] += offset[axis];
+            extents.max[axis] += offset[axis];
+        });
+        extents
+    }
+
+    /// 各軸の最小値を返す
+    pub fn get_min(&self) -> [f32; 3] {
+        self.min
+    }
+
+    /// 各軸の最大値を返す
+    pub fn get_max(&self) -> [f32; 3] {
+        self.max
+    }
+}
+
+impl std::fmt::Display for Extents {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        AXES.iter().enumerate().try_for_each(|(axis, code)| {
+            writeln!(f, "{} : {:.3} .. {:.3}", code, self.min[axis], self.max[axis])
+        })
+    }
+}
+
+/// 状態の座標を配列で返す
+pub fn position(state: &State) -> [f32; 3] {
+    [state.get_x(), state.get_y(), state.get_z()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn extents_of_program() {
+        let program = Program::new("G00X10.0Y5.0Z50.0\nG01Z-2.0\nG03X-10.0I-10.0\nG00Z50.0").unwrap();
+        let result = Extents::of_program(&program).unwrap();
+        assert_eq!(result.get_min(), [-10.0, 5.0, -2.0]);
+        assert_eq!(result.get_max(), [10.0, 15.0, 50.0]);
+
+        let program = Program::new("G00X10.0Z5.0\nG18G02X0Z15.0R10.0\nG19G03Y5.0Z10.0K-5.0").unwrap();
+        let result = Extents::of_program(&program).unwrap();
+        assert_eq!(result.get_min(), [0.0, -5.0, 5.0]);
+        assert_eq!(result.get_max(), [10.0, 5.0, 15.0]);
+    }
+
+    #[test]
+    fn extents_of_program_without_move() {
+        let program = Program::new("M03\nM05").unwrap();
+        assert_eq!(Extents::of_program(&program), None);
+    }
+
+    #[test]
+    fn extents_display() {
+        let result = Extents { min: [-9.0, 2.0, -8.5], max: [10.0, 2.0, 50.0] };
+        assert_eq!(result.to_string(), "X : -9.000 .. 10.000\nY : 2.000 .. 2.000\nZ : -8.500 .. 50.000\n");
+    }
+
+    #[test]
+    fn extents_translated() {
+        let result = Extents::new([1.0, 2.0, 3.0]).translated([-10.0, 0.0, 5.0]);
+        assert_eq!(result, Extents { min: [-9.0, 2.0, 8.0], max: [-9.0, 2.0, 8.0] });
+    }
+}
@@ -0,0 +1,259 @@
+use crate::analysis::extents::{Extents, AXES};
+use crate::error::Error;
+use crate::structs::machine_profile::{MachineProfile, OffsetTable};
+use crate::structs::program::Program;
+use serde::{Deserialize, Serialize};
+
+/// 軸の移動範囲の構造体
+#[derive(Debug, PartialEq, Clone, Copy, Serialize, Deserialize)]
+pub struct AxisRange {
+    min: f32,
+    max: f32,
+}
+
+impl AxisRange {
+    /// 最小値が最大値以下であるかを検証し、インスタンス化
+    pub fn new(min: f32, max: f32) -> Result<Self, Error> {
+        if min <= max {
+            Ok(Self { min, max })
+        } else {
+            Err(Error::InvalidStateSetting)
+        }
+    }
+
+    /// 値が範囲内であるかの真偽値を返す
+    pub fn contains(&self, value: f32) -> bool {
+        (self.min..=self.max).contains(&value)
+    }
+
+    /// 最小値を返す
+    pub fn get_min(&self) -> f32 {
+        self.min
+    }
+
+    /// 最大値を返す
+    pub fn get_max(&self) -> f32 {
+        self.max
+    }
+}
+
+/// 機械のストローク範囲（ソフトリミット）の設定
+/// 範囲は機械座標で指定し、プログラムの座標はワーク座標系と工具長補正の補正量を加えて比較する
+#[derive(Debug, Default, PartialEq, Clone, Serialize, Deserialize)]
+pub struct Envelope {
+    x: Option<AxisRange>,
+    y: Option<AxisRange>,
+    z: Option<AxisRange>,
+    #[serde(default)]
+    offsets: OffsetTable,
+}
+
+impl Envelope {
+    /// 各軸の範囲からインスタンス化
+    /// 範囲を指定しない軸は検証しない
+    pub fn new(x: Option<AxisRange>, y: Option<AxisRange>, z: Option<AxisRange>) -> Self {
+        Self { x, y, z, offsets: OffsetTable::default() }
+    }
+
+    /// 機械の設定の各軸の範囲と補正量からインスタンス化
+    /// 最小値と最大値の両方を指定した軸のみ検証する
+    pub fn from_profile(profile: &MachineProfile) -> Result<Self, Error> {
+        let [x, y, z] = profile.get_axes().to_array().map(|axis| match (axis.get_min(), axis.get_max()) {
+            (Some(min), Some(max)) => AxisRange::new(min, max).map(Some),
+            _ => Ok(None),
+        });
+        let mut envelope = Self::new(x?, y?, z?);
+        envelope.offsets_set(profile.get_offsets().clone());
+        Ok(envelope)
+    }
+
+    /// 補正量を設定
+    pub fn offsets_set(&mut self, offsets: OffsetTable) {
+        self.offsets = offsets;
+    }
+
+    /// 範囲を指定した軸があるかの真偽値を返す
+    pub fn is_limited(&self) -> bool {
+        self.get_ranges().iter().any(|range| range.is_some())
+    }
+
+    /// 各軸の範囲を返す
+    pub fn get_ranges(&self) -> [Option<AxisRange>; 3] {
+        [self.x, self.y, self.z]
+    }
+
+    /// プログラムの全移動を検証し、最初の違反と全体の範囲を返す
+    /// 円弧は通過する象限点も検証する
+    pub fn check(&self, program: &Program) -> LimitReport {
+        let mut violation = None;
+        let mut extents: Option<Extents> = None;
+        program.moves().for_each(|(start, block)| {
+            let offset = self.offsets.offset(&block.get_state());
+            let current = Extents::of_move(&start, &block.get_state()).translated(offset);
+            if violation.is_none() {
+                violation = self.violation(&current, block.get_line_number());
+            }
+            match extents.as_mut() {
+                Some(extents) => extents.include(&current),
+                None => extents = Some(current),
+            }
+        });
+
+        LimitReport { violation, extents }
+    }
+
+    /// 範囲外となる最初の軸を返す
+    fn violation(&self, extents: &Extents, line_number: usize) -> Option<Violation> {
+        self.get_ranges().iter().enumerate().find_map(|(axis, range)| {
+            let range = (*range)?;
+            [extents.get_min()[axis], extents.get_max()[axis]].into_iter()
+                .find(|&value| !range.contains(value))
+                .map(|value| Violation { line_number, axis: AXES[axis], value, range })
+        })
+    }
+}
+
+/// ストローク範囲外となった移動
+#[derive(Debug, PartialEq, Clone, Copy, Serialize, Deserialize)]
+pub struct Violation {
+    line_number: usize,
+    axis: char,
+    /// 範囲外となった機械座標
+    value: f32,
+    range: AxisRange,
+}
+
+impl Violation {
+    /// 行番号を返す
+    pub fn get_line_number(&self) -> usize {
+        self.line_number
+    }
+
+    /// 軸のアドレスを返す
+    pub fn get_axis(&self) -> char {
+        self.axis
+    }
+
+    /// 範囲外となった機械座標を返す
+    pub fn get_value(&self) -> f32 {
+        self.value
+    }
+
+    /// 軸の移動範囲を返す
+    pub fn get_range(&self) -> AxisRange {
+        self.range
+    }
+}
+
+impl std::fmt::Display for Violation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Line {} : {}{:.3} is out of range [{:.3}, {:.3}]",
+            self.line_number, self.axis, self.value, self.range.min, self.range.max
+        )
+    }
+}
+
+/// ストローク範囲の検証結果
+#[derive(Debug, PartialEq, Clone, Copy, Serialize, Deserialize)]
+pub struct LimitReport {
+    violation: Option<Violation>,
+    /// プログラム全体の機械座標の範囲
+    extents: Option<Extents>,
+}
+
+impl LimitReport {
+    /// 最初の違反を返す
+    pub fn get_violation(&self) -> Option<Violation> {
+        self.violation
+    }
+
+    /// プログラム全体の機械座標の範囲を返す
+    pub fn get_extents(&self) -> Option<Extents> {
+        self.extents
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn envelope() -> Envelope {
+        Envelope::new(
+            Some(AxisRange::new(-100.0, 100.0).unwrap()),
+            Some(AxisRange::new(-30.0, 30.0).unwrap()),
+            None,
+        )
+    }
+
+    #[test]
+    fn axis_range_new_abnormality() {
+        assert_eq!(AxisRange::new(1.0, -1.0), Err(Error::InvalidStateSetting));
+    }
+
+    #[test]
+    fn axis_range_contains() {
+        let range = AxisRange::new(-1.0, 1.0).unwrap();
+        assert!(range.contains(-1.0));
+        assert!(range.contains(1.0));
+        assert!(!range.contains(1.001));
+    }
+
+    #[test]
+    fn envelope_check_normal() {
+        let program = Program::new("G00X90.0Y0.\nG01Z-500.0\nX-90.0").unwrap();
+        let result = envelope().check(&program);
+        assert_eq!(result.get_violation(), None);
+        let extents = result.get_extents().unwrap();
+        assert_eq!(extents.get_min(), [-90.0, 0.0, -500.0]);
+        assert_eq!(extents.get_max(), [90.0, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn envelope_check_arc() {
+        // 始点と終点は範囲内だが、円弧の途中で範囲外となる
+        let program = Program::new("G00X40.0\nG03X-40.0I-40.0\nG00X200.0").unwrap();
+        let result = envelope().check(&program);
+        let violation = result.get_violation().unwrap();
+        assert_eq!(violation.get_line_number(), 2);
+        assert_eq!(violation.get_axis(), 'Y');
+        assert_eq!(violation.get_value(), 40.0);
+        assert_eq!(result.get_extents().unwrap().get_max()[0], 200.0);
+    }
+
+    #[test]
+    fn envelope_check_offsets() {
+        let program = Program::new("G55G00X10.0\nG43H1Z-20.0").unwrap();
+        let mut offsets = OffsetTable::default();
+        offsets.work_offset_set("G55", [95.0, 0.0, -300.0]);
+        offsets.tool_length_set(1, 100.0);
+        let mut envelope = envelope();
+        envelope.offsets_set(offsets);
+        let result = envelope.check(&program);
+        assert_eq!(
+            result.get_violation().unwrap().to_string(),
+            "Line 1 : X105.000 is out of range [-100.000, 100.000]"
+        );
+        assert_eq!(result.get_extents().unwrap().get_min()[2], -300.0);
+        assert_eq!(result.get_extents().unwrap().get_max()[2], -300.0 + 100.0 - 20.0);
+    }
+
+    #[test]
+    fn envelope_from_profile() {
+        let profile = MachineProfile::from_toml(r#"
+            [axes]
+            x = { letter = "X", min = -1800.0, max = 0.0, rapid_rate = 24000.0 }
+            y = { letter = "Y", min = -800.0, rapid_rate = 24000.0 }
+            z = { letter = "Z", min = -600.0, max = 0.0, rapid_rate = 15000.0 }
+        "#).unwrap();
+        let result = Envelope::from_profile(&profile).unwrap();
+        assert!(result.is_limited());
+        assert_eq!(result.get_ranges(), [
+            Some(AxisRange::new(-1800.0, 0.0).unwrap()),
+            None,
+            Some(AxisRange::new(-600.0, 0.0).unwrap()),
+        ]);
+        assert!(!Envelope::from_profile(&MachineProfile::default()).unwrap().is_limited());
+    }
+}
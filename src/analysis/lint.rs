@@ -0,0 +1,214 @@
+use crate::structs::line_parser::m_code;
+use crate::structs::motion_mode::Motion;
+use crate::structs::program::{ExecutedBlock, Program};
+use serde::{Deserialize, Serialize};
+
+/// 検証する規則
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash, Serialize, Deserialize)]
+pub enum Rule {
+    /// 主軸停止中の切削送り
+    SpindleOff,
+    /// 送り速度を指令していない切削送り
+    NoFeed,
+    /// クーラントが必要な工具でのクーラント停止中の切削送り
+    CoolantOff,
+    /// 機械の最高回転数を超える主軸回転数
+    SpindleSpeedOverLimit,
+    /// 主軸回転数を指令していない主軸の起動
+    SpindleStartWithoutSpeed,
+    /// 機械に定義されていないMコード
+    UnknownMCode,
+}
+
+impl Rule {
+    const ALL: [Rule; 6] = [
+        Rule::SpindleOff,
+        Rule::NoFeed,
+        Rule::CoolantOff,
+        Rule::SpindleSpeedOverLimit,
+        Rule::SpindleStartWithoutSpeed,
+        Rule::UnknownMCode,
+    ];
+}
+
+/// 規則に違反したブロック
+#[derive(Debug, PartialEq, Clone, Copy, Serialize, Deserialize)]
+pub struct Diagnostic {
+    line_number: usize,
+    rule: Rule,
+}
+
+impl Diagnostic {
+    /// 行番号を返す
+    pub fn get_line_number(&self) -> usize {
+        self.line_number
+    }
+
+    /// 違反した規則を返す
+    pub fn get_rule(&self) -> Rule {
+        self.rule
+    }
+}
+
+impl std::fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let message = match self.rule {
+            Rule::SpindleOff => "cutting move with the spindle off",
+            Rule::NoFeed => "cutting move without a feed rate",
+            Rule::CoolantOff => "cutting move with the coolant off",
+            Rule::SpindleSpeedOverLimit => "spindle speed exceeds the machine maximum",
+            Rule::SpindleStartWithoutSpeed => "spindle started without a spindle speed",
+            Rule::UnknownMCode => "M code not defined for the machine",
+        };
+        write!(f, "Line {} : {}", self.line_number, message)
+    }
+}
+
+/// 検証の設定
+#[derive(Debug, Default, PartialEq, Clone, Serialize, Deserialize)]
+pub struct LintConfig {
+    /// 機械の最高回転数
+    max_spindle_speed: Option<f32>,
+    /// クーラントが必要な工具番号
+    #[serde(default)]
+    coolant_tools: Vec<u32>,
+    /// 機械に定義されたMコード、Noneの場合は検証しない
+    #[serde(skip)]
+    m_codes: Option<Vec<String>>,
+}
+
+impl LintConfig {
+    /// インスタンス化
+    pub fn new(max_spindle_speed: Option<f32>, coolant_tools: Vec<u32>) -> Self {
+        Self { max_spindle_speed, coolant_tools, m_codes: None }
+    }
+
+    /// 機械の最高回転数を返す
+    pub fn get_max_spindle_speed(&self) -> Option<f32> {
+        self.max_spindle_speed
+    }
+
+    /// 機械の最高回転数を設定
+    pub fn max_spindle_speed_set(&mut self, max_spindle_speed: Option<f32>) {
+        self.max_spindle_speed = max_spindle_speed;
+    }
+
+    /// 機械に定義されたMコードを設定
+    pub fn m_codes_set(&mut self, m_codes: Option<Vec<String>>) {
+        self.m_codes = m_codes;
+    }
+
+    /// プログラムの全ブロックを検証し、違反を返す
+    /// 切削送りの規則は違反が続く間は最初のブロックのみ報告する
+    pub fn check(&self, program: &Program) -> Vec<Diagnostic> {
+        let mut diagnostics = Vec::new();
+        let mut continuing = Vec::new();
+        program.get_blocks().iter().for_each(|block| {
+            let violations = self.violations(block);
+            violations.iter()
+                .filter(|rule| !continuing.contains(*rule))
+                .for_each(|&rule| diagnostics.push(Diagnostic { line_number: block.get_line_number(), rule }));
+            // 切削送りでないブロックでは継続中の違反を保持する
+            if is_cutting(block) {
+                continuing = violations;
+            }
+        });
+        diagnostics
+    }
+
+    /// 1ブロックで違反した規則を返す
+    fn violations(&self, block: &ExecutedBlock) -> Vec<Rule> {
+        let state = block.get_state();
+        Rule::ALL.into_iter().filter(|rule| match rule {
+            Rule::SpindleOff => is_cutting(block) && !state.get_spindle().is_running(),
+            Rule::NoFeed => is_cutting(block) && state.get_feed() <= 0.0,
+            Rule::CoolantOff => {
+                is_cutting(block)
+                    && self.coolant_tools.contains(&state.get_active_tool())
+                    && !state.get_coolant().is_on()
+            },
+            Rule::SpindleSpeedOverLimit => match self.max_spindle_speed {
+                Some(max) => block.get_block().words_of('S')
+                    .any(|word| word.to_f32().is_ok_and(|speed| speed > max)),
+                None => false,
+            },
+            Rule::SpindleStartWithoutSpeed => starts_spindle(block) && state.get_spindle_speed() <= 0.0,
+            Rule::UnknownMCode => match &self.m_codes {
+                Some(m_codes) => block.get_block().words_of('M')
+                    .any(|word| !m_codes.contains(&m_code(word.get_value()))),
+                None => false,
+            },
+        }).collect()
+    }
+}
+
+/// 切削送りのブロックであるかの真偽値を返す
+fn is_cutting(block: &ExecutedBlock) -> bool {
+    block.get_moved() && block.get_state().get_motion() != Motion::Rapid
+}
+
+/// 主軸を起動するブロックであるかの真偽値を返す
+fn starts_spindle(block: &ExecutedBlock) -> bool {
+    block.get_block().words_of('M')
+        .any(|word| matches!(m_code(word.get_value()).as_str(), "M03" | "M04"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::structs::machine_profile::MachineProfile;
+
+    fn check(config: &LintConfig, source: &str) -> Vec<(usize, Rule)> {
+        let program = Program::new(source).unwrap();
+        config.check(&program).iter()
+            .map(|diagnostic| (diagnostic.get_line_number(), diagnostic.get_rule()))
+            .collect()
+    }
+
+    #[test]
+    fn lint_config_check_normal() {
+        let source = "T1M06\nS1000M03\nM08\nG00X0.Y0.Z5.0\nG01Z-1.0F100.\nX10.0\nG00Z5.0\nM05M09";
+        let config = LintConfig::new(Some(8000.0), vec![1]);
+        assert_eq!(check(&config, source), []);
+    }
+
+    #[test]
+    fn lint_config_check_spindle_and_feed() {
+        let source = "G00Z5.0\nG01Z-1.0\nX10.0\nY10.0F100.\nS500M03\nX0.\nM05\nG02X10.0R5.0";
+        let result = check(&LintConfig::default(), source);
+        assert_eq!(result, [
+            (2, Rule::SpindleOff),
+            (2, Rule::NoFeed),
+            (8, Rule::SpindleOff),
+        ]);
+    }
+
+    #[test]
+    fn lint_config_check_coolant() {
+        let source = "T2S1000M03\nG01X1.0F100.\nX2.0\nM08\nX3.0\nM09\nG00Z10.0\nG01X4.0";
+        let result = check(&LintConfig::new(None, vec![2]), source);
+        assert_eq!(result, [(2, Rule::CoolantOff), (8, Rule::CoolantOff)]);
+    }
+
+    #[test]
+    fn lint_config_check_spindle_speed() {
+        let source = "M03\nS12000\nS6000M04";
+        let result = check(&LintConfig::new(Some(10000.0), vec![]), source);
+        assert_eq!(result, [(1, Rule::SpindleStartWithoutSpeed), (2, Rule::SpindleSpeedOverLimit)]);
+    }
+
+    #[test]
+    fn lint_config_check_m_code() {
+        let source = "M03S1000\nM102\nM08M51\nM5";
+        let mut config = LintConfig::default();
+        assert_eq!(check(&config, source), []);
+        config.m_codes_set(Some(MachineProfile::default().get_m_codes()));
+        assert_eq!(check(&config, source), [(2, Rule::UnknownMCode), (3, Rule::UnknownMCode)]);
+    }
+
+    #[test]
+    fn diagnostic_display() {
+        let diagnostic = Diagnostic { line_number: 3, rule: Rule::NoFeed };
+        assert_eq!(diagnostic.to_string(), "Line 3 : cutting move without a feed rate");
+    }
+}
@@ -0,0 +1,266 @@
+use crate::analysis::statistics::Statistics;
+use crate::error::Error;
+use crate::restart::Restart;
+use crate::structs::block::Block;
+use crate::structs::interpreter::Interpreter;
+use crate::structs::line_parser::{m_code, Line};
+use crate::structs::program::{ExecutedBlock, Program};
+use crate::structs::state::State;
+
+/// 工具交換、任意停止、コメントの行で区切ったプログラムの1つの工程
+#[derive(Debug, PartialEq, Clone)]
+pub struct Operation {
+    /// 工程を始めたコメントのみの行、または工具交換の行のコメント
+    name: Option<String>,
+    /// 工程の最初の行番号（メインプログラムの行）
+    first_line: usize,
+    /// 工程の最後の行番号（メインプログラムの行）
+    last_line: usize,
+    /// 移動に使用した工具番号、移動がない場合は工程の最後に装着していた工具番号
+    tool: u32,
+    /// 工程を始める直前の状態
+    start: State,
+    /// 工程の移動の統計
+    statistics: Statistics,
+    /// プログラムの終了（M02、M30）を含むか
+    ends: bool,
+}
+
+impl Operation {
+    /// 工程の最初のブロックと直前の状態からインスタンス化
+    fn new(block: &ExecutedBlock, start: State) -> Self {
+        Self {
+            name: None,
+            first_line: block.get_line_number(),
+            last_line: block.get_line_number(),
+            tool: start.get_active_tool(),
+            start,
+            statistics: Statistics::default(),
+            ends: false,
+        }
+    }
+
+    /// プログラムを実行したブロックの順に工程に分ける
+    /// 工具交換（M06）とコメントのみの行は新しい工程を始め、任意停止（M01）の行は工程を終える
+    /// 移動のない工程は次の工程の区切りと合わせて1つの工程とする
+    /// テープの区切り（%）とプログラム番号の行は工程に含めず、
+    /// サブプログラムのブロックは工程を区切らずに呼び出したメインプログラムの行の工程に含める
+    pub fn of_program(program: &Program) -> Vec<Self> {
+        let mut result: Vec<Self> = Vec::new();
+        let mut previous = program.get_start();
+        let mut closed = false;
+        program.get_blocks().iter().for_each(|block| {
+            let start = previous;
+            previous = block.get_state();
+            let main = block.get_depth() == 0;
+            if main && is_header(block.get_source(), block.get_block()) {
+                return;
+            }
+            let words = block.get_block().get_words();
+            let opens = main && (block.get_state().get_tool_change()
+                || (words.is_empty() && !block.get_block().get_comments().is_empty()));
+            let split = match result.last() {
+                Some(operation) => operation.moved() && (closed || opens),
+                None => true,
+            };
+            if split {
+                result.push(Self::new(block, start));
+            }
+            if let Some(operation) = result.last_mut() {
+                operation.push(start, block);
+            }
+            if main {
+                closed = block.get_block().words_of('M').any(|word| m_code(word.get_value()) == "M01");
+            }
+        });
+        result
+    }
+
+    /// 工程に1ブロックを追加
+    fn push(&mut self, start: State, block: &ExecutedBlock) {
+        let state = block.get_state();
+        if block.get_depth() == 0 {
+            self.first_line = self.first_line.min(block.get_line_number());
+            self.last_line = self.last_line.max(block.get_line_number());
+        }
+        let named = state.get_tool_change() || block.get_block().get_words().is_empty();
+        if self.name.is_none() && named {
+            self.name = block.get_block().get_comments().first().map(|comment| comment.trim().to_string());
+        }
+        if !self.moved() {
+            self.tool = state.get_active_tool();
+        }
+        if block.get_moved() {
+            self.statistics.push(&start, &state);
+        }
+        self.ends |= block.get_block().words_of('M').any(|word| matches!(m_code(word.get_value()).as_str(), "M02" | "M30"));
+    }
+
+    /// 工程に移動があるかの真偽値を返す
+    fn moved(&self) -> bool {
+        !self.statistics.get_tools().is_empty()
+    }
+
+    /// 工程の名前を返す
+    pub fn get_name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    /// 工程の最初の行番号を返す
+    pub fn get_first_line(&self) -> usize {
+        self.first_line
+    }
+
+    /// 工程の最後の行番号を返す
+    pub fn get_last_line(&self) -> usize {
+        self.last_line
+    }
+
+    /// 工具番号を返す
+    pub fn get_tool(&self) -> u32 {
+        self.tool
+    }
+
+    /// 工程を始める直前の状態を返す
+    pub fn get_start(&self) -> State {
+        self.start
+    }
+
+    /// 工程の移動の統計を返す
+    pub fn get_statistics(&self) -> &Statistics {
+        &self.statistics
+    }
+
+    /// 工程だけを実行するプログラムを返す
+    /// 工程の最初の行から再開する前置き、工程の行の記述、プログラムを終了する後置きを続ける
+    /// 工程にプログラムの終了を含む場合は後置きを付けない
+    /// プログラムの先頭のテープの区切り（%）とプログラム番号は前置きの前に出力し、テープの区切りで終える
+    pub fn extract(&self, source: &str, interpreter: Interpreter) -> Result<String, Error> {
+        let restart = Restart::new(source, self.first_line, interpreter)?;
+        let header: Vec<String> = source.lines()
+            .take_while(|line| Block::new(&Line::new(line.to_string())).is_ok_and(|block| is_header(line, &block)))
+            .map(|line| line.to_string())
+            .collect();
+        let tape = header.iter().any(|line| line.trim() == "%");
+        let mut lines = header;
+        lines.extend(restart.preamble()?);
+        lines.extend(source.lines()
+            .skip(self.first_line - 1)
+            .take(self.last_line - self.first_line + 1)
+            .map(|line| line.to_string()));
+        if !self.ends {
+            lines.extend(restart.postamble()?);
+        }
+        if tape {
+            lines.push("%".to_string());
+        }
+        Ok(lines.iter().map(|line| format!("{}\n", line)).collect())
+    }
+}
+
+/// テープの区切り（%）、またはプログラム番号（O）のみの行であるかの真偽値を返す
+fn is_header(source: &str, block: &Block) -> bool {
+    let words = block.get_words();
+    source.trim() == "%" || (!words.is_empty() && words.iter().all(|word| word.get_code() == 'O'))
+}
+
+impl std::fmt::Display for Operation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.name {
+            Some(name) => writeln!(f, "Lines {} .. {} : T{} {}", self.first_line, self.last_line, self.tool, name)?,
+            None => writeln!(f, "Lines {} .. {} : T{}", self.first_line, self.last_line, self.tool)?,
+        }
+        write!(f, "{}", self.statistics)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::structs::dialect::DialectKind;
+    use crate::structs::machine_profile::MachineProfile;
+
+    const SOURCE: &str = "%\n\
+        O1000\n\
+        (FACE)\n\
+        T1M06\n\
+        G90G54G00X0Y0S1000M03\n\
+        G43Z10.H1\n\
+        G01Z-1.F200.\n\
+        X100.\n\
+        G00Z10.\n\
+        M01\n\
+        T2M06(DRILL D5)\n\
+        G43Z10.H2S2000\n\
+        G01Z-5.F100.\n\
+        G00Z10.\n\
+        (CHAMFER)\n\
+        G01Z-0.5F80.\n\
+        G00Z10.\n\
+        M30\n\
+        %\n";
+
+    #[test]
+    fn operation_of_program() {
+        let result = Operation::of_program(&Program::new(SOURCE).unwrap());
+        let summary: Vec<(Option<&str>, usize, usize, u32, usize)> = result.iter()
+            .map(|operation| (
+                operation.get_name(),
+                operation.get_first_line(),
+                operation.get_last_line(),
+                operation.get_tool(),
+                operation.get_statistics().get_feed_moves(),
+            ))
+            .collect();
+        assert_eq!(summary, [
+            (Some("FACE"), 3, 10, 1, 2),
+            (Some("DRILL D5"), 11, 14, 2, 1),
+            (Some("CHAMFER"), 15, 18, 2, 1),
+        ]);
+        let start = result[1].get_start();
+        assert_eq!((start.get_active_tool(), start.get_z(), start.get_feed()), (1, 10.0, 200.0));
+        assert_eq!(result[2].get_statistics().get_feeds(), [80.0]);
+    }
+
+    #[test]
+    fn operation_extract() {
+        let operations = Operation::of_program(&Program::new(SOURCE).unwrap());
+        let result = operations[1].extract(SOURCE, Interpreter::default()).unwrap();
+        assert_eq!(
+            result,
+            "%\nO1000\n(RESTART AT LINE 11)\nG40G49G80G17\nG91G28Z0.\nT1M06\nG90G00G54X100.Y0.\nS1000M03\nG43Z10.H1\nG00F200.\n\
+             T2M06(DRILL D5)\nG43Z10.H2S2000\nG01Z-5.F100.\nG00Z10.\n\
+             M09\nM05\nG91G28Z0.\nM30\n%\n"
+        );
+        // 抽出したプログラムは単独で実行でき、前置きの退避と工具交換の後に工程の工具で加工する
+        let program = Program::new(&result).unwrap();
+        let tools: Vec<u32> = Operation::of_program(&program).iter().map(|operation| operation.get_tool()).collect();
+        assert_eq!(tools, [0, 1, 2]);
+
+        let last = operations[2].extract(SOURCE, Interpreter::default()).unwrap();
+        assert!(last.ends_with("G00Z10.\nM30\n%\n"));
+        let first = operations[0].extract(SOURCE, Interpreter::default()).unwrap();
+        assert!(first.starts_with("%\nO1000\n(RESTART AT LINE 3)\n"));
+    }
+
+    #[test]
+    fn operation_of_program_subprogram() {
+        // サブプログラムの行は工程の範囲に含めず、呼び出した行の工程の移動とする
+        let source = "o<hole> sub\ng1 z-5. f100.\ng0 z5.\no<hole> endsub\n\
+            t1 m6 (DRILL)\ng0 x10. z5.\no<hole> call\n\
+            t2 m6 (MILL)\ng0 x20.\no<hole> call\nm2";
+        let mut profile = MachineProfile::default();
+        profile.dialect_set(DialectKind::Linuxcnc);
+        let program = Program::with_interpreter(source, Interpreter::new(profile)).unwrap();
+        let operations = Operation::of_program(&program);
+        let result: Vec<(Option<&str>, usize, usize, usize)> = operations.iter()
+            .map(|operation| (
+                operation.get_name(),
+                operation.get_first_line(),
+                operation.get_last_line(),
+                operation.get_statistics().get_feed_moves(),
+            ))
+            .collect();
+        assert_eq!(result, [(Some("DRILL"), 1, 7, 1), (Some("MILL"), 8, 11, 1)]);
+    }
+}
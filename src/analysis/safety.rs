@@ -0,0 +1,254 @@
+use crate::analysis::extents::position;
+use crate::structs::motion_mode::Motion;
+use crate::structs::program::Program;
+use crate::structs::state::State;
+use serde::{Deserialize, Serialize};
+
+/// 素材の直方体をわずかに縮めて判定し、面に接するだけの移動は干渉としない
+const TOUCH_TOLERANCE: f32 = 1.0e-4;
+
+/// 素材の直方体（ワーク座標）
+#[derive(Debug, PartialEq, Clone, Copy, Serialize, Deserialize)]
+pub struct StockBox {
+    min: [f32; 3],
+    max: [f32; 3],
+}
+
+impl StockBox {
+    /// 対角の2点からインスタンス化
+    pub fn new(a: [f32; 3], b: [f32; 3]) -> Self {
+        let mut min = a;
+        let mut max = b;
+        (0..3).for_each(|axis| {
+            min[axis] = a[axis].min(b[axis]);
+            max[axis] = a[axis].max(b[axis]);
+        });
+        Self { min, max }
+    }
+
+    /// 線分が素材の内部を通過するかの真偽値を返す
+    pub fn intersects(&self, start: [f32; 3], end: [f32; 3]) -> bool {
+        let mut entry: f32 = 0.0;
+        let mut exit: f32 = 1.0;
+        (0..3).all(|axis| {
+            let min = self.min[axis] + TOUCH_TOLERANCE;
+            let max = self.max[axis] - TOUCH_TOLERANCE;
+            let delta = end[axis] - start[axis];
+            if delta.abs() < f32::EPSILON {
+                return (min..=max).contains(&start[axis]);
+            }
+            let a = (min - start[axis]) / delta;
+            let b = (max - start[axis]) / delta;
+            entry = entry.max(a.min(b));
+            exit = exit.min(a.max(b));
+            entry <= exit
+        })
+    }
+}
+
+/// 安全性の検証の設定
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SafetyConfig {
+    /// 早送りで移動してよい安全高さ（ワーク座標のZ）
+    clearance: f32,
+    stock: Option<StockBox>,
+    /// 各軸の早送り速度、ドッグレッグ（各軸が独立して移動する）経路の計算に使用
+    rapid_rates: [f32; 3],
+}
+
+impl Default for SafetyConfig {
+    fn default() -> Self {
+        Self { clearance: 0.0, stock: None, rapid_rates: [1.0; 3] }
+    }
+}
+
+impl SafetyConfig {
+    /// 安全高さと素材からインスタンス化
+    pub fn new(clearance: f32, stock: Option<StockBox>) -> Self {
+        Self { clearance, stock, ..Default::default() }
+    }
+
+    /// 各軸の早送り速度を設定
+    pub fn rapid_rates_set(&mut self, rapid_rates: [f32; 3]) {
+        self.rapid_rates = rapid_rates;
+    }
+
+    /// プログラムの全ての早送りを検証し、危険な移動を返す
+    pub fn check(&self, program: &Program) -> Vec<Finding> {
+        program.moves()
+            .filter(|(_, block)| block.get_state().get_motion() == Motion::Rapid)
+            .flat_map(|(start, block)| {
+                self.hazards(&start, &block.get_state()).into_iter()
+                    .map(|hazard| Finding { line_number: block.get_line_number(), hazard })
+            })
+            .collect()
+    }
+
+    /// 1回の早送りの危険を返す
+    /// 固定サイクル中はR点までの早送りを許容し、R点が安全高さより低い場合はR点を安全高さとする
+    fn hazards(&self, start: &State, end: &State) -> Vec<Hazard> {
+        let mut hazards = Vec::new();
+        let clearance = match end.get_cycle() {
+            Some(cycle) => self.clearance.min(cycle.get_r()),
+            None => self.clearance,
+        };
+        let moves_xy = (start.get_x(), start.get_y()) != (end.get_x(), end.get_y());
+        if moves_xy && start.get_z().min(end.get_z()) < clearance {
+            hazards.push(Hazard::RapidBelowClearance);
+        }
+        if end.get_z() < start.get_z() && end.get_z() < clearance {
+            hazards.push(Hazard::RapidPlunge);
+        }
+        // 切削後のZ方向のみの退避は素材内から始まるため除外する
+        let retract = !moves_xy && end.get_z() > start.get_z();
+        if let Some(stock) = self.stock.filter(|_| !retract) {
+            let path = dogleg(position(start), position(end), self.rapid_rates);
+            if path.windows(2).any(|pair| stock.intersects(pair[0], pair[1])) {
+                hazards.push(Hazard::StockCollision);
+            }
+        }
+        hazards
+    }
+}
+
+/// 危険の種類
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Serialize, Deserialize)]
+pub enum Hazard {
+    /// 安全高さより下でのXYの早送り
+    RapidBelowClearance,
+    /// 安全高さより下へのZの早送り
+    RapidPlunge,
+    /// ドッグレッグ経路が素材を通過する早送り
+    StockCollision,
+}
+
+/// 危険と判定された移動
+#[derive(Debug, PartialEq, Clone, Copy, Serialize, Deserialize)]
+pub struct Finding {
+    line_number: usize,
+    hazard: Hazard,
+}
+
+impl Finding {
+    /// 行番号を返す
+    pub fn get_line_number(&self) -> usize {
+        self.line_number
+    }
+
+    /// 危険の種類を返す
+    pub fn get_hazard(&self) -> Hazard {
+        self.hazard
+    }
+}
+
+impl std::fmt::Display for Finding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let message = match self.hazard {
+            Hazard::RapidBelowClearance => "rapid move below clearance height",
+            Hazard::RapidPlunge => "rapid plunge below clearance height",
+            Hazard::StockCollision => "rapid path crosses the stock",
+        };
+        write!(f, "Line {} : {}", self.line_number, message)
+    }
+}
+
+/// 各軸が早送り速度で独立して移動する場合の経路の頂点を返す
+/// 移動量の少ない軸から順に移動を終える
+pub fn dogleg(start: [f32; 3], end: [f32; 3], rates: [f32; 3]) -> Vec<[f32; 3]> {
+    let times: Vec<f32> = (0..3)
+        .map(|axis| (end[axis] - start[axis]).abs() / rates[axis])
+        .collect();
+    let total = times.iter().cloned().fold(0.0, f32::max);
+    let mut breaks: Vec<f32> = times.iter().cloned().filter(|&time| time > 0.0 && time < total).collect();
+    breaks.sort_by(|a, b| a.total_cmp(b));
+    breaks.dedup();
+
+    let mut path = vec![start];
+    breaks.iter().for_each(|&time| {
+        let mut point = start;
+        (0..3).for_each(|axis| {
+            let delta = end[axis] - start[axis];
+            point[axis] += delta.signum() * (rates[axis] * time).min(delta.abs());
+        });
+        path.push(point);
+    });
+    path.push(end);
+    path
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> SafetyConfig {
+        SafetyConfig::new(
+            5.0,
+            Some(StockBox::new([-50.0, -50.0, -20.0], [50.0, 50.0, 0.0])),
+        )
+    }
+
+    #[test]
+    fn stock_box_intersects() {
+        let stock = StockBox::new([0.0, 0.0, 0.0], [10.0, 10.0, 10.0]);
+        assert!(stock.intersects([-5.0, 5.0, 5.0], [15.0, 5.0, 5.0]));
+        assert!(!stock.intersects([-5.0, 5.0, 15.0], [15.0, 5.0, 15.0]));
+        // 上面に沿った移動は干渉としない
+        assert!(!stock.intersects([-5.0, 5.0, 10.0], [15.0, 5.0, 10.0]));
+        assert!(!stock.intersects([-5.0, -5.0, 5.0], [-1.0, 20.0, 5.0]));
+    }
+
+    #[test]
+    fn dogleg_path() {
+        let result = dogleg([0.0, 0.0, 0.0], [10.0, 5.0, 0.0], [1.0; 3]);
+        assert_eq!(result, [[0.0, 0.0, 0.0], [5.0, 5.0, 0.0], [10.0, 5.0, 0.0]]);
+
+        let result = dogleg([0.0, 0.0, 10.0], [10.0, 0.0, 0.0], [1.0, 1.0, 2.0]);
+        assert_eq!(result, [[0.0, 0.0, 10.0], [5.0, 0.0, 0.0], [10.0, 0.0, 0.0]]);
+
+        let result = dogleg([0.0, 0.0, 0.0], [0.0, 0.0, 0.0], [1.0; 3]);
+        assert_eq!(result, [[0.0, 0.0, 0.0], [0.0, 0.0, 0.0]]);
+    }
+
+    #[test]
+    fn safety_config_check_clearance() {
+        let program = Program::new("G00Z10.0\nX10.0\nZ2.0\nX20.0\nG01Z-1.0F100.\nG00Z10.0").unwrap();
+        let result: Vec<(usize, Hazard)> = config().check(&program).iter()
+            .map(|finding| (finding.get_line_number(), finding.get_hazard()))
+            .collect();
+        assert_eq!(result, [(3, Hazard::RapidPlunge), (4, Hazard::RapidBelowClearance)]);
+    }
+
+    #[test]
+    fn safety_config_check_canned_cycle() {
+        // R点への早送りと、R点に復帰した後の穴の間の位置決めは危険としない
+        let program = Program::new("G90G00X0Y0Z20.\nG99G81X10.Y10.Z-5.R1.F100.\nX20.\nG98X30.\nG80\nG00X0Y0").unwrap();
+        assert_eq!(config().check(&program), []);
+
+        // 穴あけの取り消し後はR点の高さでの位置決めを危険とする
+        let program = Program::new("G90G00Z20.\nG99G81X10.Y10.Z-5.R1.F100.\nG80\nX0").unwrap();
+        let result: Vec<(usize, Hazard)> = config().check(&program).iter()
+            .map(|finding| (finding.get_line_number(), finding.get_hazard()))
+            .collect();
+        assert_eq!(result, [(4, Hazard::RapidBelowClearance)]);
+    }
+
+    #[test]
+    fn safety_config_check_stock() {
+        // Z10からX-60,Z-10へのドッグレッグは素材の角を通過する
+        let program = Program::new("G00Z50.0\nX10.0Y0.Z10.0\nX-60.0Z-10.0").unwrap();
+        let result: Vec<(usize, Hazard)> = config().check(&program).iter()
+            .map(|finding| (finding.get_line_number(), finding.get_hazard()))
+            .collect();
+        assert_eq!(
+            result,
+            [(3, Hazard::RapidBelowClearance), (3, Hazard::RapidPlunge), (3, Hazard::StockCollision)]
+        );
+    }
+
+    #[test]
+    fn finding_display() {
+        let finding = Finding { line_number: 12, hazard: Hazard::StockCollision };
+        assert_eq!(finding.to_string(), "Line 12 : rapid path crosses the stock");
+    }
+}
@@ -0,0 +1,196 @@
+use crate::analysis::extents::{position, Extents};
+use crate::structs::arc::Arc;
+use crate::structs::motion_mode::Motion;
+use crate::structs::program::Program;
+use crate::structs::state::State;
+use serde::{Deserialize, Serialize};
+
+/// 移動の統計
+#[derive(Debug, Default, PartialEq, Clone, Serialize, Deserialize)]
+pub struct Statistics {
+    extents: Option<Extents>,
+    rapid_distance: f32,
+    feed_distance: f32,
+    rapid_moves: usize,
+    feed_moves: usize,
+    /// 切削送りで使用した送り速度（使用順）
+    feeds: Vec<f32>,
+    /// 移動中に指令されていた主軸回転数（使用順）
+    spindle_speeds: Vec<f32>,
+    /// 移動があった工具番号（使用順）
+    tools: Vec<u32>,
+}
+
+impl Statistics {
+    /// プログラム全体の統計を返す
+    pub fn new(program: &Program) -> Self {
+        let mut statistics = Self::default();
+        program.moves().for_each(|(start, block)| statistics.push(&start, &block.get_state()));
+        statistics
+    }
+
+    /// 工具ごとの統計を工具の使用順に返す
+    pub fn per_tool(program: &Program) -> Vec<(u32, Self)> {
+        let mut result: Vec<(u32, Self)> = Vec::new();
+        program.moves().for_each(|(start, block)| {
+            let end = block.get_state();
+            let index = match result.iter().position(|(tool, _)| *tool == end.get_active_tool()) {
+                Some(index) => index,
+                None => {
+                    result.push((end.get_active_tool(), Self::default()));
+                    result.len() - 1
+                },
+            };
+            result[index].1.push(&start, &end);
+        });
+        result
+    }
+
+    /// 移動前と移動後の状態から1回の移動を集計
+    pub fn push(&mut self, start: &State, end: &State) {
+        let current = Extents::of_move(start, end);
+        match self.extents.as_mut() {
+            Some(extents) => extents.include(&current),
+            None => self.extents = Some(current),
+        }
+
+        let distance = distance(start, end);
+        if end.get_motion() == Motion::Rapid {
+            self.rapid_distance += distance;
+            self.rapid_moves += 1;
+        } else {
+            self.feed_distance += distance;
+            self.feed_moves += 1;
+            push_unique(&mut self.feeds, end.get_feed());
+        }
+        if end.get_spindle_speed() > 0.0 {
+            push_unique(&mut self.spindle_speeds, end.get_spindle_speed());
+        }
+        push_unique(&mut self.tools, end.get_active_tool());
+    }
+
+    /// 各軸の範囲を返す
+    pub fn get_extents(&self) -> Option<Extents> {
+        self.extents
+    }
+
+    /// 早送りの移動距離を返す
+    pub fn get_rapid_distance(&self) -> f32 {
+        self.rapid_distance
+    }
+
+    /// 切削送りの移動距離を返す
+    pub fn get_feed_distance(&self) -> f32 {
+        self.feed_distance
+    }
+
+    /// 早送りの移動回数を返す
+    pub fn get_rapid_moves(&self) -> usize {
+        self.rapid_moves
+    }
+
+    /// 切削送りの移動回数を返す
+    pub fn get_feed_moves(&self) -> usize {
+        self.feed_moves
+    }
+
+    /// 使用した送り速度を返す
+    pub fn get_feeds(&self) -> &[f32] {
+        &self.feeds
+    }
+
+    /// 使用した主軸回転数を返す
+    pub fn get_spindle_speeds(&self) -> &[f32] {
+        &self.spindle_speeds
+    }
+
+    /// 使用した工具番号を返す
+    pub fn get_tools(&self) -> &[u32] {
+        &self.tools
+    }
+}
+
+impl std::fmt::Display for Statistics {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(extents) = self.extents {
+            write!(f, "{}", extents)?;
+        }
+        writeln!(f, "Rapid : {} moves, {:.3}", self.rapid_moves, self.rapid_distance)?;
+        writeln!(f, "Feed : {} moves, {:.3}", self.feed_moves, self.feed_distance)?;
+        writeln!(f, "Feeds : {}", join(&self.feeds))?;
+        writeln!(f, "Spindle speeds : {}", join(&self.spindle_speeds))?;
+        writeln!(f, "Tools : {}", join(&self.tools))
+    }
+}
+
+/// 移動前と移動後の状態から移動距離を返す
+/// 円弧は弧の長さとする
+pub fn distance(start: &State, end: &State) -> f32 {
+    match Arc::new(start, end) {
+        Some(arc) => arc.length(),
+        None => {
+            let (start, end) = (position(start), position(end));
+            (0..3).map(|axis| (end[axis] - start[axis]).powi(2)).sum::<f32>().sqrt()
+        },
+    }
+}
+
+/// 未登録の値のみを追加
+pub fn push_unique<T: PartialEq>(values: &mut Vec<T>, value: T) {
+    if !values.contains(&value) {
+        values.push(value);
+    }
+}
+
+/// 値を空白区切りの文字列にする
+fn join<T: std::fmt::Display>(values: &[T]) -> String {
+    values.iter().map(|value| value.to_string()).collect::<Vec<_>>().join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOURCE: &str = "T1S1000\nG00X30.0Y40.0\nG01Z-5.0F200.\nX0.Y0.F300.\nT2S2000\nG00Z10.0\nX10.0\nG02X-10.0I-10.0F100.";
+
+    fn approx(a: f32, b: f32) -> bool {
+        (a - b).abs() < 1.0e-3
+    }
+
+    #[test]
+    fn statistics_new() {
+        let result = Statistics::new(&Program::new(SOURCE).unwrap());
+        assert_eq!(result.get_rapid_moves(), 3);
+        assert_eq!(result.get_feed_moves(), 3);
+        assert!(approx(result.get_rapid_distance(), 50.0 + 15.0 + 10.0));
+        assert!(approx(result.get_feed_distance(), 5.0 + 50.0 + 10.0 * std::f32::consts::PI));
+        assert_eq!(result.get_feeds(), [200.0, 300.0, 100.0]);
+        assert_eq!(result.get_spindle_speeds(), [1000.0, 2000.0]);
+        assert_eq!(result.get_tools(), [1, 2]);
+        let extents = result.get_extents().unwrap();
+        assert_eq!(extents.get_min(), [-10.0, -10.0, -5.0]);
+        assert_eq!(extents.get_max(), [30.0, 40.0, 10.0]);
+    }
+
+    #[test]
+    fn statistics_per_tool() {
+        let result = Statistics::per_tool(&Program::new(SOURCE).unwrap());
+        assert_eq!(result.len(), 2);
+        assert_eq!(result[0].0, 1);
+        assert_eq!(result[0].1.get_feed_moves(), 2);
+        assert_eq!(result[1].0, 2);
+        assert_eq!(result[1].1.get_rapid_moves(), 2);
+        assert_eq!(result[1].1.get_extents().unwrap().get_max(), [10.0, 0.0, 10.0]);
+    }
+
+    #[test]
+    fn statistics_display() {
+        let result = Statistics::new(&Program::new("T1S500\nG01X3.0Y4.0F100.").unwrap());
+        assert_eq!(
+            result.to_string(),
+            "X : 3.000 .. 3.000\nY : 4.000 .. 4.000\nZ : 0.000 .. 0.000\n\
+             Rapid : 0 moves, 0.000\nFeed : 1 moves, 5.000\n\
+             Feeds : 100\nSpindle speeds : 500\nTools : 1\n"
+        );
+    }
+}
@@ -0,0 +1,291 @@
+use crate::analysis::cycle_time::minutes;
+use crate::analysis::extents::Extents;
+use crate::analysis::statistics::{distance, push_unique};
+use crate::structs::line_parser::g_code;
+use crate::structs::motion_mode::Motion;
+use crate::structs::program::{ExecutedBlock, Program};
+use crate::structs::state::State;
+use serde::{Deserialize, Serialize};
+
+/// 工具ごとの使用状況
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct ToolSummary {
+    tool: u32,
+    /// 工具を選択、交換した行のコメント
+    description: Option<String>,
+    /// 工具交換の後に指令した工具長補正（G43）で、移動に使用した番号（H）
+    length_offsets: Vec<u32>,
+    /// 使用した工具径補正の番号（D）
+    radius_offsets: Vec<u32>,
+    /// 工具を最初に使用した行番号
+    first_line: usize,
+    /// 工具を最後に使用した行番号
+    last_line: usize,
+    /// 切削送りの時間（分）
+    cutting_time: f32,
+    /// 切削送りの送り速度の最小値と最大値
+    feed_range: Option<(f32, f32)>,
+    /// 移動中の主軸回転数の最小値と最大値
+    spindle_range: Option<(f32, f32)>,
+    /// 移動で到達したZの最小値
+    min_z: Option<f32>,
+}
+
+impl ToolSummary {
+    /// 工具番号からインスタンス化
+    pub fn new(tool: u32, line_number: usize) -> Self {
+        Self {
+            tool,
+            description: None,
+            length_offsets: Vec::new(),
+            radius_offsets: Vec::new(),
+            first_line: line_number,
+            last_line: line_number,
+            cutting_time: 0.0,
+            feed_range: None,
+            spindle_range: None,
+            min_z: None,
+        }
+    }
+
+    /// 装着した工具ごとの使用状況を工具の使用順に返す
+    /// ワードのない行は含めず、工具を装着していない間（工具番号0）は移動がある場合のみ含める
+    /// 同じ工具を複数回使用した場合は1つにまとめる
+    /// 工具長補正は前の工具から引き継いだ番号を含めず、工具交換の後にG43を指令してからの番号とする
+    pub fn of_program(program: &Program) -> Vec<Self> {
+        let mut result: Vec<Self> = Vec::new();
+        let mut previous = program.get_start();
+        let mut compensated = false;
+        program.get_blocks().iter().for_each(|block| {
+            let start = previous;
+            previous = block.get_state();
+            if block.get_state().get_tool_change() {
+                compensated = false;
+            }
+            compensated |= block.get_block().words_of('G').any(|word| g_code(word.get_value()) == "G43");
+            let tool = block.get_state().get_active_tool();
+            if block.get_block().get_words().is_empty() || (tool == 0 && !block.get_moved()) {
+                return;
+            }
+            let index = match result.iter().position(|summary| summary.tool == tool) {
+                Some(index) => index,
+                None => {
+                    result.push(Self::new(tool, block.get_line_number()));
+                    result.len() - 1
+                },
+            };
+            result[index].push(block);
+            if block.get_moved() {
+                result[index].push_move(&start, block, compensated);
+            }
+        });
+        result
+    }
+
+    /// 工具を使用した1ブロックの工具径補正の番号と行番号を集計
+    fn push(&mut self, block: &ExecutedBlock) {
+        let state = block.get_state();
+        self.last_line = self.last_line.max(block.get_line_number());
+        let selected = block.get_block().words_of('T').any(|word| word.get_value().parse() == Ok(self.tool));
+        if self.description.is_none() && (selected || state.get_tool_change()) {
+            self.description = block.get_block().get_comments().first().map(|comment| comment.trim().to_string());
+        }
+        block.get_block().words_of('D')
+            .filter_map(|word| word.get_value().parse::<u32>().ok())
+            .filter(|&offset| offset > 0)
+            .for_each(|offset| push_unique(&mut self.radius_offsets, offset));
+    }
+
+    /// 1回の移動の工具長補正の番号、時間、送り速度、主軸回転数、Zの最小値を集計
+    /// 工具長補正の番号は、工具交換の後にG43を指令した移動のみ集計する
+    fn push_move(&mut self, start: &State, block: &ExecutedBlock, compensated: bool) {
+        let end = block.get_state();
+        if compensated && end.get_tool_length_offset() > 0 {
+            push_unique(&mut self.length_offsets, end.get_tool_length_offset());
+        }
+        if end.get_motion() != Motion::Rapid && end.get_feed() > 0.0 {
+            self.cutting_time += distance(start, &end) / end.get_feed();
+            self.feed_range = Some(range(self.feed_range, end.get_feed()));
+        }
+        if end.get_spindle_speed() > 0.0 {
+            self.spindle_range = Some(range(self.spindle_range, end.get_spindle_speed()));
+        }
+        let z = Extents::of_move(start, &end).get_min()[2];
+        self.min_z = Some(self.min_z.map_or(z, |min_z| min_z.min(z)));
+    }
+
+    /// 工具番号を返す
+    pub fn get_tool(&self) -> u32 {
+        self.tool
+    }
+
+    /// 工具を選択、交換した行のコメントを返す
+    pub fn get_description(&self) -> Option<&str> {
+        self.description.as_deref()
+    }
+
+    /// 使用した工具長補正の番号を返す
+    pub fn get_length_offsets(&self) -> &[u32] {
+        &self.length_offsets
+    }
+
+    /// 使用した工具径補正の番号を返す
+    pub fn get_radius_offsets(&self) -> &[u32] {
+        &self.radius_offsets
+    }
+
+    /// 工具を最初に使用した行番号を返す
+    pub fn get_first_line(&self) -> usize {
+        self.first_line
+    }
+
+    /// 工具を最後に使用した行番号を返す
+    pub fn get_last_line(&self) -> usize {
+        self.last_line
+    }
+
+    /// 切削送りの時間（分）を返す
+    pub fn get_cutting_time(&self) -> f32 {
+        self.cutting_time
+    }
+
+    /// 送り速度の最小値と最大値を返す
+    pub fn get_feed_range(&self) -> Option<(f32, f32)> {
+        self.feed_range
+    }
+
+    /// 主軸回転数の最小値と最大値を返す
+    pub fn get_spindle_range(&self) -> Option<(f32, f32)> {
+        self.spindle_range
+    }
+
+    /// 到達したZの最小値を返す、移動がない場合はNone
+    pub fn get_min_z(&self) -> Option<f32> {
+        self.min_z
+    }
+}
+
+impl std::fmt::Display for ToolSummary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.description {
+            Some(description) => writeln!(f, "T{} : {}", self.tool, description)?,
+            None => writeln!(f, "T{}", self.tool)?,
+        }
+        writeln!(f, "Lines : {} .. {}", self.first_line, self.last_line)?;
+        writeln!(f, "H : {}", join(&self.length_offsets))?;
+        writeln!(f, "D : {}", join(&self.radius_offsets))?;
+        writeln!(f, "Cutting time : {}", minutes(self.cutting_time))?;
+        writeln!(f, "Feeds : {}", range_text(self.feed_range))?;
+        writeln!(f, "Spindle speeds : {}", range_text(self.spindle_range))?;
+        match self.min_z {
+            Some(min_z) => writeln!(f, "Min Z : {:.3}", min_z),
+            None => writeln!(f, "Min Z : -"),
+        }
+    }
+}
+
+/// 値を含むように最小値と最大値を広げる
+fn range(range: Option<(f32, f32)>, value: f32) -> (f32, f32) {
+    match range {
+        Some((min, max)) => (min.min(value), max.max(value)),
+        None => (value, value),
+    }
+}
+
+/// 最小値と最大値を文字列にする
+fn range_text(range: Option<(f32, f32)>) -> String {
+    match range {
+        Some((min, max)) => format!("{} .. {}", min, max),
+        None => "-".to_string(),
+    }
+}
+
+/// 値を空白区切りの文字列にする
+fn join(values: &[u32]) -> String {
+    values.iter().map(|value| value.to_string()).collect::<Vec<_>>().join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOURCE: &str = "O1000\n\
+        T1M06(FACE MILL D50)\n\
+        T2\n\
+        G90G54G00X0Y0S1000M03\n\
+        G43Z10.H1\n\
+        G01Z-1.F200.\n\
+        X100.F400.\n\
+        G00Z10.\n\
+        M06\n\
+        G43Z10.H2S3000\n\
+        G41G01X10.D12F100.\n\
+        G02X30.I10.Z-5.\n\
+        G40G00Z10.\n\
+        T1M06\n\
+        G01Z-2.F150.";
+
+    #[test]
+    fn tool_summary_of_program() {
+        let result = ToolSummary::of_program(&Program::new(SOURCE).unwrap());
+        assert_eq!(result.len(), 2);
+
+        let first = &result[0];
+        assert_eq!(first.get_tool(), 1);
+        assert_eq!(first.get_description(), Some("FACE MILL D50"));
+        assert_eq!((first.get_first_line(), first.get_last_line()), (2, 15));
+        // 再び装着した後はG43の指令がないため、T2のH2を含めない
+        assert_eq!(first.get_length_offsets(), [1]);
+        assert_eq!(first.get_radius_offsets(), [] as [u32; 0]);
+        assert_eq!(first.get_feed_range(), Some((150.0, 400.0)));
+        assert_eq!(first.get_spindle_range(), Some((1000.0, 3000.0)));
+        assert_eq!(first.get_min_z(), Some(-2.0));
+        assert!((first.get_cutting_time() - (11.0 / 200.0 + 100.0 / 400.0 + 12.0 / 150.0)).abs() < 1.0e-4);
+
+        let second = &result[1];
+        assert_eq!(second.get_tool(), 2);
+        assert_eq!(second.get_description(), None);
+        assert_eq!((second.get_first_line(), second.get_last_line()), (9, 13));
+        assert_eq!(second.get_length_offsets(), [2]);
+        assert_eq!(second.get_radius_offsets(), [12]);
+        assert_eq!(second.get_feed_range(), Some((100.0, 100.0)));
+        assert_eq!(second.get_spindle_range(), Some((3000.0, 3000.0)));
+        assert_eq!(second.get_min_z(), Some(-5.0));
+    }
+
+    #[test]
+    fn tool_summary_length_offsets() {
+        let datas = [
+            // 工具交換の後、G43の前の移動は前の工具の番号を含めない
+            ("T1M06\nG43Z10.H1\nT2M06\nG00X10.\nG43Z10.H2", vec![vec![1], vec![2]]),
+            ("T1M06\nG43Z10.H1\nT2M06\nG00X10.", vec![vec![1], vec![]]),
+            ("T1M06\nG43Z10.H1\nG49Z20.\nG43Z10.H11", vec![vec![1, 11]]),
+        ];
+        datas.iter().for_each(|(source, expected)| {
+            let result: Vec<Vec<u32>> = ToolSummary::of_program(&Program::new(source).unwrap()).iter()
+                .map(|summary| summary.get_length_offsets().to_vec())
+                .collect();
+            assert_eq!(result, *expected, "{}", source);
+        });
+    }
+
+    #[test]
+    fn tool_summary_canned_cycle() {
+        let source = "T3M06(DRILL D5)\nG90G00X0Y0Z20.S1000M03\nG81X10.Z-5.R2.F100.\nX20.\nG80";
+        let result = ToolSummary::of_program(&Program::new(source).unwrap());
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].get_feed_range(), Some((100.0, 100.0)));
+        assert_eq!(result[0].get_min_z(), Some(-5.0));
+        assert!((result[0].get_cutting_time() - 2.0 * 7.0 / 100.0).abs() < 1.0e-4);
+    }
+
+    #[test]
+    fn tool_summary_display() {
+        let result = ToolSummary::of_program(&Program::new("T5(DRILL)\nS800M03\nG01Z-3.F60.").unwrap());
+        assert_eq!(
+            result[0].to_string(),
+            "T5 : DRILL\nLines : 1 .. 3\nH : \nD : \nCutting time : 0:00:03\n\
+             Feeds : 60 .. 60\nSpindle speeds : 800 .. 800\nMin Z : -3.000\n"
+        );
+    }
+}
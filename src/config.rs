@@ -0,0 +1,136 @@
+use crate::analysis::limits::Envelope;
+use crate::analysis::lint::LintConfig;
+use crate::analysis::safety::SafetyConfig;
+use crate::error::Error;
+use crate::export::nc::NcFormat;
+use crate::structs::machine_profile::MachineProfile;
+use crate::translate::TranslationTable;
+use serde::{Deserialize, Serialize};
+
+/// 機械ごとの設定
+/// TOMLファイルから読み込む
+/// 機械の仕様は[machine]、検証の設定は[safety]と[lint]、変換の対応表は[translation]、出力の書式は[format]に記述する
+#[derive(Debug, Default, PartialEq, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    machine: MachineProfile,
+    safety: Option<SafetyConfig>,
+    lint: LintConfig,
+    translation: TranslationTable,
+    format: NcFormat,
+}
+
+impl Config {
+    /// TOMLの文字列からインスタンス化
+    pub fn from_toml(source: &str) -> Result<Self, Error> {
+        toml::from_str(source).map_err(|e| Error::InvalidConfig(e.to_string()))
+    }
+
+    /// 機械の設定を返す
+    pub fn get_machine(&self) -> &MachineProfile {
+        &self.machine
+    }
+
+    /// 機械の設定の各軸の範囲からストローク範囲を返す
+    /// 範囲を指定した軸がない場合はNoneを返す
+    pub fn get_envelope(&self) -> Result<Option<Envelope>, Error> {
+        let envelope = Envelope::from_profile(&self.machine)?;
+        Ok(envelope.is_limited().then_some(envelope))
+    }
+
+    /// 安全性の検証の設定を、機械の設定の早送り速度を反映して返す
+    pub fn get_safety(&self) -> Option<SafetyConfig> {
+        self.safety.clone().map(|mut safety| {
+            safety.rapid_rates_set(self.machine.get_axes().to_array().map(|axis| axis.get_rapid_rate()));
+            safety
+        })
+    }
+
+    /// 検証の設定を返す
+    /// 最高回転数を指定しない場合は機械の設定の値を使い、Mコードは機械に定義されたものと照合する
+    pub fn get_lint(&self) -> LintConfig {
+        let mut lint = self.lint.clone();
+        if lint.get_max_spindle_speed().is_none() {
+            lint.max_spindle_speed_set(self.machine.get_max_spindle_speed());
+        }
+        lint.m_codes_set(Some(self.machine.get_m_codes()));
+        lint
+    }
+
+    /// 変換の対応表を返す
+    pub fn get_translation(&self) -> &TranslationTable {
+        &self.translation
+    }
+
+    /// NCプログラムの出力の書式を返す
+    pub fn get_format(&self) -> &NcFormat {
+        &self.format
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::export::nc::{Numbering, Trailing};
+
+    #[test]
+    fn config_from_toml_normal() {
+        let source = r#"
+            [machine]
+            name = "VMC-1"
+            max_spindle_speed = 8000.0
+
+            [machine.axes]
+            x = { letter = "X", min = -1800.0, max = 0.0, rapid_rate = 24000.0 }
+            z = { letter = "Z", min = -500.0, max = 0.0, rapid_rate = 15000.0 }
+
+            [machine.offsets.work_offsets]
+            G54 = [-100.0, -200.0, -300.0]
+
+            [safety]
+            clearance = 5.0
+            stock = { min = [-10.0, -10.0, -5.0], max = [10.0, 10.0, 0.0] }
+
+            [lint]
+            coolant_tools = [1, 3]
+
+            [translation.m_codes]
+            M08 = "M51"
+
+            [format]
+            decimal_places = 3
+            trailing = "zeros"
+            numbering = { start = 10, increment = 10 }
+        "#;
+        let result = Config::from_toml(source).unwrap();
+        assert_eq!(result.get_machine().get_name(), "VMC-1");
+        let ranges = result.get_envelope().unwrap().unwrap().get_ranges();
+        assert_eq!(ranges[0].unwrap().get_min(), -1800.0);
+        assert_eq!(ranges[1], None);
+        assert!(result.get_safety().is_some());
+        let mut lint = LintConfig::new(Some(8000.0), vec![1, 3]);
+        lint.m_codes_set(Some(result.get_machine().get_m_codes()));
+        assert_eq!(result.get_lint(), lint);
+        let mut translation = TranslationTable::default();
+        translation.m_code_set("M08", "M51");
+        assert_eq!(result.get_translation(), &translation);
+        let mut format = NcFormat::default();
+        format.decimal_places_set(Some(3), Trailing::Zeros);
+        format.numbering_set(Some(Numbering::new(10, 10, 0)));
+        assert_eq!(result.get_format(), &format);
+    }
+
+    #[test]
+    fn config_from_toml_default() {
+        let result = Config::from_toml("").unwrap();
+        assert_eq!(result, Config::default());
+        assert_eq!(result.get_envelope(), Ok(None));
+        assert_eq!(result.get_lint().get_max_spindle_speed(), Some(12000.0));
+    }
+
+    #[test]
+    fn config_from_toml_abnormality() {
+        let result = Config::from_toml("[machine]\ntool_change_time = \"fast\"");
+        assert!(matches!(result, Err(Error::InvalidConfig(_))));
+    }
+}
@@ -1,13 +1,29 @@
 use thiserror::Error;
 
-#[derive(Debug, Error, PartialEq)]
+#[derive(Debug, Error, PartialEq, Clone)]
 pub enum Error {
     #[error("Invalid State Setting")]
     InvalidStateSetting,
     #[error("Invalid Code : {0}")]
     InvalidCode(char),
+    #[error("Invalid Arc")]
+    InvalidArc,
     #[error("Invalid parser : {0}")]
     InvalidParser(String),
+    #[error("Invalid expression : {0}")]
+    InvalidExpression(String),
+    #[error("Undefined label : {0}")]
+    UndefinedLabel(String),
+    #[error("Execution limit of {0} lines exceeded")]
+    ExecutionLimit(usize),
+    #[error("Receive buffer overflow : {0} bytes")]
+    BufferOverflow(usize),
+    #[error("No response from controller")]
+    NoResponse,
+    #[error("Invalid config : {0}")]
+    InvalidConfig(String),
+    #[error("Line {0} : {1}")]
+    Line(usize, Box<Error>),
     #[error("Parse Int Error : {0}")]
     ParseIntError(#[from] std::num::ParseIntError),
     #[error("Parse Float Error : {0}")]
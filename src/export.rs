@@ -0,0 +1,7 @@
+pub mod dxf;
+pub mod svg;
+pub mod record;
+pub mod csv;
+pub mod json;
+pub mod nc;
+pub mod setup_sheet;
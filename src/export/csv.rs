@@ -0,0 +1,69 @@
+use std::io::{self, Write};
+
+use crate::export::record::Record;
+use crate::structs::program::Program;
+
+/// 実行した全ブロックをCSVとして書き込む
+/// 1行目は列名とする
+pub fn write<W: Write + ?Sized>(writer: &mut W, program: &Program) -> io::Result<()> {
+    writeln!(writer, "{}", Record::HEADER.join(","))?;
+    program.get_blocks().iter().try_for_each(|block| {
+        let record = Record::new(block);
+        writeln!(
+            writer,
+            "{},{},{:.3},{:.3},{:.3},{:.3},{:.3},{:.3},{},{:.3},{:.3},{},{}",
+            record.line_number,
+            field(record.source),
+            record.x,
+            record.y,
+            record.z,
+            record.a,
+            record.b,
+            record.c,
+            field(&record.modal),
+            record.feed,
+            record.spindle_speed,
+            record.tool,
+            record.tool_change,
+        )
+    })
+}
+
+/// カンマ、ダブルクォート、改行を含む場合はダブルクォートで囲む
+fn field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn csv_write() {
+        let program = Program::new("G90G55G00X-1610.0Y137.0S1000M03\nM08").unwrap();
+        let mut output = Vec::new();
+        write(&mut output, &program).unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "line_number,source,x,y,z,a,b,c,modal,feed,spindle_speed,tool,tool_change\n\
+             1,G90G55G00X-1610.0Y137.0S1000M03,-1610.000,137.000,0.000,0.000,0.000,0.000,G00 G17 G90 G21 G40 G49 G80 G55 M03 M09,0.000,1000.000,0,false\n\
+             2,M08,-1610.000,137.000,0.000,0.000,0.000,0.000,G00 G17 G90 G21 G40 G49 G80 G55 M03 M08,0.000,1000.000,0,false\n"
+        );
+    }
+
+    #[test]
+    fn csv_field() {
+        let datas = [
+            ("X1.", "X1."),
+            ("(A,B)", "\"(A,B)\""),
+            ("(\"A\")", "\"(\"\"A\"\")\""),
+        ];
+        datas.iter().for_each(|&(input, expected)| {
+            assert_eq!(field(input), expected);
+        })
+    }
+}
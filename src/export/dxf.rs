@@ -0,0 +1,259 @@
+use std::f32::consts::PI;
+use std::fmt::Write as _;
+use std::io::{self, Write};
+
+use crate::structs::arc::Arc;
+use crate::structs::motion_mode::Motion;
+use crate::structs::plane::Plane;
+use crate::structs::state::State;
+
+/// ヘリカル補間をポリラインに分割する際の1区間の角度（ラジアン）
+const HELICAL_STEP: f32 = PI / 36.0;
+
+/// DXF（R12 ASCII）の出力
+/// 連続した状態の間の移動を図形要素に変換する
+/// 直線移動はLINE、XY平面の円弧はARC、ヘリカル補間とXY平面以外の円弧は3DのPOLYLINEとする
+/// 画層は工具番号と移動モードごとに分ける（例: "T12_G01"）
+#[derive(Debug, Default, PartialEq)]
+pub struct Dxf {
+    layers: Vec<(String, Motion)>,
+    entities: String,
+}
+
+impl Dxf {
+    /// 状態の列からインスタンス化
+    /// 先頭の状態を始点とし、以降の各状態への移動を図形要素とする
+    pub fn new(states: &[State]) -> Self {
+        let mut dxf = Self::default();
+        states.windows(2).for_each(|pair| dxf.push(&pair[0], &pair[1]));
+        dxf
+    }
+
+    /// 移動前と移動後の状態から図形要素を追加
+    pub fn push(&mut self, start: &State, end: &State) {
+        let layer = self.layer(end);
+        match Arc::new(start, end) {
+            Some(arc) if arc.is_helical() || arc.get_plane() != Plane::XY => self.polyline(&layer, &arc),
+            Some(arc) => self.arc(&layer, &arc),
+            None => self.line(&layer, start, end),
+        }
+    }
+
+    /// DXFの文字列を書き込む
+    pub fn write<W: Write + ?Sized>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_all(self.to_string().as_bytes())
+    }
+
+    /// 画層名を返し、未登録の場合は登録する
+    fn layer(&mut self, state: &State) -> String {
+        let name = format!("T{}_{}", state.get_active_tool(), state.get_motion().code());
+        if !self.layers.iter().any(|(layer, _)| *layer == name) {
+            self.layers.push((name.clone(), state.get_motion()));
+        }
+        name
+    }
+
+    /// 直線を追加
+    fn line(&mut self, layer: &str, start: &State, end: &State) {
+        pair(&mut self.entities, 0, "LINE");
+        pair(&mut self.entities, 8, layer);
+        point(&mut self.entities, 10, (start.get_x(), start.get_y(), start.get_z()));
+        point(&mut self.entities, 11, (end.get_x(), end.get_y(), end.get_z()));
+    }
+
+    /// 円弧を追加
+    /// DXFの円弧は反時計回りのため、時計回りの場合は始点と終点を入れ替える
+    fn arc(&mut self, layer: &str, arc: &Arc) {
+        let (start, end) = if arc.is_clockwise() {
+            (arc.get_end_angle(), arc.get_start_angle())
+        } else {
+            (arc.get_start_angle(), arc.get_end_angle())
+        };
+        let (x, y) = arc.get_center();
+        let (_, _, z) = arc.point_at(0.0);
+        if arc.get_sweep().abs() >= 2.0 * PI - 1.0e-4 {
+            pair(&mut self.entities, 0, "CIRCLE");
+            pair(&mut self.entities, 8, layer);
+            point(&mut self.entities, 10, (x, y, z));
+            number(&mut self.entities, 40, arc.get_radius());
+        } else {
+            pair(&mut self.entities, 0, "ARC");
+            pair(&mut self.entities, 8, layer);
+            point(&mut self.entities, 10, (x, y, z));
+            number(&mut self.entities, 40, arc.get_radius());
+            number(&mut self.entities, 50, start.to_degrees().rem_euclid(360.0));
+            number(&mut self.entities, 51, end.to_degrees().rem_euclid(360.0));
+        }
+    }
+
+    /// ヘリカル補間とXY平面以外の円弧を3Dのポリラインとして追加
+    fn polyline(&mut self, layer: &str, arc: &Arc) {
+        let segments = (arc.get_sweep().abs() / HELICAL_STEP).ceil() as usize;
+        pair(&mut self.entities, 0, "POLYLINE");
+        pair(&mut self.entities, 8, layer);
+        pair(&mut self.entities, 66, 1);
+        point(&mut self.entities, 10, (0.0, 0.0, 0.0));
+        pair(&mut self.entities, 70, 8);
+        arc.points(segments).into_iter().for_each(|vertex| {
+            pair(&mut self.entities, 0, "VERTEX");
+            pair(&mut self.entities, 8, layer);
+            point(&mut self.entities, 10, vertex);
+            pair(&mut self.entities, 70, 32);
+        });
+        pair(&mut self.entities, 0, "SEQEND");
+        pair(&mut self.entities, 8, layer);
+    }
+}
+
+impl std::fmt::Display for Dxf {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut output = String::new();
+        pair(&mut output, 0, "SECTION");
+        pair(&mut output, 2, "HEADER");
+        pair(&mut output, 9, "$ACADVER");
+        pair(&mut output, 1, "AC1009");
+        pair(&mut output, 0, "ENDSEC");
+
+        pair(&mut output, 0, "SECTION");
+        pair(&mut output, 2, "TABLES");
+        pair(&mut output, 0, "TABLE");
+        pair(&mut output, 2, "LTYPE");
+        pair(&mut output, 70, 1);
+        pair(&mut output, 0, "LTYPE");
+        pair(&mut output, 2, "CONTINUOUS");
+        pair(&mut output, 70, 0);
+        pair(&mut output, 3, "Solid line");
+        pair(&mut output, 72, 65);
+        pair(&mut output, 73, 0);
+        number(&mut output, 40, 0.0);
+        pair(&mut output, 0, "ENDTAB");
+        pair(&mut output, 0, "TABLE");
+        pair(&mut output, 2, "LAYER");
+        pair(&mut output, 70, self.layers.len());
+        self.layers.iter().for_each(|(name, motion)| {
+            pair(&mut output, 0, "LAYER");
+            pair(&mut output, 2, name);
+            pair(&mut output, 70, 0);
+            pair(&mut output, 62, color(*motion));
+            pair(&mut output, 6, "CONTINUOUS");
+        });
+        pair(&mut output, 0, "ENDTAB");
+        pair(&mut output, 0, "ENDSEC");
+
+        pair(&mut output, 0, "SECTION");
+        pair(&mut output, 2, "ENTITIES");
+        output.push_str(&self.entities);
+        pair(&mut output, 0, "ENDSEC");
+        pair(&mut output, 0, "EOF");
+
+        f.write_str(&output)
+    }
+}
+
+/// 移動モードごとの画層の色番号を返す
+fn color(motion: Motion) -> u8 {
+    match motion {
+        Motion::Rapid => 1,
+        Motion::Linear => 3,
+        Motion::ClockwiseArc => 5,
+        Motion::CounterClockwiseArc => 4,
+    }
+}
+
+/// グループコードと値を書き込む
+fn pair<T: std::fmt::Display>(output: &mut String, code: u16, value: T) {
+    let _ = write!(output, "{:>3}\n{}\n", code, value);
+}
+
+/// グループコードと数値を書き込む
+fn number(output: &mut String, code: u16, value: f32) {
+    pair(output, code, format!("{:.4}", value));
+}
+
+/// 座標をX、Y、Zのグループコードで書き込む
+fn point(output: &mut String, code: u16, (x, y, z): (f32, f32, f32)) {
+    number(output, code, x);
+    number(output, code + 10, y);
+    number(output, code + 20, z);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::structs::line_parser::Line;
+
+    fn states(lines: &[&str]) -> Vec<State> {
+        let mut state = State::default();
+        let mut states = vec![state];
+        lines.iter().for_each(|line| {
+            if let Some(result) = state.state_update(Line::new(line.to_string())).unwrap() {
+                states.push(result);
+            }
+        });
+        states
+    }
+
+    fn entities(dxf: &str, name: &str) -> usize {
+        dxf.lines().filter(|line| *line == name).count()
+    }
+
+    #[test]
+    fn dxf_new_layers() {
+        let dxf = Dxf::new(&states(&["T1G00X10.0Y10.0", "G01Z-5.0", "T2G00Z10.0", "G01X20.0"]));
+        let layers: Vec<&str> = dxf.layers.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(layers, ["T1_G00", "T1_G01", "T2_G00", "T2_G01"]);
+    }
+
+    #[test]
+    fn dxf_line() {
+        let dxf = Dxf::new(&states(&["G01X10.0Y20.0Z-5.0"])).to_string();
+        assert_eq!(entities(&dxf, "LINE"), 1);
+        assert!(dxf.contains(" 11\n10.0000\n 21\n20.0000\n 31\n-5.0000\n"));
+    }
+
+    #[test]
+    fn dxf_arc() {
+        // 時計回りの円弧は始点と終点の角度を入れ替える
+        let dxf = Dxf::new(&states(&["G00X10.0", "G02X0.Y-10.0I-10.0"])).to_string();
+        assert_eq!(entities(&dxf, "ARC"), 1);
+        assert!(dxf.contains(" 40\n10.0000\n 50\n270.0000\n 51\n0.0000\n"));
+
+        let dxf = Dxf::new(&states(&["G00X10.0Z-2.0", "G03X0.Y10.0I-10.0"])).to_string();
+        assert!(dxf.contains(" 10\n0.0000\n 20\n0.0000\n 30\n-2.0000\n 40\n10.0000\n 50\n0.0000\n 51\n90.0000\n"));
+    }
+
+    #[test]
+    fn dxf_circle() {
+        let dxf = Dxf::new(&states(&["G00X10.0", "G02I-10.0"])).to_string();
+        assert_eq!(entities(&dxf, "CIRCLE"), 1);
+        assert_eq!(entities(&dxf, "ARC"), 0);
+    }
+
+    #[test]
+    fn dxf_helical_polyline() {
+        let dxf = Dxf::new(&states(&["G00X10.0", "G03X-10.0Z-5.0I-10.0"])).to_string();
+        assert_eq!(entities(&dxf, "POLYLINE"), 1);
+        assert_eq!(entities(&dxf, "VERTEX"), 37);
+        assert_eq!(entities(&dxf, "SEQEND"), 1);
+    }
+
+    #[test]
+    fn dxf_plane_polyline() {
+        let dxf = Dxf::new(&states(&["G00X10.0", "G18G03X-10.0I-10.0"])).to_string();
+        assert_eq!(entities(&dxf, "ARC"), 0);
+        assert_eq!(entities(&dxf, "POLYLINE"), 1);
+        assert_eq!(entities(&dxf, "VERTEX"), 37);
+        // ZX平面の円弧はZの負の側を通る
+        assert!(dxf.contains(" 30\n-10.0000\n"));
+    }
+
+    #[test]
+    fn dxf_write() {
+        let dxf = Dxf::new(&states(&["G01X10.0"]));
+        let mut output = Vec::new();
+        dxf.write(&mut output).unwrap();
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.starts_with("  0\nSECTION\n  2\nHEADER\n"));
+        assert!(output.ends_with("  0\nENDSEC\n  0\nEOF\n"));
+    }
+}
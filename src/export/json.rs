@@ -0,0 +1,29 @@
+use std::io::{self, Write};
+
+use crate::export::record::Record;
+use crate::structs::program::Program;
+
+/// 実行した全ブロックを1行1レコードのJSON Linesとして書き込む
+pub fn write_lines<W: Write + ?Sized>(writer: &mut W, program: &Program) -> io::Result<()> {
+    program.get_blocks().iter().try_for_each(|block| {
+        serde_json::to_writer(&mut *writer, &Record::new(block))?;
+        writeln!(writer)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn json_write_lines() {
+        let program = Program::new("G01X1.5F100.M04M07(CUT)\nM05M09").unwrap();
+        let mut output = Vec::new();
+        write_lines(&mut output, &program).unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "{\"line_number\":1,\"source\":\"G01X1.5F100.M04M07(CUT)\",\"x\":1.5,\"y\":0.0,\"z\":0.0,\"a\":0.0,\"b\":0.0,\"c\":0.0,\"modal\":\"G01 G17 G90 G21 G40 G49 G80 G54 M04 M07\",\"feed\":100.0,\"spindle_speed\":0.0,\"tool\":0,\"tool_change\":false}\n\
+             {\"line_number\":2,\"source\":\"M05M09\",\"x\":1.5,\"y\":0.0,\"z\":0.0,\"a\":0.0,\"b\":0.0,\"c\":0.0,\"modal\":\"G01 G17 G90 G21 G40 G49 G80 G54 M05 M09\",\"feed\":100.0,\"spindle_speed\":0.0,\"tool\":0,\"tool_change\":false}\n"
+        );
+    }
+}
@@ -0,0 +1,293 @@
+use std::io::{self, Write};
+
+use serde::{Deserialize, Serialize};
+
+use crate::structs::block::{Block, Word};
+use crate::structs::line_parser::{g_code, m_code};
+use crate::structs::program::Program;
+
+/// 小数点付きの数値を書式に従って出力するアドレス
+const DECIMAL_ADDRESSES: [char; 14] = ['X', 'Y', 'Z', 'A', 'B', 'C', 'U', 'V', 'W', 'I', 'J', 'K', 'R', 'F'];
+
+/// 整数の値の小数点以下の書き方
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Trailing {
+    /// 小数点のみ、例: "X-1515."
+    #[default]
+    Dot,
+    /// 桁数分の0、例: "X-1515.000"
+    Zeros,
+    /// 小数点を省略、例: "X-1515"
+    Omit,
+}
+
+/// シーケンス番号の振り方
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Serialize, Deserialize)]
+pub struct Numbering {
+    start: u32,
+    increment: u32,
+    /// 0で埋める桁数
+    #[serde(default)]
+    width: usize,
+}
+
+impl Numbering {
+    /// 開始番号、増分、0で埋める桁数からインスタンス化
+    pub fn new(start: u32, increment: u32, width: usize) -> Self {
+        Self { start, increment, width }
+    }
+}
+
+/// NCプログラムの書式
+/// 数値の書式は小数点を含む値のみに適用し、小数点のない値は機械の設定で単位が変わるため記述のまま出力する
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NcFormat {
+    /// 小数点以下の桁数、Noneの場合は記述のまま
+    decimal_places: Option<usize>,
+    /// 小数点以下の桁数を指定した場合の、小数点以下の0の書き方
+    trailing: Trailing,
+    /// 1未満の値の小数点の前に0を付ける、例: "X0.5"（偽の場合は"X.5"）
+    leading_zero: bool,
+    /// GコードとMコードの整数部を2桁に揃える、例: "G01"、"M03"
+    code_digits: bool,
+    /// 正の値に"+"を付ける
+    plus_sign: bool,
+    /// ワードを並べるアドレスの順序、含まないアドレスは後ろに記述の順で並べる
+    word_order: Option<String>,
+    /// ワードの区切り
+    separator: String,
+    /// シーケンス番号、Noneの場合は記述のまま
+    numbering: Option<Numbering>,
+}
+
+impl Default for NcFormat {
+    fn default() -> Self {
+        Self {
+            decimal_places: None,
+            trailing: Trailing::Dot,
+            leading_zero: true,
+            code_digits: false,
+            plus_sign: false,
+            word_order: None,
+            separator: String::new(),
+            numbering: None,
+        }
+    }
+}
+
+impl NcFormat {
+    /// 小数点以下の桁数と0の書き方を設定
+    pub fn decimal_places_set(&mut self, decimal_places: Option<usize>, trailing: Trailing) {
+        self.decimal_places = decimal_places;
+        self.trailing = trailing;
+    }
+
+    /// 1未満の値の小数点の前に0を付けるかを設定
+    pub fn leading_zero_set(&mut self, leading_zero: bool) {
+        self.leading_zero = leading_zero;
+    }
+
+    /// GコードとMコードの整数部を2桁に揃えるかを設定
+    pub fn code_digits_set(&mut self, code_digits: bool) {
+        self.code_digits = code_digits;
+    }
+
+    /// 正の値に"+"を付けるかを設定
+    pub fn plus_sign_set(&mut self, plus_sign: bool) {
+        self.plus_sign = plus_sign;
+    }
+
+    /// ワードを並べるアドレスの順序を設定
+    pub fn word_order_set(&mut self, word_order: Option<&str>) {
+        self.word_order = word_order.map(str::to_string);
+    }
+
+    /// ワードの区切りを返す
+    pub fn get_separator(&self) -> &str {
+        &self.separator
+    }
+
+    /// ワードの区切りを設定
+    pub fn separator_set(&mut self, separator: &str) {
+        self.separator = separator.to_string();
+    }
+
+    /// シーケンス番号の振り方を設定
+    pub fn numbering_set(&mut self, numbering: Option<Numbering>) {
+        self.numbering = numbering;
+    }
+}
+
+/// ブロックを書式に従ってNCプログラムの行として出力する
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct NcWriter {
+    format: NcFormat,
+    /// 次のシーケンス番号
+    number: Option<u32>,
+}
+
+impl NcWriter {
+    /// 書式からインスタンス化
+    pub fn new(format: NcFormat) -> Self {
+        let number = format.numbering.map(|numbering| numbering.start);
+        Self { format, number }
+    }
+
+    /// 書式を返す
+    pub fn get_format(&self) -> &NcFormat {
+        &self.format
+    }
+
+    /// ブロックのワードとコメントを1行の文字列で返す
+    /// シーケンス番号を振る場合は、ワードを含むブロックの先頭に番号を付ける
+    pub fn block(&mut self, block: &Block) -> String {
+        let mut words: Vec<Word> = block.get_words().to_vec();
+        if let (Some(numbering), Some(number)) = (self.format.numbering, self.number) {
+            if !words.is_empty() {
+                words.retain(|word| word.get_code() != 'N');
+                words.insert(0, Word::new('N', &format!("{:0width$}", number, width = numbering.width)));
+                self.number = Some(number + numbering.increment);
+            }
+        }
+        let comments = block.get_comments().iter().map(|comment| format!("({})", comment));
+        let line = self.words(&words);
+        match line.is_empty() {
+            true => comments.collect(),
+            false => std::iter::once(line).chain(comments).collect(),
+        }
+    }
+
+    /// ワードを書式の順序と区切りで並べた文字列を返す
+    pub fn words(&self, words: &[Word]) -> String {
+        let mut words: Vec<&Word> = words.iter().collect();
+        if let Some(order) = &self.format.word_order {
+            words.sort_by_key(|word| order.find(word.get_code()).unwrap_or(order.len()));
+        }
+        words.iter()
+            .map(|word| self.word(word))
+            .collect::<Vec<_>>()
+            .join(&self.format.separator)
+    }
+
+    /// ワードを書式に従った文字列で返す
+    pub fn word(&self, word: &Word) -> String {
+        let value = word.get_value();
+        let value = match word.get_code() {
+            'G' if self.format.code_digits => g_code(value)[1..].to_string(),
+            'M' if self.format.code_digits => m_code(value)[1..].to_string(),
+            code if DECIMAL_ADDRESSES.contains(&code) && value.contains('.') => self.decimal(value),
+            _ => value.to_string(),
+        };
+        format!("{}{}", word.get_code(), value)
+    }
+
+    /// 小数点を含む数値を書式に従った文字列で返す
+    /// 例: 小数点以下3桁で"-1515." -> "-1515.000"、"+.5" -> "0.500"
+    fn decimal(&self, value: &str) -> String {
+        let Ok(number) = value.parse::<f64>() else {
+            return value.to_string();
+        };
+        let mut digits = match self.format.decimal_places {
+            Some(places) => {
+                let digits = format!("{:.*}", places, number.abs());
+                match (self.format.trailing, digits.contains('.')) {
+                    (Trailing::Zeros, true) => digits,
+                    (_, true) => digits.trim_end_matches('0').to_string(),
+                    (_, false) => digits + ".",
+                }
+            },
+            None => value.trim_start_matches(['+', '-']).to_string(),
+        };
+        if self.format.trailing == Trailing::Omit && self.format.decimal_places.is_some() {
+            digits = digits.trim_end_matches('.').to_string();
+        }
+        if self.format.leading_zero && digits.starts_with('.') {
+            digits.insert(0, '0');
+        } else if !self.format.leading_zero && digits.starts_with("0.") && digits.len() > 2 {
+            digits.remove(0);
+        }
+        let zero = digits.chars().all(|c| c == '0' || c == '.');
+        match (number.is_sign_negative() && !zero, self.format.plus_sign && !zero) {
+            (true, _) => format!("-{}", digits),
+            (false, true) => format!("+{}", digits),
+            (false, false) => digits,
+        }
+    }
+}
+
+/// 実行した全ブロックを書式に従ってNCプログラムとして書き込む
+/// 式は評価した値、条件が偽の範囲は除き、繰り返しは展開して出力する
+pub fn write<W: Write + ?Sized>(writer: &mut W, program: &Program, format: &NcFormat) -> io::Result<()> {
+    let mut nc_writer = NcWriter::new(format.clone());
+    program.get_blocks().iter().try_for_each(|block| {
+        writeln!(writer, "{}", nc_writer.block(block.get_block()))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::structs::line_parser::Line;
+
+    fn block(line: &str) -> Block {
+        Block::new(&Line::new(line.to_string())).unwrap()
+    }
+
+    #[test]
+    fn nc_writer_word() {
+        let format = |decimal_places, trailing, leading_zero, plus_sign| {
+            let mut format = NcFormat::default();
+            format.decimal_places_set(decimal_places, trailing);
+            format.leading_zero_set(leading_zero);
+            format.plus_sign_set(plus_sign);
+            NcWriter::new(format)
+        };
+        let datas = [
+            (format(None, Trailing::Dot, true, false), "X-1515.", "X-1515."),
+            (format(None, Trailing::Dot, true, false), "X.5", "X0.5"),
+            (format(None, Trailing::Dot, false, false), "X0.5", "X.5"),
+            (format(None, Trailing::Dot, true, false), "X100", "X100"),
+            (format(Some(3), Trailing::Dot, true, false), "X-1515.0", "X-1515."),
+            (format(Some(3), Trailing::Dot, true, false), "Y1.23456", "Y1.235"),
+            (format(Some(3), Trailing::Zeros, true, false), "X-1515.", "X-1515.000"),
+            (format(Some(3), Trailing::Omit, true, false), "X-1515.", "X-1515"),
+            (format(Some(3), Trailing::Omit, true, false), "X1.50", "X1.5"),
+            (format(Some(0), Trailing::Zeros, true, false), "Z2.4", "Z2."),
+            (format(Some(3), Trailing::Dot, true, true), "Z2.", "Z+2."),
+            (format(Some(3), Trailing::Dot, true, true), "Z-0.0001", "Z0."),
+            (format(Some(3), Trailing::Dot, true, true), "S1000", "S1000"),
+        ];
+        datas.iter().for_each(|(writer, value, expected)| {
+            let block = block(value);
+            assert_eq!(writer.word(&block.get_words()[0]), *expected);
+        });
+
+        let mut format = NcFormat::default();
+        format.code_digits_set(true);
+        let writer = NcWriter::new(format);
+        assert_eq!(writer.words(block("G0G90.0M3").get_words()), "G00G90M03");
+    }
+
+    #[test]
+    fn nc_writer_block() {
+        let mut format = NcFormat::default();
+        format.word_order_set(Some("NGXYZF"));
+        format.separator_set(" ");
+        format.numbering_set(Some(Numbering::new(10, 5, 4)));
+        let mut writer = NcWriter::new(format);
+        assert_eq!(writer.block(&block("N1F100.X1.G01(CUT)")), "N0010 G01 X1. F100.(CUT)");
+        assert_eq!(writer.block(&block("(NOTE)")), "(NOTE)");
+        assert_eq!(writer.block(&block("M05Y2.")), "N0015 Y2. M05");
+    }
+
+    #[test]
+    fn nc_write_round_trip() {
+        let source = "G90G00X-1515.Y137.(START)\n#1=2.\nG01Z-#1F100.\nM30";
+        let program = Program::new(source).unwrap();
+        let mut output = Vec::new();
+        write(&mut output, &program, &NcFormat::default()).unwrap();
+        assert_eq!(String::from_utf8(output).unwrap(), "G90G00X-1515.Y137.(START)\n\nG01Z-2.F100.\nM30\n");
+    }
+}
@@ -0,0 +1,96 @@
+use crate::structs::program::ExecutedBlock;
+use serde::Serialize;
+
+/// 出力用に1ブロックの状態を平坦にした記録
+#[derive(Debug, PartialEq, Serialize)]
+pub struct Record<'a> {
+    pub line_number: usize,
+    pub source: &'a str,
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+    /// 回転軸A
+    pub a: f32,
+    /// 回転軸B
+    pub b: f32,
+    /// 回転軸C
+    pub c: f32,
+    /// 有効なモーダルのGコード、主軸とクーラントのMコード（空白区切り）
+    pub modal: String,
+    pub feed: f32,
+    pub spindle_speed: f32,
+    /// 主軸に装着した工具番号
+    pub tool: u32,
+    /// このブロックで工具交換を行ったか
+    pub tool_change: bool,
+}
+
+impl<'a> Record<'a> {
+    /// 列名
+    pub const HEADER: [&'static str; 13] = [
+        "line_number", "source", "x", "y", "z", "a", "b", "c", "modal", "feed", "spindle_speed", "tool", "tool_change",
+    ];
+
+    /// 実行したブロックからインスタンス化
+    pub fn new(block: &'a ExecutedBlock) -> Self {
+        let state = block.get_state();
+        let [a, b, c] = state.get_rotary();
+        Self {
+            line_number: block.get_line_number(),
+            source: block.get_source(),
+            x: state.get_x(),
+            y: state.get_y(),
+            z: state.get_z(),
+            a,
+            b,
+            c,
+            modal: state.modal_codes().join(" "),
+            feed: state.get_feed(),
+            spindle_speed: state.get_spindle_speed(),
+            tool: state.get_active_tool(),
+            tool_change: state.get_tool_change(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::structs::program::Program;
+
+    #[test]
+    fn record_new() {
+        let program = Program::new("T3S1000M03\nG91G01X10.0Z-1.0C90.F200.M08").unwrap();
+        let result = Record::new(&program.get_blocks()[1]);
+        assert_eq!(result, Record {
+            line_number: 2,
+            source: "G91G01X10.0Z-1.0C90.F200.M08",
+            x: 10.0,
+            y: 0.0,
+            z: -1.0,
+            a: 0.0,
+            b: 0.0,
+            c: 90.0,
+            modal: "G01 G17 G91 G21 G40 G49 G80 G54 M03 M08".to_string(),
+            feed: 200.0,
+            spindle_speed: 1000.0,
+            tool: 3,
+            tool_change: false,
+        });
+    }
+
+    #[test]
+    fn record_new_tool_change() {
+        let program = Program::new("T1M06
+T2
+G00X10.
+M06").unwrap();
+        let result: Vec<(u32, bool)> = program.get_blocks().iter()
+            .map(|block| {
+                let record = Record::new(block);
+                (record.tool, record.tool_change)
+            })
+            .collect();
+        assert_eq!(result, [(1, true), (1, false), (1, false), (2, true)]);
+    }
+}
@@ -0,0 +1,356 @@
+use std::fmt::Write as _;
+use std::io::{self, Write};
+
+use crate::analysis::cycle_time::{minutes, CycleTime};
+use crate::analysis::extents::{Extents, AXES};
+use crate::analysis::tools::ToolSummary;
+use crate::export::svg::Svg;
+use crate::structs::line_parser::m_code;
+use crate::structs::machine_profile::MachineProfile;
+use crate::structs::program::{ExecutedBlock, Program};
+
+/// 段取りシートの内容
+enum Content {
+    /// 列名と行
+    Table(Vec<&'static str>, Vec<Vec<String>>),
+    /// 箇条書き
+    List(Vec<String>),
+    /// 工具経路の画像
+    Image,
+}
+
+/// 作業者向けの段取りシート
+/// プログラム番号とタイトル、工具、ワーク座標系、移動範囲、加工時間、任意停止、コメント、工具経路をまとめる
+#[derive(Debug, PartialEq)]
+pub struct SetupSheet {
+    /// プログラム番号（O）
+    program_number: Option<String>,
+    /// プログラム番号の行、またはプログラムの先頭のコメント
+    title: Option<String>,
+    tools: Vec<ToolSummary>,
+    /// 使用したワーク座標系とワーク原点の機械座標
+    work_offsets: Vec<(String, [f32; 3])>,
+    extents: Option<Extents>,
+    cycle_time: CycleTime,
+    /// 任意停止（M01）の行番号、工具番号、位置
+    optional_stops: Vec<(usize, u32, [f32; 3])>,
+    /// タイトルと工具の説明以外のコメントの行番号と内容
+    notes: Vec<(usize, String)>,
+    /// 工具経路のSVG
+    image: String,
+}
+
+impl SetupSheet {
+    /// 実行したプログラムと機械の設定からインスタンス化
+    pub fn new(program: &Program, profile: &MachineProfile) -> Self {
+        let blocks = program.get_blocks();
+        let numbered = blocks.iter().find(|block| block.get_block().words_of('O').next().is_some());
+        let program_number = numbered
+            .and_then(|block| block.get_block().words_of('O').next())
+            .map(|word| format!("O{}", word.get_value()));
+        // プログラム番号がない場合は最初の移動より前のコメントのみの行をタイトルとする
+        let titled = numbered
+            .filter(|block| !block.get_block().get_comments().is_empty())
+            .or_else(|| blocks.iter()
+                .take_while(|block| !block.get_moved())
+                .find(|block| block.get_block().get_words().is_empty() && !block.get_block().get_comments().is_empty()));
+        let title = titled.map(|block| block.get_block().get_comments()[0].trim().to_string());
+
+        let mut work_offsets: Vec<(String, [f32; 3])> = Vec::new();
+        program.moves().for_each(|(_, block)| {
+            let code = block.get_state().get_work_offset().code();
+            if !work_offsets.iter().any(|(used, _)| *used == code) {
+                let offset = profile.get_offsets().work_offset(&code);
+                work_offsets.push((code, offset));
+            }
+        });
+
+        let optional_stops = blocks.iter()
+            .filter(|block| block.get_block().words_of('M').any(|word| m_code(word.get_value()) == "M01"))
+            .map(|block| {
+                let state = block.get_state();
+                (block.get_line_number(), state.get_active_tool(), [state.get_x(), state.get_y(), state.get_z()])
+            })
+            .collect();
+
+        let title_line = titled.map(|block| block.get_line_number());
+        let mut notes: Vec<(usize, String)> = Vec::new();
+        blocks.iter()
+            .filter(|block| Some(block.get_line_number()) != title_line && !describes_tool(block))
+            .for_each(|block| block.get_block().get_comments().iter()
+                .map(|comment| (block.get_line_number(), comment.trim().to_string()))
+                .filter(|(_, comment)| !comment.is_empty())
+                .for_each(|note| {
+                    // ジャンプで繰り返し実行した行は1回のみとする
+                    if !notes.contains(&note) {
+                        notes.push(note);
+                    }
+                }));
+
+        Self {
+            program_number,
+            title,
+            tools: ToolSummary::of_program(program),
+            work_offsets,
+            extents: Extents::of_program(program),
+            cycle_time: CycleTime::new(program, profile),
+            optional_stops,
+            notes,
+            image: Svg::new(program).to_string(),
+        }
+    }
+
+    /// HTMLを書き込む
+    /// 工具経路はSVGとして埋め込む
+    pub fn write_html<W: Write + ?Sized>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_all(self.html().as_bytes())
+    }
+
+    /// Markdownを書き込む
+    /// 工具経路はSVGのデータURIの画像として埋め込む
+    pub fn write_markdown<W: Write + ?Sized>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_all(self.markdown().as_bytes())
+    }
+
+    /// 見出しの文字列を返す
+    fn heading(&self) -> String {
+        let names: Vec<&str> = [self.program_number.as_deref(), self.title.as_deref()]
+            .into_iter()
+            .flatten()
+            .collect();
+        match names.is_empty() {
+            true => "Setup sheet".to_string(),
+            false => format!("Setup sheet : {}", names.join(" ")),
+        }
+    }
+
+    /// 項目ごとの見出しと内容を返す
+    fn sections(&self) -> Vec<(&'static str, Content)> {
+        let tools = self.tools.iter().map(|summary| vec![
+            format!("T{}", summary.get_tool()),
+            summary.get_description().unwrap_or_default().to_string(),
+            join(summary.get_length_offsets()),
+            join(summary.get_radius_offsets()),
+            format!("{} .. {}", summary.get_first_line(), summary.get_last_line()),
+            minutes(summary.get_cutting_time()),
+            range(summary.get_feed_range()),
+            range(summary.get_spindle_range()),
+            summary.get_min_z().map_or("-".to_string(), |z| format!("{:.3}", z)),
+        ]).collect();
+        let work_offsets = self.work_offsets.iter()
+            .map(|(code, offset)| std::iter::once(code.clone()).chain(offset.map(|value| format!("{:.3}", value))).collect())
+            .collect();
+        let extents = self.extents.map_or(Vec::new(), |extents| (0..3).map(|axis| vec![
+            AXES[axis].to_string(),
+            format!("{:.3}", extents.get_min()[axis]),
+            format!("{:.3}", extents.get_max()[axis]),
+        ]).collect());
+        let cycle_time = vec![
+            vec!["Rapid".to_string(), minutes(self.cycle_time.get_rapid())],
+            vec!["Feed".to_string(), minutes(self.cycle_time.get_feed())],
+            vec!["Tool change".to_string(), minutes(self.cycle_time.get_tool_change())],
+            vec!["Total".to_string(), minutes(self.cycle_time.total())],
+        ];
+        let optional_stops = self.optional_stops.iter()
+            .map(|(line_number, tool, position)| [line_number.to_string(), format!("T{}", tool)].into_iter()
+                .chain(position.map(|value| format!("{:.3}", value)))
+                .collect())
+            .collect();
+        let notes = self.notes.iter().map(|(line_number, note)| format!("Line {} : {}", line_number, note)).collect();
+
+        vec![
+            ("Tools", Content::Table(vec!["Tool", "Description", "H", "D", "Lines", "Cutting time", "Feeds", "Spindle speeds", "Min Z"], tools)),
+            ("Work offsets", Content::Table(vec!["Code", "X", "Y", "Z"], work_offsets)),
+            ("Extents", Content::Table(vec!["Axis", "Min", "Max"], extents)),
+            ("Run time", Content::Table(vec!["", "Time"], cycle_time)),
+            ("Optional stops", Content::Table(vec!["Line", "Tool", "X", "Y", "Z"], optional_stops)),
+            ("Notes", Content::List(notes)),
+            ("Toolpath", Content::Image),
+        ]
+    }
+
+    /// HTMLの文字列を返す
+    fn html(&self) -> String {
+        let heading = escape_html(&self.heading());
+        let mut html = String::new();
+        let _ = writeln!(html, "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>", heading);
+        html.push_str("<style>table { border-collapse: collapse; } th, td { border: 1px solid #888; padding: 2px 8px; } svg { max-width: 100%; max-height: 80vh; }</style>\n");
+        let _ = writeln!(html, "</head>\n<body>\n<h1>{}</h1>", heading);
+        self.sections().into_iter().for_each(|(name, content)| {
+            let _ = writeln!(html, "<h2>{}</h2>", name);
+            match content {
+                Content::Table(_, rows) if rows.is_empty() => html.push_str("<p>-</p>\n"),
+                Content::Table(header, rows) => {
+                    html.push_str("<table>\n<tr>");
+                    header.iter().for_each(|name| {
+                        let _ = write!(html, "<th>{}</th>", name);
+                    });
+                    html.push_str("</tr>\n");
+                    rows.iter().for_each(|row| {
+                        html.push_str("<tr>");
+                        row.iter().for_each(|cell| {
+                            let _ = write!(html, "<td>{}</td>", escape_html(cell));
+                        });
+                        html.push_str("</tr>\n");
+                    });
+                    html.push_str("</table>\n");
+                },
+                Content::List(items) if items.is_empty() => html.push_str("<p>-</p>\n"),
+                Content::List(items) => {
+                    html.push_str("<ul>\n");
+                    items.iter().for_each(|item| {
+                        let _ = writeln!(html, "<li>{}</li>", escape_html(item));
+                    });
+                    html.push_str("</ul>\n");
+                },
+                Content::Image => html.push_str(&self.image),
+            }
+        });
+        html.push_str("</body>\n</html>\n");
+        html
+    }
+
+    /// Markdownの文字列を返す
+    fn markdown(&self) -> String {
+        let mut markdown = String::new();
+        let _ = writeln!(markdown, "# {}", self.heading());
+        self.sections().into_iter().for_each(|(name, content)| {
+            let _ = writeln!(markdown, "\n## {}", name);
+            match content {
+                Content::Table(_, rows) if rows.is_empty() => markdown.push_str("-\n"),
+                Content::Table(header, rows) => {
+                    let _ = writeln!(markdown, "| {} |", header.join(" | "));
+                    let _ = writeln!(markdown, "|{}", " --- |".repeat(header.len()));
+                    rows.iter().for_each(|row| {
+                        let cells: Vec<String> = row.iter().map(|cell| cell.replace('|', "\\|")).collect();
+                        let _ = writeln!(markdown, "| {} |", cells.join(" | "));
+                    });
+                },
+                Content::List(items) if items.is_empty() => markdown.push_str("-\n"),
+                Content::List(items) => items.iter().for_each(|item| {
+                    let _ = writeln!(markdown, "- {}", item);
+                }),
+                Content::Image => {
+                    let _ = writeln!(markdown, "![Toolpath](data:image/svg+xml,{})", encode_uri(&self.image));
+                },
+            }
+        });
+        markdown
+    }
+}
+
+/// 工具の選択、交換の行であるかの真偽値を返す
+/// この行のコメントは工具の説明とする
+fn describes_tool(block: &ExecutedBlock) -> bool {
+    block.get_block().words_of('T').next().is_some() || block.get_state().get_tool_change()
+}
+
+/// 値を空白区切りの文字列にする
+fn join(values: &[u32]) -> String {
+    values.iter().map(|value| value.to_string()).collect::<Vec<_>>().join(" ")
+}
+
+/// 最小値と最大値を文字列にする
+fn range(range: Option<(f32, f32)>) -> String {
+    match range {
+        Some((min, max)) => format!("{} .. {}", min, max),
+        None => "-".to_string(),
+    }
+}
+
+/// HTMLの特殊文字を文字参照にする
+fn escape_html(value: &str) -> String {
+    value.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+/// データURIに使えない文字をパーセントエンコードする
+fn encode_uri(value: &str) -> String {
+    value.bytes().map(|byte| match byte {
+        b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' | b':' | b'=' | b',' => {
+            (byte as char).to_string()
+        },
+        _ => format!("%{:02X}", byte),
+    }).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOURCE: &str = "%\n\
+        O1234(BRACKET OP1)\n\
+        (CHECK CLAMP <A>)\n\
+        T1M06(FACE MILL)\n\
+        G90G54G00X0Y0S1000M03\n\
+        G43Z10.H1\n\
+        G01Z-1.F200.( --- 5.0MM NOKOSHI )\n\
+        X100.\n\
+        M01\n\
+        G55G00X10.Y-5.\n\
+        M30\n\
+        %";
+
+    fn sheet() -> SetupSheet {
+        let mut profile = MachineProfile::default();
+        let mut offsets = crate::structs::machine_profile::OffsetTable::default();
+        offsets.work_offset_set("G55", [-100.0, -50.0, -300.0]);
+        profile.offsets_set(offsets);
+        SetupSheet::new(&Program::new(SOURCE).unwrap(), &profile)
+    }
+
+    #[test]
+    fn setup_sheet_new() {
+        let result = sheet();
+        assert_eq!(result.program_number.as_deref(), Some("O1234"));
+        assert_eq!(result.title.as_deref(), Some("BRACKET OP1"));
+        assert_eq!(result.tools.len(), 1);
+        assert_eq!(result.tools[0].get_description(), Some("FACE MILL"));
+        assert_eq!(result.work_offsets, [("G54".to_string(), [0.0; 3]), ("G55".to_string(), [-100.0, -50.0, -300.0])]);
+        assert_eq!(result.optional_stops, [(9, 1, [100.0, 0.0, -1.0])]);
+        assert_eq!(result.notes, [(3, "CHECK CLAMP <A>".to_string()), (7, "--- 5.0MM NOKOSHI".to_string())]);
+        assert!(result.image.starts_with("<svg "));
+    }
+
+    #[test]
+    fn setup_sheet_title_without_program_number() {
+        let result = SetupSheet::new(&Program::new("(SIDE PLATE)\n(OP2)\nG00X10.").unwrap(), &MachineProfile::default());
+        assert_eq!(result.program_number, None);
+        assert_eq!(result.title.as_deref(), Some("SIDE PLATE"));
+        assert_eq!(result.notes, [(2, "OP2".to_string())]);
+        assert_eq!(result.heading(), "Setup sheet : SIDE PLATE");
+    }
+
+    #[test]
+    fn setup_sheet_write_html() {
+        let mut output = Vec::new();
+        sheet().write_html(&mut output).unwrap();
+        let result = String::from_utf8(output).unwrap();
+        assert!(result.starts_with("<!DOCTYPE html>\n"));
+        assert!(result.contains("<h1>Setup sheet : O1234 BRACKET OP1</h1>\n"));
+        assert!(result.contains("<tr><td>T1</td><td>FACE MILL</td><td>1</td><td></td><td>4 .. 11</td>"));
+        assert!(result.contains("<tr><td>9</td><td>T1</td><td>100.000</td><td>0.000</td><td>-1.000</td></tr>\n"));
+        assert!(result.contains("<li>Line 3 : CHECK CLAMP &lt;A&gt;</li>\n"));
+        assert!(result.contains("<h2>Toolpath</h2>\n<svg "));
+        assert!(result.ends_with("</svg>\n</body>\n</html>\n"));
+    }
+
+    #[test]
+    fn setup_sheet_write_markdown() {
+        let mut output = Vec::new();
+        sheet().write_markdown(&mut output).unwrap();
+        let result = String::from_utf8(output).unwrap();
+        assert!(result.starts_with("# Setup sheet : O1234 BRACKET OP1\n\n## Tools\n"));
+        assert!(result.contains("| Code | X | Y | Z |\n| --- | --- | --- | --- |\n| G54 | 0.000 | 0.000 | 0.000 |\n| G55 | -100.000 | -50.000 | -300.000 |\n"));
+        assert!(result.contains("| Axis | Min | Max |\n| --- | --- | --- |\n| X | 0.000 | 100.000 |\n"));
+        assert!(result.contains("## Notes\n- Line 3 : CHECK CLAMP <A>\n- Line 7 : --- 5.0MM NOKOSHI\n"));
+        assert!(result.contains("![Toolpath](data:image/svg+xml,%3Csvg%20xmlns="));
+    }
+
+    #[test]
+    fn setup_sheet_encode_uri() {
+        let datas = [("<a b=\"1\">", "%3Ca%20b=%221%22%3E"), ("M0,1\n#", "M0,1%0A%23")];
+        datas.iter().for_each(|&(input, expected)| {
+            assert_eq!(encode_uri(input), expected);
+        })
+    }
+}
@@ -0,0 +1,143 @@
+use std::fmt::Write as _;
+use std::io::{self, Write};
+
+use crate::analysis::extents::{position, Extents};
+use crate::structs::arc::Arc;
+use crate::structs::motion_mode::Motion;
+use crate::structs::plane::Plane;
+use crate::structs::program::Program;
+
+/// XY平面以外の円弧を折れ線に分割する際の1区間の角度（ラジアン）
+const ARC_STEP: f32 = std::f32::consts::PI / 36.0;
+
+/// XY平面に投影した工具経路のSVG出力
+/// 早送りは赤の破線、切削送りは青の実線とする
+#[derive(Debug, PartialEq)]
+pub struct Svg {
+    extents: Extents,
+    paths: String,
+}
+
+impl Svg {
+    /// プログラムの全移動からインスタンス化
+    pub fn new(program: &Program) -> Self {
+        let mut paths = String::new();
+        program.moves().for_each(|(start, block)| {
+            let end = block.get_state();
+            let class = if end.get_motion() == Motion::Rapid { "rapid" } else { "feed" };
+            let _ = write!(paths, "<path class=\"{}\" d=\"M{},{} ", class, number(start.get_x()), number(start.get_y()));
+            match Arc::new(&start, &end) {
+                // XY平面以外の円弧は投影した折れ線とする
+                Some(arc) if arc.get_plane() != Plane::XY => {
+                    let segments = (arc.get_sweep().abs() / ARC_STEP).ceil() as usize;
+                    arc.points(segments).iter().skip(1).for_each(|&(x, y, _)| {
+                        let _ = write!(paths, "L{},{} ", number(x), number(y));
+                    });
+                },
+                // 全周の円弧は1つの円弧コマンドで描けないため2つに分ける
+                Some(arc) if arc.get_sweep().abs() > std::f32::consts::PI => {
+                    let (x, y, _) = arc.point_at(0.5);
+                    arc_command(&mut paths, &arc, (x, y));
+                    arc_command(&mut paths, &arc, (end.get_x(), end.get_y()));
+                },
+                Some(arc) => arc_command(&mut paths, &arc, (end.get_x(), end.get_y())),
+                None => {
+                    let _ = write!(paths, "L{},{}", number(end.get_x()), number(end.get_y()));
+                },
+            }
+            paths.push_str("\"/>\n");
+        });
+
+        // 最初の移動の始点も表示範囲に含める
+        let mut extents = Extents::new(position(&program.get_start()));
+        if let Some(moves) = Extents::of_program(program) {
+            extents.include(&moves);
+        }
+
+        Self { extents, paths }
+    }
+
+    /// SVGの文字列を書き込む
+    pub fn write<W: Write + ?Sized>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_all(self.to_string().as_bytes())
+    }
+}
+
+impl std::fmt::Display for Svg {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let (min, max) = (self.extents.get_min(), self.extents.get_max());
+        let size = (max[0] - min[0]).max(max[1] - min[1]).max(1.0);
+        let margin = size * 0.05;
+        let stroke = size / 500.0;
+        // SVGはYが下向きのため、上下を反転する
+        writeln!(
+            f,
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"{:.3} {:.3} {:.3} {:.3}\">",
+            min[0] - margin,
+            -max[1] - margin,
+            max[0] - min[0] + margin * 2.0,
+            max[1] - min[1] + margin * 2.0,
+        )?;
+        writeln!(
+            f,
+            "<style>path {{ fill: none; stroke-width: {:.3}; }} .rapid {{ stroke: red; stroke-dasharray: {:.3}; }} .feed {{ stroke: blue; }}</style>",
+            stroke,
+            stroke * 4.0,
+        )?;
+        writeln!(f, "<g transform=\"scale(1,-1)\">")?;
+        f.write_str(&self.paths)?;
+        writeln!(f, "</g>")?;
+        writeln!(f, "</svg>")
+    }
+}
+
+/// 円弧のコマンドを書き込む
+/// Yを反転して表示するため、回転方向のフラグはXY平面の向きのまま指定する
+fn arc_command(paths: &mut String, arc: &Arc, (x, y): (f32, f32)) {
+    let sweep = if arc.is_clockwise() { 0 } else { 1 };
+    let radius = number(arc.get_radius());
+    let _ = write!(paths, "A{},{} 0 0 {} {},{} ", radius, radius, sweep, number(x), number(y));
+}
+
+/// 小数点以下3桁の文字列にする
+/// 誤差による"-0.000"は"0.000"とする
+fn number(value: f32) -> String {
+    let result = format!("{:.3}", value);
+    if result == "-0.000" {
+        "0.000".to_string()
+    } else {
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn svg_new() {
+        let program = Program::new("G00X10.0\nG01Y10.0F100.\nG02X20.0R5.0").unwrap();
+        let result = Svg::new(&program).to_string();
+        assert!(result.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"-1.000 -16.000 22.000 17.000\">\n"));
+        assert!(result.contains("<path class=\"rapid\" d=\"M0.000,0.000 L10.000,0.000\"/>\n"));
+        assert!(result.contains("<path class=\"feed\" d=\"M10.000,0.000 L10.000,10.000\"/>\n"));
+        assert!(result.contains("<path class=\"feed\" d=\"M10.000,10.000 A5.000,5.000 0 0 0 20.000,10.000 \"/>\n"));
+        assert!(result.ends_with("</g>\n</svg>\n"));
+    }
+
+    #[test]
+    fn svg_full_circle() {
+        let program = Program::new("G00X10.0\nG03I-10.0").unwrap();
+        let result = Svg::new(&program).to_string();
+        assert!(result.contains("d=\"M10.000,0.000 A10.000,10.000 0 0 1 -10.000,0.000 A10.000,10.000 0 0 1 10.000,0.000 \""));
+    }
+
+    #[test]
+    fn svg_plane_arc() {
+        let program = Program::new("G00X10.0\nG18G03X-10.0I-10.0").unwrap();
+        let result = Svg::new(&program).to_string();
+        assert!(result.contains("d=\"M10.000,0.000 L9.962,0.000 "));
+        assert!(result.contains("L-10.000,0.000 \"/>"));
+        assert!(!result.contains(" A"));
+    }
+}
@@ -1,3 +1,9 @@
 pub mod error;
 pub mod structs;
-
+pub mod export;
+pub mod analysis;
+pub mod config;
+pub mod streaming;
+pub mod translate;
+pub mod transform;
+pub mod restart;
@@ -1,23 +1,450 @@
-use nc_parser::structs::{
-    line_parser::Line,
-    state::State,
-    
-};
-
-use std::{fs::File, io::{BufRead, BufReader,}};
-
-
-fn main() {
-    let file = File::open("test.txt").unwrap();
-    let buf_reader = BufReader::new(file);
-    let mut state = State::default();
-    buf_reader.lines().for_each(|line| {
-        let line = Line::new(line.unwrap());
-        let result = state.state_update(line).unwrap();
-        if let Some(state) = result {
-            println!("X{:.3}Y{:.3}", state.get_x(), state.get_y());
+use nc_parser::analysis::cycle_time::CycleTime;
+use nc_parser::analysis::operations::Operation;
+use nc_parser::analysis::statistics::Statistics;
+use nc_parser::analysis::tools::ToolSummary;
+use nc_parser::config::Config;
+use nc_parser::export::{csv, dxf::Dxf, json, nc, setup_sheet::SetupSheet, svg::Svg};
+use nc_parser::structs::dialect::DialectKind;
+use nc_parser::structs::interpreter::Interpreter;
+use nc_parser::structs::program::Program;
+use nc_parser::transform::affine::Transform;
+use nc_parser::transform::positioning;
+use nc_parser::restart::Restart;
+use nc_parser::translate::Translator;
+
+use clap::{Args, Parser, Subcommand};
+use serde::de::{value::StrDeserializer, IntoDeserializer};
+use serde::Deserialize;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+/// 検証で問題が見つかった場合の終了コード
+const EXIT_FINDINGS: u8 = 1;
+/// 入力や設定の読み込みに失敗した場合の終了コード
+const EXIT_ERROR: u8 = 2;
+
+/// NCプログラムの解析
+#[derive(Debug, Parser)]
+#[command(name = "nc_parser", version)]
+struct Cli {
+    /// 機械の設定ファイル（TOML）
+    #[arg(long, global = true)]
+    config: Option<PathBuf>,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// ストローク範囲、早送りの安全性、主軸と送りの指令を検証する
+    Check(Input),
+    /// 各軸の範囲と移動の統計を表示する
+    Stats {
+        #[command(flatten)]
+        input: Input,
+        /// 工具ごとの統計も表示する
+        #[arg(long)]
+        per_tool: bool,
+    },
+    /// XY平面に投影した工具経路をSVGで出力する
+    Plot {
+        #[command(flatten)]
+        input: Input,
+        #[command(flatten)]
+        output: Output,
+    },
+    /// 実行した各ブロックを出力する
+    Export {
+        #[command(flatten)]
+        input: Input,
+        #[command(flatten)]
+        format: Format,
+        #[command(flatten)]
+        output: Output,
+    },
+    /// 加工時間を見積もる
+    Time(Input),
+    /// 工具ごとの使用行、切削時間、送り速度、主軸回転数、最小のZを表示する
+    Tools(Input),
+    /// 工具交換、任意停止、コメントの行で区切った工程と統計を表示する
+    Operations {
+        #[command(flatten)]
+        input: Input,
+        /// 指定した工程（1から始まる番号）だけを実行するプログラムを出力する
+        #[arg(long, value_name = "N")]
+        extract: Option<usize>,
+        #[command(flatten)]
+        output: Output,
+    },
+    /// 作業者向けの段取りシートを出力する
+    Setup {
+        #[command(flatten)]
+        input: Input,
+        #[command(flatten)]
+        format: SheetFormat,
+        #[command(flatten)]
+        output: Output,
+    },
+    /// 別の制御装置のプログラムに変換する
+    Translate {
+        #[command(flatten)]
+        input: Input,
+        /// 変換先の制御装置
+        #[arg(long, value_parser = dialect)]
+        to: DialectKind,
+        #[command(flatten)]
+        output: Output,
+    },
+    /// 移動を絶対値または増分値の指令に書き直す
+    Convert {
+        #[command(flatten)]
+        input: Input,
+        #[command(flatten)]
+        positioning: Positioning,
+        #[command(flatten)]
+        output: Output,
+    },
+    /// 指定した行から再開する前置きと、その行以降のプログラムを出力する
+    Restart {
+        #[command(flatten)]
+        input: Input,
+        /// 再開する行番号（1から始まる）
+        #[arg(long)]
+        line: usize,
+        /// 位置決めする安全高さ（ワーク座標のZ）、省略の場合は再開する行までに到達したZの最大値
+        #[arg(long, allow_negative_numbers = true)]
+        safe_z: Option<f32>,
+        #[command(flatten)]
+        output: Output,
+    },
+    /// 全ての位置を鏡像、回転、拡大縮小、平行移動の順に変換する
+    Transform {
+        #[command(flatten)]
+        input: Input,
+        #[command(flatten)]
+        geometry: Geometry,
+        /// 鏡像、回転、拡大縮小の中心のX、Y
+        #[arg(long, value_delimiter = ',', value_name = "X,Y", default_values_t = [0.0, 0.0], allow_negative_numbers = true)]
+        center: Vec<f32>,
+        #[command(flatten)]
+        output: Output,
+    },
+}
+
+#[derive(Debug, Args)]
+struct Input {
+    /// NCプログラムのファイル、省略または"-"の場合は標準入力
+    file: Option<PathBuf>,
+}
+
+#[derive(Debug, Default, Args)]
+struct Output {
+    /// 出力先のファイル、省略の場合は標準出力
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+}
+
+#[derive(Debug, Args)]
+#[group(required = true, multiple = false)]
+struct Format {
+    /// JSON Linesで出力する
+    #[arg(long)]
+    json: bool,
+    /// CSVで出力する
+    #[arg(long)]
+    csv: bool,
+    /// DXFで出力する
+    #[arg(long)]
+    dxf: bool,
+    /// 式を評価し、繰り返しを展開したNCプログラムを出力する
+    #[arg(long)]
+    nc: bool,
+}
+
+#[derive(Debug, Args)]
+#[group(required = true, multiple = false)]
+struct SheetFormat {
+    /// HTMLで出力する
+    #[arg(long)]
+    html: bool,
+    /// Markdownで出力する
+    #[arg(long)]
+    markdown: bool,
+}
+
+#[derive(Debug, Args)]
+#[group(required = true, multiple = false)]
+struct Positioning {
+    /// 絶対値（G90）で書き直す
+    #[arg(long)]
+    absolute: bool,
+    /// 増分値（G91）で書き直す
+    #[arg(long)]
+    incremental: bool,
+}
+
+#[derive(Debug, Args)]
+#[group(required = true, multiple = true)]
+struct Geometry {
+    /// 中心を通るY軸に平行な線でXの座標を反転する
+    #[arg(long)]
+    mirror_x: bool,
+    /// 中心を通るX軸に平行な線でYの座標を反転する
+    #[arg(long)]
+    mirror_y: bool,
+    /// 中心の周りに回転する角度（反時計回りを正とする度数）
+    #[arg(long, allow_negative_numbers = true)]
+    rotate: Option<f32>,
+    /// 中心とZ0を基準にした拡大縮小の倍率
+    #[arg(long)]
+    scale: Option<f32>,
+    /// 平行移動量のX、Y、Z（Zは省略可）
+    #[arg(long, value_delimiter = ',', value_name = "X,Y[,Z]", allow_negative_numbers = true)]
+    translate: Option<Vec<f32>>,
+}
+
+impl Geometry {
+    /// 指定した変換を鏡像、回転、拡大縮小、平行移動の順に合成する
+    fn transform(&self, center: [f32; 2]) -> Result<Transform, String> {
+        let mut transform = Transform::default();
+        if self.mirror_x {
+            transform = transform.then(&Transform::mirror('X', center[0]).map_err(|e| e.to_string())?);
         }
-        
-    });    
+        if self.mirror_y {
+            transform = transform.then(&Transform::mirror('Y', center[1]).map_err(|e| e.to_string())?);
+        }
+        if let Some(degrees) = self.rotate {
+            transform = transform.then(&Transform::rotation(center, degrees));
+        }
+        if let Some(factor) = self.scale {
+            transform = transform.then(&Transform::scale(center, factor).map_err(|e| e.to_string())?);
+        }
+        if let Some(offset) = &self.translate {
+            if !(2..=3).contains(&offset.len()) {
+                return Err("--translate requires X,Y or X,Y,Z".to_string());
+            }
+            let z = offset.get(2).copied().unwrap_or(0.0);
+            transform = transform.then(&Transform::translation(offset[0], offset[1], z));
+        }
+        Ok(transform)
+    }
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    match run(cli) {
+        Ok(code) => code,
+        Err(message) => {
+            eprintln!("{}", message);
+            ExitCode::from(EXIT_ERROR)
+        },
+    }
+}
+
+/// サブコマンドを実行し、終了コードを返す
+fn run(cli: Cli) -> Result<ExitCode, String> {
+    let config = match &cli.config {
+        Some(path) => Config::from_toml(&read(path)?).map_err(|e| e.to_string())?,
+        None => Config::default(),
+    };
+
+    match cli.command {
+        Command::Check(input) => {
+            let program = program(&input, &config)?;
+            let mut findings = Vec::new();
+            let mut extents = None;
+            if let Some(envelope) = config.get_envelope().map_err(|e| e.to_string())? {
+                let report = envelope.check(&program);
+                findings.extend(report.get_violation().map(|violation| violation.to_string()));
+                extents = report.get_extents();
+            }
+            if let Some(safety) = config.get_safety() {
+                findings.extend(safety.check(&program).iter().map(|finding| finding.to_string()));
+            }
+            findings.extend(config.get_lint().check(&program).iter().map(|diagnostic| diagnostic.to_string()));
+            write(&Output::default(), |writer| {
+                findings.iter().try_for_each(|finding| writeln!(writer, "{}", finding))?;
+                match extents {
+                    Some(extents) => write!(writer, "Machine extents :\n{}", extents),
+                    None => Ok(()),
+                }
+            })?;
+            if findings.is_empty() {
+                Ok(ExitCode::SUCCESS)
+            } else {
+                Ok(ExitCode::from(EXIT_FINDINGS))
+            }
+        },
+        Command::Stats { input, per_tool } => {
+            let program = program(&input, &config)?;
+            write(&Output::default(), |writer| {
+                write!(writer, "{}", Statistics::new(&program))?;
+                match per_tool {
+                    true => Statistics::per_tool(&program).iter().try_for_each(|(tool, statistics)| {
+                        write!(writer, "\n[T{}]\n{}", tool, statistics)
+                    }),
+                    false => Ok(()),
+                }
+            })
+        },
+        Command::Plot { input, output } => {
+            let program = program(&input, &config)?;
+            write(&output, |writer| Svg::new(&program).write(writer))
+        },
+        Command::Export { input, format, output } => {
+            let program = program(&input, &config)?;
+            write(&output, |writer| {
+                if format.json {
+                    json::write_lines(writer, &program)
+                } else if format.csv {
+                    csv::write(writer, &program)
+                } else if format.nc {
+                    nc::write(writer, &program, config.get_format())
+                } else {
+                    Dxf::new(&program.states()).write(writer)
+                }
+            })
+        },
+        Command::Time(input) => {
+            let program = program(&input, &config)?;
+            let cycle_time = CycleTime::new(&program, config.get_machine());
+            write(&Output::default(), |writer| write!(writer, "{}", cycle_time))
+        },
+        Command::Tools(input) => {
+            let program = program(&input, &config)?;
+            let summaries: Vec<String> = ToolSummary::of_program(&program).iter()
+                .map(|summary| summary.to_string())
+                .collect();
+            write(&Output::default(), |writer| write!(writer, "{}", summaries.join("\n")))
+        },
+        Command::Operations { input, extract, output } => {
+            let source = source(&input)?;
+            let interpreter = || Interpreter::new(config.get_machine().clone());
+            let program = Program::with_interpreter(&source, interpreter()).map_err(|e| e.to_string())?;
+            let operations = Operation::of_program(&program);
+            match extract {
+                Some(number) => {
+                    let operation = number.checked_sub(1)
+                        .and_then(|index| operations.get(index))
+                        .ok_or_else(|| format!("operation {} does not exist", number))?;
+                    let extracted = operation.extract(&source, interpreter()).map_err(|e| e.to_string())?;
+                    write(&output, |writer| writer.write_all(extracted.as_bytes()))
+                },
+                None => write(&output, |writer| {
+                    operations.iter().enumerate().try_for_each(|(index, operation)| {
+                        let separator = if index == 0 { "" } else { "\n" };
+                        write!(writer, "{}[{}] {}", separator, index + 1, operation)
+                    })
+                }),
+            }
+        },
+        Command::Setup { input, format, output } => {
+            let program = program(&input, &config)?;
+            let sheet = SetupSheet::new(&program, config.get_machine());
+            write(&output, |writer| {
+                if format.html {
+                    sheet.write_html(writer)
+                } else {
+                    sheet.write_markdown(writer)
+                }
+            })
+        },
+        Command::Translate { input, to, output } => {
+            let mut translator = Translator::new(config.get_machine().clone(), to, config.get_translation().clone())
+                .map_err(|e| e.to_string())?;
+            translator.format_set(config.get_format().clone());
+            let translation = translator.translate(&source(&input)?);
+            write(&output, |writer| {
+                translation.get_lines().iter().try_for_each(|line| writeln!(writer, "{}", line))
+            })?;
+            translation.get_issues().iter().for_each(|issue| eprintln!("{}", issue));
+            if translation.get_issues().is_empty() {
+                Ok(ExitCode::SUCCESS)
+            } else {
+                Ok(ExitCode::from(EXIT_FINDINGS))
+            }
+        },
+        Command::Convert { input, positioning: mode, output } => {
+            let source = source(&input)?;
+            let interpreter = Interpreter::new(config.get_machine().clone());
+            let converted = if mode.incremental {
+                positioning::incremental(&source, interpreter)
+            } else {
+                positioning::absolute(&source, interpreter)
+            }.map_err(|e| e.to_string())?;
+            write(&output, |writer| writer.write_all(converted.as_bytes()))
+        },
+        Command::Restart { input, line, safe_z, output } => {
+            let source = source(&input)?;
+            let interpreter = Interpreter::new(config.get_machine().clone());
+            let mut restart = Restart::new(&source, line, interpreter).map_err(|e| e.to_string())?;
+            if let Some(safe_z) = safe_z {
+                restart.safe_z_set(safe_z);
+            }
+            let restarted = restart.apply(&source).map_err(|e| e.to_string())?;
+            write(&output, |writer| writer.write_all(restarted.as_bytes()))
+        },
+        Command::Transform { input, geometry, center, output } => {
+            let [x, y] = center[..] else {
+                return Err("--center requires X,Y".to_string());
+            };
+            let transform = geometry.transform([x, y])?;
+            let interpreter = Interpreter::new(config.get_machine().clone());
+            let transformed = transform.apply(&source(&input)?, interpreter).map_err(|e| e.to_string())?;
+            write(&output, |writer| writer.write_all(transformed.as_bytes()))
+        },
+    }
 }
 
+/// 設定ファイルと同じ名前の制御装置の種類を返す
+fn dialect(value: &str) -> Result<DialectKind, String> {
+    let deserializer: StrDeserializer<serde::de::value::Error> = value.into_deserializer();
+    DialectKind::deserialize(deserializer).map_err(|e| e.to_string())
+}
+
+/// ファイルを文字列として読み込む
+fn read(path: &PathBuf) -> Result<String, String> {
+    fs::read_to_string(path).map_err(|e| format!("{} : {}", path.display(), e))
+}
+
+/// 入力のファイルまたは標準入力を読み込む
+fn source(input: &Input) -> Result<String, String> {
+    match &input.file {
+        Some(path) if path.as_os_str() != "-" => read(path),
+        _ => {
+            let mut source = String::new();
+            io::stdin().read_to_string(&mut source).map_err(|e| e.to_string())?;
+            Ok(source)
+        },
+    }
+}
+
+/// 入力を読み込み、機械の設定に従ってプログラムを実行する
+fn program(input: &Input, config: &Config) -> Result<Program, String> {
+    let interpreter = Interpreter::new(config.get_machine().clone());
+    Program::with_interpreter(&source(input)?, interpreter).map_err(|e| e.to_string())
+}
+
+/// 出力先に書き込む
+/// 標準出力の読み手が先に終了した場合（BrokenPipe）は正常終了とする
+fn write<F>(output: &Output, f: F) -> Result<ExitCode, String>
+where
+    F: FnOnce(&mut dyn Write) -> io::Result<()>,
+{
+    let result = match &output.output {
+        Some(path) => {
+            let mut file = fs::File::create(path).map_err(|e| format!("{} : {}", path.display(), e))?;
+            f(&mut file)
+        },
+        None => {
+            let mut stdout = io::stdout().lock();
+            f(&mut stdout).and_then(|_| stdout.flush())
+        },
+    };
+    match result {
+        Err(e) if e.kind() != io::ErrorKind::BrokenPipe => Err(e.to_string()),
+        _ => Ok(ExitCode::SUCCESS),
+    }
+}
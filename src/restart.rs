@@ -0,0 +1,269 @@
+use crate::error::Error;
+use crate::structs::dialect::DialectKind;
+use crate::structs::interpreter::Interpreter;
+use crate::structs::line_parser::g_code;
+use crate::structs::machine_profile::MachineProfile;
+use crate::structs::program::Program;
+use crate::structs::state::State;
+use crate::transform::rounded;
+use crate::translate::{TranslationTable, Translator};
+
+/// 再開の前置きで復元できないモーダルなGコードのグループ
+/// 取り消しのコードと、有効な場合に再開できないコード（工具径補正、固定サイクル）
+const UNRESTORABLE_GROUPS: [(&str, &[&str]); 2] = [
+    ("G40", &["G41", "G42"]),
+    ("G80", &["G73", "G74", "G76", "G81", "G82", "G83", "G84", "G85", "G86", "G87", "G88", "G89"]),
+];
+
+/// 状態に含まれない座標系を設定するGコード
+/// 再開する行までに指令した場合は再開できない
+const COORDINATE_CODES: [&str; 2] = ["G52", "G92"];
+
+/// 途中の行からの再開
+/// 再開する行の直前までプログラムを実行した状態から、工具交換、ワーク座標系、安全高さからの位置決め、
+/// 主軸とクーラントの起動、モーダルな指令の復元を行う前置きを作り、再開する行以降のプログラムを続ける
+#[derive(Debug, PartialEq, Clone)]
+pub struct Restart {
+    /// 再開する行（1から始まる行番号）
+    line_number: usize,
+    /// 再開する行の直前の状態
+    state: State,
+    /// 再開する行までに工具交換（M06）を指令したか
+    tool_change: bool,
+    /// 再開する行までに到達したZの最大値、Zを指令していない場合はNone
+    highest_z: Option<f32>,
+    /// 前置きを出力する制御装置
+    dialect: DialectKind,
+    /// 位置決めする安全高さ（ワーク座標のZ）、Noneの場合は到達したZの最大値とする
+    safe_z: Option<f32>,
+}
+
+impl Restart {
+    /// 再開する行を最初に実行する直前までインタプリタでプログラムを実行し、インスタンス化
+    /// 行を実行しない場合と、座標変換、極座標、工具径補正、固定サイクルが有効な場合、
+    /// G52、G92で座標系を設定した場合はエラーを返す
+    pub fn new(source: &str, line_number: usize, interpreter: Interpreter) -> Result<Self, Error> {
+        let dialect = interpreter.get_profile().get_dialect();
+        let (program, interpreter) = Program::until_line(source, interpreter, line_number)?
+            .ok_or_else(|| Error::InvalidConfig(format!("line {} is not executed", line_number)))?;
+        let state = interpreter.get_state();
+
+        let codes = || program.get_blocks().iter()
+            .flat_map(|block| block.get_block().words_of('G').map(|word| g_code(word.get_value())));
+        let unrestorable = UNRESTORABLE_GROUPS.iter().any(|(cancel, group)| {
+            codes()
+                .filter(|code| code == cancel || group.contains(&code.as_str()))
+                .last()
+                .is_some_and(|code| code != *cancel)
+        }) || codes().any(|code| COORDINATE_CODES.contains(&code.as_str()));
+        let transformed = !interpreter.get_frame().is_identity()
+            || !state.get_transform().is_identity()
+            || state.get_polar();
+        if unrestorable || transformed {
+            return Err(Error::Line(line_number, Box::new(Error::InvalidCode('G'))));
+        }
+
+        // 初期状態のZは実行した位置ではないので、Zを指令したブロックの位置のみとする
+        let highest_z = program.get_blocks().iter()
+            .filter(|block| block.get_block().words_of('Z').next().is_some())
+            .map(|block| block.get_state().get_z())
+            .reduce(f32::max);
+        let tool_change = program.tool_changes().next().is_some();
+        Ok(Self { line_number, state, tool_change, highest_z, dialect, safe_z: None })
+    }
+
+    /// 位置決めする安全高さを設定
+    pub fn safe_z_set(&mut self, safe_z: f32) {
+        self.safe_z = Some(safe_z);
+    }
+
+    /// 再開する行の直前の状態を返す
+    pub fn get_state(&self) -> State {
+        self.state
+    }
+
+    /// 再開の前置きの行を機械の制御装置の書式で返す
+    /// 主軸を機械の基準点のZに退避してから工具を交換し、安全高さでXYに位置決めしてから再開する位置まで下降する
+    pub fn preamble(&self) -> Result<Vec<String>, Error> {
+        self.translated(self.fanuc_preamble())
+    }
+
+    /// 途中で終わるプログラムの後置きの行を機械の制御装置の書式で返す
+    /// クーラントと主軸を停止し、主軸を機械の基準点のZに退避してからプログラムを終了する
+    pub fn postamble(&self) -> Result<Vec<String>, Error> {
+        self.translated(["M09", "M05", "G91G28Z0.", "M30"].map(|line| line.to_string()).to_vec())
+    }
+
+    /// 再開の前置きと、再開する行以降のプログラムを返す
+    /// 再開する行以降は記述のまま続ける
+    pub fn apply(&self, source: &str) -> Result<String, Error> {
+        let mut result: String = self.preamble()?.iter().map(|line| format!("{}\n", line)).collect();
+        source.split_inclusive('\n')
+            .skip(self.line_number - 1)
+            .for_each(|line| result.push_str(line));
+        Ok(result)
+    }
+
+    /// ファナック系の書式の行を機械の制御装置の書式に変換する
+    fn translated(&self, lines: Vec<String>) -> Result<Vec<String>, Error> {
+        if self.dialect == DialectKind::Fanuc {
+            return Ok(lines);
+        }
+        let translator = Translator::new(MachineProfile::default(), self.dialect, TranslationTable::default())?;
+        let translation = translator.translate(&lines.join("\n"));
+        match translation.get_issues().first() {
+            Some(issue) => Err(Error::InvalidConfig(format!("restart {}", issue))),
+            None => Ok(translation.get_lines().to_vec()),
+        }
+    }
+
+    /// ファナック系の書式の前置きの行を返す
+    fn fanuc_preamble(&self) -> Vec<String> {
+        let state = self.state;
+        let safe_z = self.safe_z.or(self.highest_z).unwrap_or(state.get_z()).max(state.get_z());
+        // Zも工具長補正も指令していない場合は、Zの位置が決まっていないので下降しない
+        let approach = self.highest_z.is_some() || state.get_tool_length_offset() > 0;
+        let mut lines = vec![
+            format!("(RESTART AT LINE {})", self.line_number),
+            format!("G40G49G80{}", state.get_plane().code()),
+            "G91G28Z0.".to_string(),
+        ];
+
+        let (tool, active_tool) = (state.get_tool(), state.get_active_tool());
+        if self.tool_change && active_tool > 0 {
+            lines.push(format!("T{}M06", active_tool));
+            if tool != active_tool {
+                // 次の工具の選択
+                lines.push(format!("T{}", tool));
+            }
+        } else if tool > 0 {
+            lines.push(format!("T{}", tool));
+        }
+
+        lines.push(format!(
+            "G90G00{}X{}Y{}",
+            state.get_work_offset().code(),
+            rounded(state.get_x()),
+            rounded(state.get_y()),
+        ));
+        if state.get_spindle().is_running() {
+            lines.push(format!("S{}{}", state.get_spindle_speed(), state.get_spindle().code()));
+        } else if state.get_spindle_speed() > 0.0 {
+            lines.push(format!("S{}", state.get_spindle_speed()));
+        }
+        match state.get_tool_length_offset() {
+            _ if !approach => {},
+            0 => lines.push(format!("Z{}", rounded(safe_z))),
+            offset => lines.push(format!("G43Z{}H{}", rounded(safe_z), offset)),
+        }
+        if state.get_coolant().is_on() {
+            lines.push(state.get_coolant().code().to_string());
+        }
+
+        let feed = match state.get_feed() > 0.0 {
+            true => format!("F{}", rounded(state.get_feed())),
+            false => String::new(),
+        };
+        if approach && state.get_z() < safe_z {
+            // 送り速度の指令がない場合は早送りで下降する
+            let motion = if feed.is_empty() { "G00" } else { "G01" };
+            lines.push(format!("{}Z{}{}", motion, rounded(state.get_z()), feed));
+        }
+        let positioning = if state.get_incremental() { "G91" } else { "" };
+        lines.push(format!("{}{}{}", positioning, state.get_motion().code(), feed));
+        lines
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::structs::machine_profile::MachineProfile;
+
+    const SOURCE: &str = "O1000\n\
+        T1M06\n\
+        T2\n\
+        G90G55G00X0Y0S1500M03\n\
+        G43Z50.H1M08\n\
+        Z5.\n\
+        G01Z-2.F100.\n\
+        X20.F300.\n\
+        Y10.\n\
+        G00Z50.\n\
+        M30\n";
+
+    #[test]
+    fn restart_preamble() {
+        let restart = Restart::new(SOURCE, 9, Interpreter::default()).unwrap();
+        assert_eq!(restart.preamble().unwrap(), [
+            "(RESTART AT LINE 9)",
+            "G40G49G80G17",
+            "G91G28Z0.",
+            "T1M06",
+            "T2",
+            "G90G00G55X20.Y0.",
+            "S1500M03",
+            "G43Z50.H1",
+            "M08",
+            "G01Z-2.F300.",
+            "G01F300.",
+        ]);
+    }
+
+    #[test]
+    fn restart_before_length_compensation() {
+        // 最初のG43より前から再開する場合はZに位置決めしない
+        let source = "T1M06\nG90G54G00X0Y0\nG43Z50.H1\nZ5.\n";
+        let mut restart = Restart::new(source, 3, Interpreter::default()).unwrap();
+        let expected = [
+            "(RESTART AT LINE 3)",
+            "G40G49G80G17",
+            "G91G28Z0.",
+            "T1M06",
+            "G90G00G54X0.Y0.",
+            "G00",
+        ];
+        assert_eq!(restart.preamble().unwrap(), expected);
+        restart.safe_z_set(10.0);
+        assert_eq!(restart.preamble().unwrap(), expected);
+    }
+
+    #[test]
+    fn restart_apply() {
+        let source = "T3\nG91G00X10.Y10.\nZ-5.\nG01X5.F200.\nX5.\n";
+        let mut restart = Restart::new(source, 5, Interpreter::default()).unwrap();
+        restart.safe_z_set(10.0);
+        assert_eq!(
+            restart.apply(source).unwrap(),
+            "(RESTART AT LINE 5)\nG40G49G80G17\nG91G28Z0.\nT3\nG90G00G54X15.Y10.\nZ10.\n\
+             G01Z-5.F200.\nG91G01F200.\nX5.\n"
+        );
+    }
+
+    #[test]
+    fn restart_dialect() {
+        let mut profile = MachineProfile::default();
+        profile.dialect_set(DialectKind::Linuxcnc);
+        let restart = Restart::new("G00X1.Y2.Z5.\nG01Z-1.F50.\nX3.", 3, Interpreter::new(profile)).unwrap();
+        let preamble = restart.preamble().unwrap();
+        assert_eq!(preamble.first().map(|line| line.as_str()), Some("(RESTART AT LINE 3)"));
+        assert!(preamble.contains(&"G01Z-1.F50.".to_string()));
+        assert_eq!(restart.postamble().unwrap().last().map(|line| line.as_str()), Some("M30"));
+    }
+
+    #[test]
+    fn restart_abnormality() {
+        let datas = [
+            ("G00X1.\nM30\nX2.", 4, Error::InvalidConfig("line 4 is not executed".to_string())),
+            ("G00X1.", 0, Error::InvalidConfig("line 0 is not executed".to_string())),
+            ("G41G01X10.D1F100.\nY10.", 2, Error::Line(2, Box::new(Error::InvalidCode('G')))),
+            ("G81X0Y0Z-5.R2.F100.\nX10.", 2, Error::Line(2, Box::new(Error::InvalidCode('G')))),
+            ("G68X0Y0R90.\nG01X10.F100.", 2, Error::Line(2, Box::new(Error::InvalidCode('G')))),
+            ("G52X10.\nG01X10.F100.", 2, Error::Line(2, Box::new(Error::InvalidCode('G')))),
+        ];
+        datas.iter().for_each(|(source, line_number, expected)| {
+            assert_eq!(Restart::new(source, *line_number, Interpreter::default()), Err(expected.clone()));
+        });
+        assert!(Restart::new("G41G01X10.D1F100.\nG40X0\nY10.", 3, Interpreter::default()).is_ok());
+    }
+}
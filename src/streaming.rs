@@ -0,0 +1,2 @@
+pub mod sender;
+pub mod simulator;
@@ -0,0 +1,195 @@
+use std::collections::VecDeque;
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::Error;
+use crate::streaming::simulator::RX_BUFFER_SIZE;
+use crate::structs::dialect::DialectKind;
+use crate::structs::interpreter::Interpreter;
+use crate::structs::line_parser::Line;
+use crate::structs::machine_profile::MachineProfile;
+use crate::structs::state::State;
+
+/// コントローラとの通信
+pub trait Connection {
+    /// 文字列を送信する
+    fn write(&mut self, data: &str) -> Result<(), Error>;
+
+    /// 受信した応答の1行を返す
+    /// 応答がない場合はNoneを返す
+    fn read_line(&mut self) -> Result<Option<String>, Error>;
+}
+
+/// 送信の結果
+#[derive(Debug, Default, PartialEq, Clone, Serialize, Deserialize)]
+pub struct StreamReport {
+    /// 送信した行数
+    sent: usize,
+    /// エラーを返した行番号と応答
+    errors: Vec<(usize, String)>,
+    /// 応答待ちの文字数の最大値
+    peak: usize,
+}
+
+impl StreamReport {
+    /// 送信した行数を返す
+    pub fn get_sent(&self) -> usize {
+        self.sent
+    }
+
+    /// エラーを返した行番号と応答を返す
+    pub fn get_errors(&self) -> &[(usize, String)] {
+        &self.errors
+    }
+
+    /// 応答待ちの文字数の最大値を返す
+    pub fn get_peak(&self) -> usize {
+        self.peak
+    }
+}
+
+/// 文字数を数える方式でGrblへプログラムを送信する
+/// 受信バッファに収まる範囲で応答を待たずに行を送り、"ok"を受信した行をインタプリタで実行してコントローラの位置を追跡する
+#[derive(Debug, Clone)]
+pub struct Sender {
+    interpreter: Interpreter,
+    /// 応答待ちの行番号と送信した行
+    pending: VecDeque<(usize, String)>,
+    report: StreamReport,
+}
+
+impl Sender {
+    /// 機械の設定からインスタンス化
+    pub fn new(mut profile: MachineProfile) -> Self {
+        profile.dialect_set(DialectKind::Grbl);
+        Self {
+            interpreter: Interpreter::new(profile),
+            pending: VecDeque::new(),
+            report: StreamReport::default(),
+        }
+    }
+
+    /// コントローラが実行を完了した行までの状態を返す
+    pub fn get_state(&self) -> State {
+        self.interpreter.get_state()
+    }
+
+    /// 応答待ちの文字数を返す
+    pub fn get_buffered(&self) -> usize {
+        self.pending.iter().map(|(_, line)| line.len() + 1).sum()
+    }
+
+    /// プログラムを送信し、全ての行の応答を受信するまで待つ
+    /// コメントと空白を除いて送信し、空になる行は送信しない
+    pub fn stream<C: Connection>(&mut self, source: &str, connection: &mut C) -> Result<StreamReport, Error> {
+        for (index, line) in source.lines().enumerate() {
+            let line_number = index + 1;
+            let line = strip(line);
+            if line.is_empty() {
+                continue;
+            }
+            let length = line.len() + 1;
+            if length > RX_BUFFER_SIZE {
+                return Err(Error::Line(line_number, Box::new(Error::BufferOverflow(RX_BUFFER_SIZE))));
+            }
+            while self.get_buffered() + length > RX_BUFFER_SIZE {
+                self.acknowledge(connection)?;
+            }
+            connection.write(&format!("{}\n", line))?;
+            self.pending.push_back((line_number, line));
+            self.report.sent += 1;
+            self.report.peak = self.report.peak.max(self.get_buffered());
+        }
+        while !self.pending.is_empty() {
+            self.acknowledge(connection)?;
+        }
+        Ok(self.report.clone())
+    }
+
+    /// 応答を1行受信し、"ok"の場合は最も古い応答待ちの行を実行する
+    /// 状態の報告などの応答待ちの行に対応しない応答は読み飛ばす
+    fn acknowledge<C: Connection>(&mut self, connection: &mut C) -> Result<(), Error> {
+        let response = connection.read_line()?.ok_or(Error::NoResponse)?;
+        if response != "ok" && !response.starts_with("error:") {
+            return Ok(());
+        }
+        let (line_number, line) = self.pending.pop_front().ok_or(Error::NoResponse)?;
+        if response == "ok" {
+            self.interpreter.execute(&Line::new(line))
+                .map_err(|e| Error::Line(line_number, Box::new(e)))?;
+        } else {
+            self.report.errors.push((line_number, response));
+        }
+        Ok(())
+    }
+}
+
+/// 括弧の中と";"から行末までのコメント、空白を除き、大文字にした行を返す
+fn strip(line: &str) -> String {
+    let mut comment = false;
+    line.chars()
+        .take_while(|&c| c != ';')
+        .filter(|&c| match c {
+            '(' => {
+                comment = true;
+                false
+            },
+            ')' => {
+                comment = false;
+                false
+            },
+            _ => !comment && !c.is_whitespace(),
+        })
+        .map(|c| c.to_ascii_uppercase())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::streaming::simulator::SimulatedController;
+
+    #[test]
+    fn sender_stream() {
+        let source = (0..40)
+            .map(|i| format!("G1 X{} Y{} F800 (STEP {})", i, i * 2, i))
+            .collect::<Vec<_>>()
+            .join("\n");
+        let mut controller = SimulatedController::new(MachineProfile::default());
+        let mut sender = Sender::new(MachineProfile::default());
+        let report = sender.stream(&source, &mut controller).unwrap();
+        assert_eq!(report.get_sent(), 40);
+        assert!(report.get_errors().is_empty());
+        // 応答を待たずに複数の行を送信し、受信バッファはあふれない
+        assert!(report.get_peak() > 20 && report.get_peak() <= RX_BUFFER_SIZE);
+        assert_eq!(sender.get_state(), controller.get_state());
+        assert_eq!((sender.get_state().get_x(), sender.get_state().get_y()), (39.0, 78.0));
+        assert_eq!(sender.get_buffered(), 0);
+    }
+
+    #[test]
+    fn sender_stream_error() {
+        let mut controller = SimulatedController::new(MachineProfile::default());
+        let mut sender = Sender::new(MachineProfile::default());
+        let report = sender.stream("G0 X5\n\n; SETUP\nG2 X20\n$H\nG0 Y5", &mut controller).unwrap();
+        assert_eq!(report.get_sent(), 4);
+        assert_eq!(report.get_errors(), [(4, "error:33".to_string())]);
+        assert_eq!((sender.get_state().get_x(), sender.get_state().get_y()), (5.0, 5.0));
+
+        // 送り保持中は応答がない
+        controller.write("!").unwrap();
+        assert_eq!(sender.stream("G0 X1", &mut controller), Err(Error::NoResponse));
+    }
+
+    #[test]
+    fn sender_strip() {
+        let datas = [
+            ("g1 x10 (CUT) y5 ; END", "G1X10Y5"),
+            ("(ONLY COMMENT)", ""),
+            ("$J=G91 X1 F100", "$J=G91X1F100"),
+        ];
+        datas.iter().for_each(|&(line, expected)| {
+            assert_eq!(strip(line), expected);
+        })
+    }
+}
@@ -0,0 +1,176 @@
+use std::collections::VecDeque;
+
+use crate::error::Error;
+use crate::streaming::sender::Connection;
+use crate::structs::dialect::grbl::system_command;
+use crate::structs::dialect::DialectKind;
+use crate::structs::interpreter::Interpreter;
+use crate::structs::line_parser::Line;
+use crate::structs::machine_profile::MachineProfile;
+use crate::structs::state::State;
+
+/// Grblの受信バッファの大きさ（バイト）
+pub const RX_BUFFER_SIZE: usize = 128;
+
+/// ソフトリセット後の起動メッセージ
+const WELCOME: &str = "Grbl 1.1h ['$' for help]";
+
+/// Grblの受信バッファと応答を模擬するコントローラ
+/// 実行した行はGrblの方言のインタプリタで解釈し、位置を追跡する
+#[derive(Debug, Clone)]
+pub struct SimulatedController {
+    interpreter: Interpreter,
+    /// 受信済みで未実行の文字列
+    buffer: String,
+    responses: VecDeque<String>,
+    /// 送り保持中
+    hold: bool,
+}
+
+impl SimulatedController {
+    /// 機械の設定からインスタンス化
+    pub fn new(mut profile: MachineProfile) -> Self {
+        profile.dialect_set(DialectKind::Grbl);
+        Self {
+            interpreter: Interpreter::new(profile),
+            buffer: String::new(),
+            responses: VecDeque::new(),
+            hold: false,
+        }
+    }
+
+    /// 実行済みの行の状態を返す
+    pub fn get_state(&self) -> State {
+        self.interpreter.get_state()
+    }
+
+    /// 受信バッファの使用量を返す
+    pub fn get_buffered(&self) -> usize {
+        self.buffer.len()
+    }
+
+    /// 送り保持中であるかの真偽値を返す
+    pub fn is_hold(&self) -> bool {
+        self.hold
+    }
+
+    /// 受信バッファの先頭の1行を実行し、応答を返す
+    /// 送り保持中と、改行までを受信していない場合はNoneを返す
+    pub fn step(&mut self) -> Option<String> {
+        if self.hold {
+            return None;
+        }
+        let end = self.buffer.find('\n')?;
+        let line: String = self.buffer.drain(..=end).collect();
+        Some(self.execute(line.trim_end()))
+    }
+
+    /// 1行を実行し、"ok"または"error:"とエラー番号を返す
+    /// システムコマンドは位置に影響しないため常に"ok"とする
+    fn execute(&mut self, line: &str) -> String {
+        if system_command(line).is_some() {
+            return "ok".to_string();
+        }
+        match self.interpreter.execute(&Line::new(line.to_string())) {
+            Ok(_) => "ok".to_string(),
+            Err(e) => format!("error:{}", error_code(&e)),
+        }
+    }
+
+    /// 状態の報告を返す
+    /// 例: "<Idle|WPos:10.000,0.000,0.000|FS:500,0>"
+    fn status(&self) -> String {
+        let state = self.get_state();
+        let machine_state = match (self.hold, self.buffer.contains('\n')) {
+            (true, _) => "Hold:0",
+            (false, true) => "Run",
+            (false, false) => "Idle",
+        };
+        format!(
+            "<{}|WPos:{:.3},{:.3},{:.3}|FS:{},{}>",
+            machine_state,
+            state.get_x(),
+            state.get_y(),
+            state.get_z(),
+            state.get_feed(),
+            state.get_spindle_speed(),
+        )
+    }
+}
+
+impl Connection for SimulatedController {
+    /// 文字列を受信する
+    /// リアルタイムコマンドは受信バッファに入れずに即座に処理し、受信バッファがあふれた場合はエラーを返す
+    fn write(&mut self, data: &str) -> Result<(), Error> {
+        for c in data.chars() {
+            match c {
+                '?' => self.responses.push_back(self.status()),
+                '!' => self.hold = true,
+                '~' => self.hold = false,
+                '\u{18}' => {
+                    self.buffer.clear();
+                    self.responses.clear();
+                    self.hold = false;
+                    self.responses.push_back(WELCOME.to_string());
+                },
+                _ if self.buffer.len() >= RX_BUFFER_SIZE => return Err(Error::BufferOverflow(RX_BUFFER_SIZE)),
+                _ => self.buffer.push(c),
+            }
+        }
+        Ok(())
+    }
+
+    /// 未読の応答、なければ次の1行を実行した応答を返す
+    fn read_line(&mut self) -> Result<Option<String>, Error> {
+        Ok(self.responses.pop_front().or_else(|| self.step()))
+    }
+}
+
+/// Grblのエラー番号を返す
+fn error_code(error: &Error) -> u8 {
+    match error {
+        // 数値の書式の誤り
+        Error::ParseFloatError(_) | Error::ParseIntError(_) => 2,
+        // 対応していないコマンド
+        Error::InvalidCode(_) => 20,
+        // 円弧の終点の誤り
+        Error::InvalidArc => 33,
+        // コマンドの文字がない
+        _ => 1,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn simulated_controller_write() {
+        let mut controller = SimulatedController::new(MachineProfile::default());
+        controller.write("G1 X10 F500\nG2 X20\n$H\n").unwrap();
+        assert_eq!(controller.get_buffered(), 22);
+        let datas = [Some("ok"), Some("error:33"), Some("ok"), None];
+        datas.iter().for_each(|expected| {
+            assert_eq!(controller.read_line().unwrap().as_deref(), *expected);
+        });
+        assert_eq!(controller.get_state().get_x(), 10.0);
+
+        let result = controller.write(&"X".repeat(RX_BUFFER_SIZE + 1));
+        assert_eq!(result, Err(Error::BufferOverflow(RX_BUFFER_SIZE)));
+    }
+
+    #[test]
+    fn simulated_controller_realtime() {
+        let mut controller = SimulatedController::new(MachineProfile::default());
+        controller.write("!G0 X1\n?").unwrap();
+        assert!(controller.is_hold());
+        assert_eq!(controller.read_line().unwrap().as_deref(), Some("<Hold:0|WPos:0.000,0.000,0.000|FS:0,0>"));
+        assert_eq!(controller.read_line().unwrap(), None);
+        controller.write("~").unwrap();
+        assert_eq!(controller.read_line().unwrap().as_deref(), Some("ok"));
+        controller.write("G0 Y2\n\u{18}").unwrap();
+        assert_eq!(controller.get_buffered(), 0);
+        assert_eq!(controller.read_line().unwrap().as_deref(), Some(WELCOME));
+        assert_eq!((controller.get_state().get_x(), controller.get_state().get_y()), (1.0, 0.0));
+    }
+}
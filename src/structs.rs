@@ -1,4 +1,19 @@
 mod coordinate;
 mod positioning_mode;
+pub mod motion_mode;
+pub mod plane;
+pub mod canned_cycle;
+pub mod compensation;
+pub mod auxiliary;
+pub mod work_offset;
 pub mod state;
-pub mod line_parser;
\ No newline at end of file
+pub mod line_parser;
+pub mod expression;
+pub mod dialect;
+pub mod arc;
+pub mod block;
+pub mod frame;
+pub mod programmable_transform;
+pub mod machine_profile;
+pub mod interpreter;
+pub mod program;
\ No newline at end of file
@@ -0,0 +1,282 @@
+use std::f32::consts::PI;
+
+use crate::structs::motion_mode::Motion;
+use crate::structs::plane::Plane;
+use crate::structs::state::State;
+
+/// 平面（G17、G18、G19）上の円弧の構造体
+/// 中心と角度は平面の第1軸と第2軸の座標とし、平面に垂直な軸が変化する場合はヘリカル補間とする
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Arc {
+    plane: Plane,
+    /// 平面の第1軸と第2軸の中心
+    center: (f32, f32),
+    radius: f32,
+    /// 始点の角度（ラジアン）
+    start_angle: f32,
+    /// 回転角（ラジアン）、反時計回りを正とする
+    sweep: f32,
+    /// 平面の第1軸、第2軸、垂直な軸の順の始点
+    start: (f32, f32, f32),
+    /// 平面の第1軸、第2軸、垂直な軸の順の終点
+    end: (f32, f32, f32),
+}
+
+impl Arc {
+    /// 移動前と移動後の状態から円弧をインスタンス化
+    /// 移動後の状態が円弧補間でない場合、半径が0の場合はNoneを返す
+    pub fn new(start: &State, end: &State) -> Option<Self> {
+        let motion = end.get_motion();
+        if !motion.is_arc() {
+            return None;
+        }
+        let plane = end.get_plane();
+        let (offset_first, offset_second, _) = local(plane, (end.get_i(), end.get_j(), end.get_k()));
+        let radius = offset_first.hypot(offset_second);
+        if radius == 0.0 {
+            return None;
+        }
+        let start = local(plane, (start.get_x(), start.get_y(), start.get_z()));
+        let end = local(plane, (end.get_x(), end.get_y(), end.get_z()));
+        let center = (start.0 + offset_first, start.1 + offset_second);
+        let start_angle = (start.1 - center.1).atan2(start.0 - center.0);
+        let end_angle = (end.1 - center.1).atan2(end.0 - center.0);
+        let mut sweep = end_angle - start_angle;
+        let full = (start.0 - end.0).hypot(start.1 - end.1) < 1.0e-4;
+        if motion == Motion::CounterClockwiseArc {
+            if full || sweep <= 0.0 {
+                sweep += 2.0 * PI;
+            }
+        } else if full || sweep >= 0.0 {
+            sweep -= 2.0 * PI;
+        }
+        if full {
+            sweep = sweep.signum() * 2.0 * PI;
+        }
+
+        Some(Self { plane, center, radius, start_angle, sweep, start, end })
+    }
+
+    /// 平面を返す
+    pub fn get_plane(&self) -> Plane {
+        self.plane
+    }
+
+    /// 平面の第1軸と第2軸の中心を返す
+    pub fn get_center(&self) -> (f32, f32) {
+        self.center
+    }
+
+    /// 半径を返す
+    pub fn get_radius(&self) -> f32 {
+        self.radius
+    }
+
+    /// 始点の角度（ラジアン）を返す
+    pub fn get_start_angle(&self) -> f32 {
+        self.start_angle
+    }
+
+    /// 終点の角度（ラジアン）を返す
+    pub fn get_end_angle(&self) -> f32 {
+        self.start_angle + self.sweep
+    }
+
+    /// 回転角（ラジアン）を返す、反時計回りを正とする
+    pub fn get_sweep(&self) -> f32 {
+        self.sweep
+    }
+
+    /// 時計回りであるかの真偽値を返す
+    pub fn is_clockwise(&self) -> bool {
+        self.sweep < 0.0
+    }
+
+    /// 平面に垂直な軸が変化するヘリカル補間であるかの真偽値を返す
+    pub fn is_helical(&self) -> bool {
+        self.start.2 != self.end.2
+    }
+
+    /// 始点から終点までの移動距離を返す
+    pub fn length(&self) -> f32 {
+        (self.radius * self.sweep.abs()).hypot(self.end.2 - self.start.2)
+    }
+
+    /// 指定した割合（0.0から1.0）の位置のX、Y、Zの座標を返す
+    /// 始点と終点は指令された座標をそのまま返す
+    pub fn point_at(&self, ratio: f32) -> (f32, f32, f32) {
+        global(self.plane, self.local_point_at(ratio))
+    }
+
+    /// 円弧を分割した座標を始点から終点まで返す
+    pub fn points(&self, segments: usize) -> Vec<(f32, f32, f32)> {
+        let segments = segments.max(1);
+        (0..=segments)
+            .map(|n| self.point_at(n as f32 / segments as f32))
+            .collect()
+    }
+
+    /// 円弧が通過するX、Y、Zの座標の最小値と最大値を返す
+    /// 始点、終点に加え、通過する象限点を含める
+    pub fn extents(&self) -> ((f32, f32, f32), (f32, f32, f32)) {
+        let mut points = vec![self.point_at(0.0), self.point_at(1.0)];
+        (-6..=6).for_each(|n: i32| {
+            let ratio = (n as f32 * PI / 2.0 - self.start_angle) / self.sweep;
+            if (0.0..=1.0).contains(&ratio) {
+                // 象限点は三角関数の誤差を避けて中心と半径から求める
+                let (dx, dy) = [(1.0, 0.0), (0.0, 1.0), (-1.0, 0.0), (0.0, -1.0)][n.rem_euclid(4) as usize];
+                let (_, _, normal) = self.local_point_at(ratio);
+                let point = (self.center.0 + self.radius * dx, self.center.1 + self.radius * dy, normal);
+                points.push(global(self.plane, point));
+            }
+        });
+        let mut min = points[0];
+        let mut max = points[0];
+        points.iter().for_each(|&(x, y, z)| {
+            min = (min.0.min(x), min.1.min(y), min.2.min(z));
+            max = (max.0.max(x), max.1.max(y), max.2.max(z));
+        });
+
+        (min, max)
+    }
+
+    /// 指定した割合の位置の平面の第1軸、第2軸、垂直な軸の座標を返す
+    fn local_point_at(&self, ratio: f32) -> (f32, f32, f32) {
+        if ratio == 0.0 {
+            return self.start;
+        } else if ratio == 1.0 {
+            return self.end;
+        }
+        let angle = self.start_angle + self.sweep * ratio;
+        (
+            self.center.0 + self.radius * angle.cos(),
+            self.center.1 + self.radius * angle.sin(),
+            self.start.2 + (self.end.2 - self.start.2) * ratio,
+        )
+    }
+}
+
+/// X、Y、Zの座標を平面の第1軸、第2軸、垂直な軸の順に並べ替える
+fn local(plane: Plane, (x, y, z): (f32, f32, f32)) -> (f32, f32, f32) {
+    match plane {
+        Plane::XY => (x, y, z),
+        Plane::ZX => (z, x, y),
+        Plane::YZ => (y, z, x),
+    }
+}
+
+/// 平面の第1軸、第2軸、垂直な軸の座標をX、Y、Zの順に並べ替える
+fn global(plane: Plane, (first, second, normal): (f32, f32, f32)) -> (f32, f32, f32) {
+    match plane {
+        Plane::XY => (first, second, normal),
+        Plane::ZX => (second, normal, first),
+        Plane::YZ => (normal, first, second),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::structs::line_parser::Line;
+
+    fn arc(lines: &[&str]) -> Option<Arc> {
+        let mut state = State::default();
+        let mut start = state;
+        let mut end = state;
+        lines.iter().for_each(|line| {
+            start = state;
+            end = state.state_update(Line::new(line.to_string())).unwrap().unwrap();
+        });
+        Arc::new(&start, &end)
+    }
+
+    fn approx(a: f32, b: f32) -> bool {
+        (a - b).abs() < 1.0e-3
+    }
+
+    #[test]
+    fn arc_new_normal() {
+        let result = arc(&["G00X10.0", "G02X0.Y10.0I-10.0"]).unwrap();
+        assert_eq!(result.get_center(), (0.0, 0.0));
+        assert!(approx(result.get_radius(), 10.0));
+        assert!(approx(result.get_start_angle(), 0.0));
+        assert!(approx(result.get_sweep(), -1.5 * PI));
+        assert!(result.is_clockwise());
+
+        let result = arc(&["G00X10.0", "G03X0.Y10.0I-10.0"]).unwrap();
+        assert!(approx(result.get_sweep(), 0.5 * PI));
+        assert!(approx(result.get_end_angle(), 0.5 * PI));
+        assert!(!result.is_clockwise());
+    }
+
+    #[test]
+    fn arc_new_full_circle() {
+        let result = arc(&["G00X10.0", "G02I-10.0"]).unwrap();
+        assert!(approx(result.get_sweep(), -2.0 * PI));
+
+        let result = arc(&["G00X10.0", "G03X10.0I-10.0"]).unwrap();
+        assert!(approx(result.get_sweep(), 2.0 * PI));
+    }
+
+    #[test]
+    fn arc_new_abnormality() {
+        assert_eq!(arc(&["G01X10.0"]), None);
+        assert_eq!(arc(&["G02Z-10.0"]), None);
+    }
+
+    #[test]
+    fn arc_length_and_helical() {
+        let result = arc(&["G00X10.0", "G03X-10.0Z-5.0I-10.0"]).unwrap();
+        assert!(result.is_helical());
+        assert!(approx(result.length(), (10.0 * PI).hypot(5.0)));
+    }
+
+    #[test]
+    fn arc_points() {
+        let result = arc(&["G00X10.0", "G03X-10.0I-10.0"]).unwrap();
+        let points = result.points(2);
+        assert_eq!(points.len(), 3);
+        assert!(approx(points[1].0, 0.0));
+        assert!(approx(points[1].1, 10.0));
+        assert!(approx(points[2].0, -10.0));
+    }
+
+    #[test]
+    fn arc_extents() {
+        let (min, max) = arc(&["G00X10.0", "G03X-10.0I-10.0"]).unwrap().extents();
+        assert!(approx(min.0, -10.0) && approx(min.1, 0.0));
+        assert!(approx(max.0, 10.0) && approx(max.1, 10.0));
+
+        let (min, max) = arc(&["G00X10.0", "G02X-10.0I-10.0"]).unwrap().extents();
+        assert!(approx(min.1, -10.0) && approx(max.1, 0.0));
+    }
+
+    #[test]
+    fn arc_new_plane() {
+        // ZX平面はZを第1軸、Xを第2軸とし、Yの正の側から見て反時計回りをG03とする
+        let datas = [
+            (vec!["G18G00X10.Z0", "G03X0.Z10.I-10."], Plane::ZX, (0.0, 0.0), 10.0, 1.5 * PI),
+            (vec!["G18G00X10.Z0", "G03X0.Z10.R10."], Plane::ZX, (10.0, 10.0), 10.0, 0.5 * PI),
+            (vec!["G18G00X10.Z0", "G02X10.Z10.K5."], Plane::ZX, (5.0, 10.0), 5.0, -PI),
+            (vec!["G19G00Y10.Z0", "G03Y0.Z10.J-10."], Plane::YZ, (0.0, 0.0), 10.0, 0.5 * PI),
+            (vec!["G19G00Y10.Z0", "G03Y0.Z10.R-10."], Plane::YZ, (10.0, 10.0), 10.0, 1.5 * PI),
+            (vec!["G19G00Y0Z0", "G02Y10.Z0K0J5."], Plane::YZ, (5.0, 0.0), 5.0, -PI),
+        ];
+        datas.iter().for_each(|(lines, plane, center, radius, sweep)| {
+            let result = arc(lines).unwrap();
+            assert_eq!(result.get_plane(), *plane);
+            assert!(approx(result.get_center().0, center.0) && approx(result.get_center().1, center.1), "{:?}", lines);
+            assert!(approx(result.get_radius(), *radius), "{:?}", lines);
+            assert!(approx(result.get_sweep(), *sweep), "{:?}: {}", lines, result.get_sweep());
+        });
+
+        let result = arc(&["G18G00X10.Z0", "G03X-10.Z0I-10."]).unwrap();
+        assert!(!result.is_helical());
+        let (min, max) = result.extents();
+        assert!(approx(min.0, -10.0) && approx(max.0, 10.0));
+        assert!(approx(min.2, -10.0) && approx(max.2, 0.0));
+        assert!(approx(min.1, 0.0) && approx(max.1, 0.0));
+        let (x, y, z) = result.point_at(0.5);
+        assert!(approx(x, 0.0) && approx(y, 0.0) && approx(z, -10.0));
+    }
+}
@@ -0,0 +1,134 @@
+use serde::{Deserialize, Serialize};
+
+/// 主軸の回転状態
+/// M03, M04, M05
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy, Hash, Serialize, Deserialize)]
+pub enum Spindle {
+    #[default]
+    Stop,
+    Clockwise,
+    CounterClockwise,
+}
+
+impl Spindle {
+    /// M03からM05のコードを検証し、インスタンス化
+    pub fn new(code: &str) -> Option<Self> {
+        match code {
+            "M03" => Some(Spindle::Clockwise),
+            "M04" => Some(Spindle::CounterClockwise),
+            "M05" => Some(Spindle::Stop),
+            _ => None,
+        }
+    }
+
+    /// 対応するMコードを返す
+    pub fn code(self) -> &'static str {
+        match self {
+            Spindle::Stop => "M05",
+            Spindle::Clockwise => "M03",
+            Spindle::CounterClockwise => "M04",
+        }
+    }
+
+    /// 回転中であるかの真偽値を返す
+    pub fn is_running(self) -> bool {
+        self != Spindle::Stop
+    }
+}
+
+/// クーラントの状態
+/// M07, M08, M09
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy, Hash, Serialize, Deserialize)]
+pub enum Coolant {
+    #[default]
+    Off,
+    Mist,
+    Flood,
+}
+
+impl Coolant {
+    /// M07からM09のコードを検証し、インスタンス化
+    pub fn new(code: &str) -> Option<Self> {
+        match code {
+            "M07" => Some(Coolant::Mist),
+            "M08" => Some(Coolant::Flood),
+            "M09" => Some(Coolant::Off),
+            _ => None,
+        }
+    }
+
+    /// 対応するMコードを返す
+    pub fn code(self) -> &'static str {
+        match self {
+            Coolant::Off => "M09",
+            Coolant::Mist => "M07",
+            Coolant::Flood => "M08",
+        }
+    }
+
+    /// 吐出中であるかの真偽値を返す
+    pub fn is_on(self) -> bool {
+        self != Coolant::Off
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn spindle_new_normal() {
+        let data = [
+            ("M03", Spindle::Clockwise),
+            ("M04", Spindle::CounterClockwise),
+            ("M05", Spindle::Stop),
+        ];
+        data.iter().for_each(|&(code, expected)| {
+            assert_eq!(Spindle::new(code), Some(expected));
+            assert_eq!(expected.code(), code);
+        });
+    }
+
+    #[test]
+    fn spindle_new_abnormality() {
+        let data = ["M3", "M06", "G03", "m03"];
+        data.iter().for_each(|&code| {
+            assert_eq!(Spindle::new(code), None);
+        });
+    }
+
+    #[test]
+    fn spindle_is_running() {
+        assert!(!Spindle::Stop.is_running());
+        assert!(Spindle::Clockwise.is_running());
+        assert!(Spindle::CounterClockwise.is_running());
+    }
+
+    #[test]
+    fn coolant_new_normal() {
+        let data = [
+            ("M07", Coolant::Mist),
+            ("M08", Coolant::Flood),
+            ("M09", Coolant::Off),
+        ];
+        data.iter().for_each(|&(code, expected)| {
+            assert_eq!(Coolant::new(code), Some(expected));
+            assert_eq!(expected.code(), code);
+        });
+    }
+
+    #[test]
+    fn coolant_new_abnormality() {
+        let data = ["M8", "M10", "G08"];
+        data.iter().for_each(|&code| {
+            assert_eq!(Coolant::new(code), None);
+        });
+    }
+
+    #[test]
+    fn coolant_is_on() {
+        assert!(!Coolant::Off.is_on());
+        assert!(Coolant::Mist.is_on());
+        assert!(Coolant::Flood.is_on());
+    }
+}
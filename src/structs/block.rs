@@ -0,0 +1,125 @@
+use crate::error::Error;
+use crate::structs::line_parser::Line;
+use serde::{Deserialize, Serialize};
+
+/// アドレスと値の組であるワードの構造体
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct Word {
+    code: char,
+    value: String,
+}
+
+impl Word {
+    /// インスタンス化
+    pub fn new(code: char, value: &str) -> Self {
+        Self { code, value: value.to_string() }
+    }
+
+    /// アドレスを返す
+    pub fn get_code(&self) -> char {
+        self.code
+    }
+
+    /// 値の文字列を返す
+    pub fn get_value(&self) -> &str {
+        &self.value
+    }
+
+    /// 値を数値として返す
+    pub fn to_f32(&self) -> Result<f32, Error> {
+        Ok(self.value.parse::<f32>()?)
+    }
+}
+
+/// 1行を解析したブロックの構造体
+#[derive(Debug, Default, PartialEq, Clone, Serialize, Deserialize)]
+pub struct Block {
+    words: Vec<Word>,
+    comments: Vec<String>,
+}
+
+impl Block {
+    /// 行を解析し、インスタンス化
+    /// ワードとして解析できない文字列が残る場合はエラーを返す
+    pub fn new(line: &Line) -> Result<Self, Error> {
+        let (rest, words) = line.parsed_words()
+            .map_err(|e| Error::InvalidParser(format!("{:?}", e)))?;
+        if !rest.is_empty() {
+            return Err(Error::InvalidParser(rest.to_string()));
+        }
+        let (_, comments) = line.parsed_comments()
+            .map_err(|e| Error::InvalidParser(format!("{:?}", e)))?;
+
+        Ok(Self {
+            words: words.iter().map(|&(code, value)| Word::new(code, value)).collect(),
+            comments: comments.iter().map(|comment| comment.to_string()).collect(),
+        })
+    }
+
+    /// ワードとコメントからインスタンス化
+    pub fn from_words(words: Vec<Word>, comments: Vec<String>) -> Self {
+        Self { words, comments }
+    }
+
+    /// ワードを返す
+    pub fn get_words(&self) -> &[Word] {
+        &self.words
+    }
+
+    /// コメントを返す
+    pub fn get_comments(&self) -> &[String] {
+        &self.comments
+    }
+
+    /// 指定したアドレスのワードを先頭から順に返す
+    pub fn words_of(&self, code: char) -> impl Iterator<Item = &Word> {
+        self.words.iter().filter(move |word| word.code == code)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn word_to_f32() {
+        let datas = [("-1515.", -1515.0), ("1000", 1000.0), ("+.5", 0.5)];
+        datas.iter().for_each(|&(value, expected)| {
+            assert_eq!(Word::new('X', value).to_f32(), Ok(expected));
+        })
+    }
+
+    #[test]
+    fn block_new_normal() {
+        let line = Line::new("Y193.( *** KANSYOU CYUUI *** )".to_string());
+        let result = Block::new(&line).unwrap();
+        assert_eq!(result.get_words(), [Word::new('Y', "193.")]);
+        assert_eq!(result.get_comments(), [" *** KANSYOU CYUUI *** ".to_string()]);
+    }
+
+    #[test]
+    fn block_new_abnormality() {
+        let line = Line::new("G90x100.0".to_string());
+        let result = Block::new(&line);
+        assert_eq!(result, Err(Error::InvalidParser("x100.0".to_string())));
+    }
+
+    #[test]
+    fn block_words_of() {
+        let line = Line::new("G90G00X-1610.0M03".to_string());
+        let block = Block::new(&line).unwrap();
+        let result: Vec<&str> = block.words_of('G').map(|word| word.get_value()).collect();
+        assert_eq!(result, ["90", "00"]);
+    }
+
+    #[test]
+    fn block_serialize() {
+        let line = Line::new("G01X1.(A)".to_string());
+        let block = Block::new(&line).unwrap();
+        let result = serde_json::to_string(&block).unwrap();
+        assert_eq!(
+            result,
+            r#"{"words":[{"code":"G","value":"01"},{"code":"X","value":"1."}],"comments":["A"]}"#
+        );
+    }
+}
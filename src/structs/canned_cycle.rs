@@ -0,0 +1,153 @@
+use serde::{Deserialize, Serialize};
+
+/// 固定サイクルのGコードの番号
+/// G73、G74、G76、G81からG89
+const CYCLE_NUMBERS: [u32; 12] = [73, 74, 76, 81, 82, 83, 84, 85, 86, 87, 88, 89];
+
+/// 穴底から切削送りで復帰する固定サイクルの番号
+/// タップ（G74、G84）とボーリング（G85、G89）
+const FEED_RETRACT_NUMBERS: [u32; 4] = [74, 84, 85, 89];
+
+/// 固定サイクルの穴あけ後の復帰点
+/// G98（イニシャル点）、G99（R点）
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy, Serialize, Deserialize)]
+pub enum ReturnLevel {
+    #[default]
+    Initial,
+    RPoint,
+}
+
+impl ReturnLevel {
+    /// G98、G99のコードを検証し、インスタンス化
+    pub fn new(code: &str) -> Option<Self> {
+        match code {
+            "G98" => Some(ReturnLevel::Initial),
+            "G99" => Some(ReturnLevel::RPoint),
+            _ => None,
+        }
+    }
+
+    /// 対応するGコードを返す
+    pub fn code(self) -> &'static str {
+        match self {
+            ReturnLevel::Initial => "G98",
+            ReturnLevel::RPoint => "G99",
+        }
+    }
+}
+
+/// 固定サイクル（G73、G74、G76、G81からG89）
+/// 穴あけは位置決め、R点までの早送り、穴底までの切削送り、復帰点までの復帰とし、
+/// ペック、ドウェル、シフト、繰り返し回数は扱わない
+#[derive(Debug, PartialEq, Clone, Copy, Serialize, Deserialize)]
+pub struct CannedCycle {
+    /// Gコードの番号
+    number: u32,
+    /// サイクルを開始した時のZ（イニシャル点）
+    initial: f32,
+    /// R点のZ
+    r: f32,
+    /// 穴底のZ
+    bottom: f32,
+}
+
+impl CannedCycle {
+    /// 固定サイクルのコードを検証し、イニシャル点からインスタンス化
+    /// R点と穴底は指令があるまでイニシャル点とする
+    pub fn new(code: &str, initial: f32) -> Option<Self> {
+        let number = code.strip_prefix('G')?.parse::<u32>().ok()?;
+        if !CYCLE_NUMBERS.contains(&number) {
+            return None;
+        }
+        Some(Self { number, initial, r: initial, bottom: initial })
+    }
+
+    /// 実行中のサイクルのイニシャル点、R点、穴底を引き継いだサイクルを返す
+    pub fn continued(self, active: CannedCycle) -> Self {
+        Self { number: self.number, ..active }
+    }
+
+    /// R点のZを設定
+    pub fn r_set(&mut self, r: f32) {
+        self.r = r;
+    }
+
+    /// 穴底のZを設定
+    pub fn bottom_set(&mut self, bottom: f32) {
+        self.bottom = bottom;
+    }
+
+    /// 対応するGコードを返す
+    pub fn code(self) -> String {
+        format!("G{}", self.number)
+    }
+
+    /// イニシャル点のZを返す
+    pub fn get_initial(self) -> f32 {
+        self.initial
+    }
+
+    /// R点のZを返す
+    pub fn get_r(self) -> f32 {
+        self.r
+    }
+
+    /// 穴底のZを返す
+    pub fn get_bottom(self) -> f32 {
+        self.bottom
+    }
+
+    /// 穴底から切削送りで復帰するかの真偽値を返す
+    pub fn is_feed_retract(self) -> bool {
+        FEED_RETRACT_NUMBERS.contains(&self.number)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn canned_cycle_new() {
+        let data = [
+            ("G81", Some("G81"), false),
+            ("G73", Some("G73"), false),
+            ("G84", Some("G84"), true),
+            ("G85", Some("G85"), true),
+            ("G80", None, false),
+            ("G75", None, false),
+            ("G01", None, false),
+            ("M81", None, false),
+        ];
+        data.iter().for_each(|&(code, expected, feed_retract)| {
+            let result = CannedCycle::new(code, 50.0);
+            assert_eq!(result.map(|cycle| cycle.code()).as_deref(), expected);
+            if let Some(cycle) = result {
+                assert_eq!((cycle.get_initial(), cycle.get_r(), cycle.get_bottom()), (50.0, 50.0, 50.0));
+                assert_eq!(cycle.is_feed_retract(), feed_retract);
+            }
+        });
+    }
+
+    #[test]
+    fn canned_cycle_continued() {
+        let mut active = CannedCycle::new("G81", 50.0).unwrap();
+        active.r_set(2.0);
+        active.bottom_set(-5.0);
+        let result = CannedCycle::new("G85", 10.0).unwrap().continued(active);
+        assert_eq!(result.code(), "G85");
+        assert_eq!((result.get_initial(), result.get_r(), result.get_bottom()), (50.0, 2.0, -5.0));
+    }
+
+    #[test]
+    fn return_level_new() {
+        let data = [("G98", Some(ReturnLevel::Initial)), ("G99", Some(ReturnLevel::RPoint)), ("G97", None)];
+        data.iter().for_each(|&(code, expected)| {
+            let result = ReturnLevel::new(code);
+            assert_eq!(result, expected);
+            if let Some(level) = result {
+                assert_eq!(level.code(), code);
+            }
+        });
+    }
+}
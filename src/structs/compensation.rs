@@ -0,0 +1,94 @@
+use serde::{Deserialize, Serialize};
+
+/// 工具径補正
+/// G40（取り消し）、G41（左）、G42（右）
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy, Serialize, Deserialize)]
+pub enum CutterCompensation {
+    #[default]
+    Off,
+    Left,
+    Right,
+}
+
+impl CutterCompensation {
+    /// G40からG42のコードを検証し、インスタンス化
+    pub fn new(code: &str) -> Option<Self> {
+        match code {
+            "G40" => Some(CutterCompensation::Off),
+            "G41" => Some(CutterCompensation::Left),
+            "G42" => Some(CutterCompensation::Right),
+            _ => None,
+        }
+    }
+
+    /// 対応するGコードを返す
+    pub fn code(self) -> &'static str {
+        match self {
+            CutterCompensation::Off => "G40",
+            CutterCompensation::Left => "G41",
+            CutterCompensation::Right => "G42",
+        }
+    }
+}
+
+/// 入力単位
+/// G20（インチ）、G21（ミリ）、値の換算は行わない
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy, Serialize, Deserialize)]
+pub enum Units {
+    Inch,
+    #[default]
+    Millimeter,
+}
+
+impl Units {
+    /// G20、G21のコードを検証し、インスタンス化
+    pub fn new(code: &str) -> Option<Self> {
+        match code {
+            "G20" => Some(Units::Inch),
+            "G21" => Some(Units::Millimeter),
+            _ => None,
+        }
+    }
+
+    /// 対応するGコードを返す
+    pub fn code(self) -> &'static str {
+        match self {
+            Units::Inch => "G20",
+            Units::Millimeter => "G21",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cutter_compensation_new() {
+        let data = [
+            ("G40", Some(CutterCompensation::Off)),
+            ("G41", Some(CutterCompensation::Left)),
+            ("G42", Some(CutterCompensation::Right)),
+            ("G43", None),
+        ];
+        data.iter().for_each(|&(code, expected)| {
+            let result = CutterCompensation::new(code);
+            assert_eq!(result, expected);
+            if let Some(compensation) = result {
+                assert_eq!(compensation.code(), code);
+            }
+        });
+    }
+
+    #[test]
+    fn units_new() {
+        let data = [("G20", Some(Units::Inch)), ("G21", Some(Units::Millimeter)), ("G22", None)];
+        data.iter().for_each(|&(code, expected)| {
+            let result = Units::new(code);
+            assert_eq!(result, expected);
+            if let Some(units) = result {
+                assert_eq!(units.code(), code);
+            }
+        });
+    }
+}
@@ -1,7 +1,8 @@
 use crate::error::Error;
+use serde::{Deserialize, Serialize};
 
 /// X座標の構造体
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
 pub struct XCoordinate {
     code: char,
     value: f32,
@@ -24,7 +25,7 @@ impl XCoordinate {
 }
 
 /// Y座標の構造体
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
 pub struct YCoordinate {
     code: char,
     value: f32,
@@ -46,6 +47,29 @@ impl YCoordinate {
     }
 }
 
+/// Z座標の構造体
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct ZCoordinate {
+    code: char,
+    value: f32,
+}
+
+impl ZCoordinate {
+    /// 座標がZの座標であるかを検証し、構造体インスタンス化
+    pub fn new(code: char, value: f32) -> Result<Self, Error> {
+        if code == 'Z' {
+            Ok(Self { code, value })
+        } else {
+            Err(Error::InvalidCode(code))
+        }
+    }
+
+    /// valueの値を返す
+    pub fn get_value(&self) -> f32 {
+        self.value
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -99,4 +123,28 @@ mod tests {
         let result = ycode.get_value();
         assert_eq!(result, expected);
     }
+
+    #[test]
+    fn z_coordinate_new_normal() {
+        let expected = ZCoordinate {code: 'Z', value: -5.0 };
+        let result = ZCoordinate::new('Z', -5.0);
+        assert_eq!(result.unwrap(), expected);
+    }
+
+    #[test]
+    fn z_coordinate_new_abnormality() {
+        let codes = ['z', 'X', 'Y', 'W'];
+        codes.iter().for_each(|&code| {
+            let result = ZCoordinate::new(code, 100.0);
+            assert_eq!(result, Err(Error::InvalidCode(code)));
+        });
+    }
+
+    #[test]
+    fn z_coordinate_get_value() {
+        let expected = -5.0;
+        let zcode = ZCoordinate {code: 'Z', value: -5.0 };
+        let result = zcode.get_value();
+        assert_eq!(result, expected);
+    }
 }
@@ -0,0 +1,573 @@
+use nom::branch::alt;
+use nom::character::complete::{alpha1, char, digit0, multispace0, satisfy};
+use nom::combinator::{map, recognize};
+use nom::multi::many0;
+use nom::sequence::{delimited, pair, preceded, separated_pair, tuple};
+use nom::IResult;
+use serde::{Deserialize, Serialize};
+
+use crate::error::Error;
+use crate::structs::block::{Block, Word};
+use crate::structs::expression::{expression, factor, sharp_variable, Expression, Function, Syntax, Variables};
+use crate::structs::line_parser::{g_code, separator, Line};
+use crate::structs::machine_profile::DecimalInput;
+
+pub mod grbl;
+pub mod heidenhain;
+pub mod linuxcnc;
+pub mod marlin;
+pub mod sinumerik;
+
+/// 1行に含まれる文
+#[derive(Debug, PartialEq, Clone)]
+pub enum Statement {
+    /// 変数への代入
+    Assignment(String, Expression),
+    /// アドレスと値の組
+    Word(char, Expression),
+    /// 絶対値（偽）または増分値（真）を個別に指定した座標
+    Coordinate(char, bool, Expression),
+    /// 座標変換の指令
+    Frame(FrameCommand, Vec<(char, Expression)>),
+    /// サイクルの呼び出し
+    /// モーダルな呼び出しは以降の位置決めごとに実行する
+    Call {
+        name: String,
+        arguments: Vec<Option<Expression>>,
+        modal: bool,
+    },
+    /// モーダルな呼び出しの取消し
+    CancelModalCall,
+    /// 条件が真の場合のみ実行する範囲の開始
+    If(Expression),
+    /// それまでの条件が偽で、条件が真の場合のみ実行する範囲の開始
+    ElseIf(Expression),
+    Else,
+    EndIf,
+    /// 条件が真の間繰り返す範囲の開始、またはDoで始まる範囲の終了
+    While(String, Expression),
+    /// 終了時に条件を判定して繰り返す範囲の開始
+    Do(String),
+    /// 指定した回数繰り返す範囲の開始
+    Repeat(String, Expression),
+    /// WhileとRepeatで始まる範囲の終了
+    EndLoop(String),
+    /// 繰り返しの中断
+    Break(String),
+    /// 繰り返しの次の判定へ進む
+    Continue(String),
+    /// サブプログラムの開始
+    Sub(String),
+    /// 引数を指定したサブプログラムの呼び出し
+    SubCall(String, Vec<Expression>),
+    /// 戻り値を指定したサブプログラムからの復帰
+    Return(String, Option<Expression>),
+    /// サブプログラムの終了
+    EndSub(String, Option<Expression>),
+    /// 条件が真の場合（条件がない場合は常に）ジャンプする
+    Goto(Option<Expression>, Jump),
+    /// 円弧の中心（極）の設定
+    /// 指定しない軸は現在の位置とする
+    SetCenter(Vec<(char, bool, Expression)>),
+    /// 設定した円弧の中心を円弧補間の中心とする
+    UseCenter,
+    /// 位置に影響しないため解釈しない命令
+    Command(String),
+}
+
+/// 座標変換の指令
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum FrameCommand {
+    /// 平行移動を設定
+    Trans,
+    /// 平行移動を追加
+    Atrans,
+    /// 回転を設定
+    Rot,
+    /// 回転を追加
+    Arot,
+}
+
+/// ジャンプの探索方向
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Direction {
+    Forward,
+    Backward,
+    /// 前方を探索し、見つからない場合は後方を探索する
+    Both,
+}
+
+/// ジャンプ先のラベルと探索方向
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Jump {
+    label: String,
+    direction: Direction,
+}
+
+impl Jump {
+    /// インスタンス化
+    pub fn new(label: &str, direction: Direction) -> Self {
+        Self { label: label.to_string(), direction }
+    }
+
+    /// ラベルを返す
+    pub fn get_label(&self) -> &str {
+        &self.label
+    }
+
+    /// 探索方向を返す
+    pub fn get_direction(&self) -> Direction {
+        self.direction
+    }
+}
+
+/// 繰り返しとサブプログラムの範囲の行のラベルを、範囲の名前とキーワードから返す
+/// 例: ("O100", "SUB") -> "O100 SUB"
+pub fn block_label(name: &str, keyword: &str) -> String {
+    format!("{} {}", name, keyword)
+}
+
+/// 制御装置ごとの文法とGコードの意味の違い
+/// 字句解析で変数と式を含むワードを読み取り、解釈の前にファナック系のGコードに置き換える
+pub trait Dialect: std::fmt::Debug {
+    /// 制御装置の名前を返す
+    fn name(&self) -> &'static str;
+
+    /// 式の書式を返す
+    fn syntax(&self) -> Syntax {
+        Syntax::new(sharp_variable, ('[', ']'))
+    }
+
+    /// "X=VC1+10"のようにアドレスの後に"="で式を書けるかの真偽値を返す
+    fn word_assignment(&self) -> bool {
+        false
+    }
+
+    /// 制御装置で決まっている小数点のない数値の解釈を返す
+    /// Noneの場合は機械の設定に従う
+    fn decimal_input(&self) -> Option<DecimalInput> {
+        None
+    }
+
+    /// 行を文に分解する
+    /// 解析できない文字列が残る場合はエラーを返す
+    fn tokenize(&self, line: &Line) -> Result<Vec<Statement>, Error> {
+        words(line.get_line(), self.syntax(), self.word_assignment())
+    }
+
+    /// 行に含まれるコメントを返す
+    fn comments(&self, line: &Line) -> Vec<String> {
+        line.parsed_comments()
+            .map(|(_, comments)| comments.iter().map(|comment| comment.to_string()).collect())
+            .unwrap_or_default()
+    }
+
+    /// 行の先頭のジャンプ先のラベルを返す
+    fn label(&self, _line: &str) -> Option<String> {
+        None
+    }
+
+    /// 穴あけサイクルの呼び出しであれば、サイクル終了時のZの位置（復帰点）を返す
+    fn retract_plane(
+        &self,
+        _name: &str,
+        _arguments: &[Option<Expression>],
+        _variables: &Variables,
+    ) -> Result<Option<f32>, Error> {
+        Ok(None)
+    }
+
+    /// 制御装置固有のGコードをファナック系の意味のワードに置き換えたブロックを返す
+    fn canonical(&self, block: &Block) -> Result<Block, Error> {
+        Ok(block.clone())
+    }
+
+    /// ファナック系の意味のワードを制御装置固有のGコードに置き換えたブロックを返す
+    /// 制御装置で指令できないワードを含む場合はエラーを返す
+    fn localize(&self, block: &Block) -> Result<Block, Error> {
+        Ok(block.clone())
+    }
+
+    /// 変数をファナック系の"#"に続く番号に対応させて返す
+    /// 対応する番号がない変数はNoneを返す
+    fn variable_number(&self, name: &str) -> Option<u32> {
+        name.strip_prefix('#')?.parse().ok()
+    }
+
+    /// 変数と式を記述できるかの真偽値を返す
+    fn expressions(&self) -> bool {
+        true
+    }
+
+    /// ファナック系の"#"に続く番号に対応する変数を返す
+    /// 変数を使用できない場合はNoneを返す
+    fn variable_name(&self, number: u32) -> Option<String> {
+        Some(format!("#{}", number))
+    }
+
+    /// 関数を適用する式を返す
+    fn function(&self, function: Function, argument: &str) -> String {
+        let (open, close) = self.syntax().brackets();
+        format!("{}{}{}{}", function.name(), open, argument, close)
+    }
+
+    /// コメントを記述した文字列を返す
+    /// コメントの中の括弧は角括弧に置き換える
+    fn comment(&self, text: &str) -> String {
+        format!("({})", text.replace('(', "[").replace(')', "]"))
+    }
+}
+
+/// 拡張のワーク座標系、工具長補正、固定サイクルがないGコードの範囲のブロックを検証する
+/// 範囲外のGコードを含む場合はエラーを返す
+fn basic_g_codes(block: &Block) -> Result<Block, Error> {
+    let unsupported = block.words_of('G').any(|word| {
+        let code = g_code(word.get_value());
+        matches!(code.as_str(), "G54.1" | "G43" | "G44" | "G52" | "G98" | "G99")
+            || code[1..].parse::<u32>().is_ok_and(|number| (73..=89).contains(&number) && number != 80)
+    });
+    if unsupported {
+        return Err(Error::InvalidCode('G'));
+    }
+    Ok(block.clone())
+}
+
+/// 追加のワーク座標系の番号を返す
+/// G54.1を含まないブロックはNoneを返す
+fn extended_work_offset(block: &Block) -> Result<Option<u32>, Error> {
+    if !block.words_of('G').any(|word| g_code(word.get_value()) == "G54.1") {
+        return Ok(None);
+    }
+    let number = block.words_of('P').next().ok_or(Error::InvalidCode('P'))?;
+    Ok(Some(number.get_value().parse()?))
+}
+
+/// ";"から行末までのコメントを除いた文字列を返す
+fn code(line: &str) -> &str {
+    line.split_once(';').map_or(line, |(code, _)| code)
+}
+
+/// 括弧で囲まれたコメントと、";"から行末までのコメントを返す
+fn semicolon_comments(line: &str) -> Vec<String> {
+    let tail = line.split_once(';').map(|(_, comment)| comment.trim().to_string());
+    Line::new(code(line).to_string()).parsed_comments()
+        .map(|(_, comments)| comments.iter().map(|comment| comment.to_string()).collect::<Vec<_>>())
+        .unwrap_or_default()
+        .into_iter()
+        .chain(tail)
+        .collect()
+}
+
+/// 変数への代入とワードからなる行を文に分解する
+/// 解析できない文字列が残る場合はエラーを返す
+fn words(line: &str, syntax: Syntax, word_assignment: bool) -> Result<Vec<Statement>, Error> {
+    let statement = |input| statement(input, syntax, word_assignment);
+    let (rest, statements) = delimited(
+        separator,
+        many0(delimited(separator, statement, separator)),
+        separator,
+    )(line)
+        .map_err(|e| Error::InvalidParser(format!("{:?}", e)))?;
+    if !rest.is_empty() {
+        return Err(Error::InvalidParser(rest.to_string()));
+    }
+    Ok(statements)
+}
+
+/// 変数への代入、またはアドレスと値の組を返す
+/// 値は数値、変数、括弧で囲まれた式と、それらの符号を反転した値に対応する
+fn statement(input: &str, syntax: Syntax, word_assignment: bool) -> IResult<&str, Statement> {
+    let value = |input| {
+        if word_assignment {
+            alt((preceded(char('='), expression(syntax)), factor(syntax)))(input)
+        } else {
+            factor(syntax)(input)
+        }
+    };
+    alt((
+        map(
+            separated_pair(syntax.variable(), delimited(multispace0, char('='), multispace0), expression(syntax)),
+            |(name, expression)| Statement::Assignment(name.to_string(), expression),
+        ),
+        map(
+            pair(satisfy(|c| c.is_ascii_uppercase()), value),
+            |(code, expression)| Statement::Word(code, expression),
+        ),
+    ))(input)
+}
+
+/// ファナック
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub struct Fanuc;
+
+impl Dialect for Fanuc {
+    fn name(&self) -> &'static str {
+        "Fanuc"
+    }
+}
+
+/// ハース
+/// G154 P1-P99とG110-G129の追加のワーク座標系をG54.1 Pに置き換え、変換先としてはG154 Pで指令する
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub struct Haas;
+
+impl Dialect for Haas {
+    fn name(&self) -> &'static str {
+        "Haas"
+    }
+
+    fn canonical(&self, block: &Block) -> Result<Block, Error> {
+        let words = block.get_words().iter().flat_map(|word| {
+            let code = g_code(word.get_value());
+            match (word.get_code(), code.as_str()) {
+                ('G', "G154") => vec![Word::new('G', "54.1")],
+                ('G', _) => match code[1..].parse::<u32>() {
+                    Ok(number @ 110..=129) => {
+                        vec![Word::new('G', "54.1"), Word::new('P', &(number - 109).to_string())]
+                    },
+                    _ => vec![word.clone()],
+                },
+                _ => vec![word.clone()],
+            }
+        }).collect();
+        Ok(Block::from_words(words, block.get_comments().to_vec()))
+    }
+
+    fn localize(&self, block: &Block) -> Result<Block, Error> {
+        if extended_work_offset(block)?.is_some_and(|number| !(1..=99).contains(&number)) {
+            return Err(Error::InvalidCode('P'));
+        }
+        let words = block.get_words().iter().map(|word| {
+            match (word.get_code(), g_code(word.get_value()).as_str()) {
+                ('G', "G54.1") => Word::new('G', "154"),
+                _ => word.clone(),
+            }
+        }).collect();
+        Ok(Block::from_words(words, block.get_comments().to_vec()))
+    }
+}
+
+/// 三菱電機（MELDAS）
+/// 変数とGコードの意味はファナック系と共通
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub struct Mitsubishi;
+
+impl Dialect for Mitsubishi {
+    fn name(&self) -> &'static str {
+        "Mitsubishi"
+    }
+}
+
+/// オークマ（OSP）
+/// 変数は"VC1"の形式で、"X=VC1+10"のように式を書ける
+/// 共通変数VC1-VC200はファナック系の#100-#299に対応させる
+/// ワーク座標系の選択"G15 H1"をG54.1 P1に、工具長補正のG56をG43に置き換える
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub struct Okuma;
+
+impl Dialect for Okuma {
+    fn name(&self) -> &'static str {
+        "Okuma"
+    }
+
+    fn syntax(&self) -> Syntax {
+        Syntax::new(okuma_variable, ('[', ']'))
+    }
+
+    fn word_assignment(&self) -> bool {
+        true
+    }
+
+    fn canonical(&self, block: &Block) -> Result<Block, Error> {
+        let selects_work_offset = block.words_of('G').any(|word| g_code(word.get_value()) == "G15");
+        let words = block.get_words().iter().map(|word| {
+            match (word.get_code(), g_code(word.get_value()).as_str()) {
+                ('G', "G15") => Word::new('G', "54.1"),
+                ('G', "G56") => Word::new('G', "43"),
+                ('H', _) if selects_work_offset => Word::new('P', word.get_value()),
+                _ => word.clone(),
+            }
+        }).collect();
+        Ok(Block::from_words(words, block.get_comments().to_vec()))
+    }
+
+    fn localize(&self, block: &Block) -> Result<Block, Error> {
+        let selects_work_offset = extended_work_offset(block)?.is_some();
+        let words = block.get_words().iter().map(|word| {
+            match (word.get_code(), g_code(word.get_value()).as_str()) {
+                ('G', "G54.1") => Word::new('G', "15"),
+                ('G', "G43") => Word::new('G', "56"),
+                ('P', _) if selects_work_offset => Word::new('H', word.get_value()),
+                _ => word.clone(),
+            }
+        }).collect();
+        Ok(Block::from_words(words, block.get_comments().to_vec()))
+    }
+
+    /// 共通変数VC1-VC200を#100-#299に対応させる
+    fn variable_number(&self, name: &str) -> Option<u32> {
+        match name.strip_prefix("VC")?.parse::<u32>().ok()? {
+            number @ 1..=200 => Some(number + 99),
+            _ => None,
+        }
+    }
+
+    fn variable_name(&self, number: u32) -> Option<String> {
+        (100..=299).contains(&number).then(|| format!("VC{}", number - 99))
+    }
+}
+
+/// オークマの変数（"V"と英字、番号）を認識して返す
+/// 例: "VC1", "VS10"
+fn okuma_variable(input: &str) -> IResult<&str, &str> {
+    recognize(tuple((char('V'), alpha1, digit0)))(input)
+}
+
+/// 制御装置の種類
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DialectKind {
+    #[default]
+    Fanuc,
+    Haas,
+    Mitsubishi,
+    Okuma,
+    Sinumerik,
+    Heidenhain,
+    Linuxcnc,
+    Grbl,
+    Marlin,
+}
+
+impl DialectKind {
+    /// 制御装置の文法とGコードの意味を返す
+    pub fn dialect(self) -> &'static dyn Dialect {
+        match self {
+            DialectKind::Fanuc => &Fanuc,
+            DialectKind::Haas => &Haas,
+            DialectKind::Mitsubishi => &Mitsubishi,
+            DialectKind::Okuma => &Okuma,
+            DialectKind::Sinumerik => &sinumerik::Sinumerik,
+            DialectKind::Heidenhain => &heidenhain::Heidenhain,
+            DialectKind::Linuxcnc => &linuxcnc::LinuxCnc,
+            DialectKind::Grbl => &grbl::Grbl,
+            DialectKind::Marlin => &marlin::Marlin,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn canonical(dialect: &dyn Dialect, line: &str) -> Vec<(char, String)> {
+        let block = Block::new(&Line::new(line.to_string())).unwrap();
+        dialect.canonical(&block).unwrap().get_words().iter()
+            .map(|word| (word.get_code(), word.get_value().to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn dialect_tokenize_fanuc() {
+        let line = Line::new("#1=[#2+1.0]*2 G01X#1Y-#3Z-[#1/2](COMMENT)F100".to_string());
+        let result = Fanuc.tokenize(&line).unwrap();
+        let variable = |name: &str| Box::new(Expression::Variable(name.to_string()));
+        assert_eq!(result.len(), 6);
+        assert!(matches!(&result[0], Statement::Assignment(name, Expression::Binary(..)) if name == "#1"));
+        assert_eq!(result[2], Statement::Word('X', *variable("#1")));
+        assert_eq!(result[3], Statement::Word('Y', Expression::Negative(variable("#3"))));
+        assert!(matches!(&result[4], Statement::Word('Z', Expression::Negative(_))));
+        assert_eq!(result[5], Statement::Word('F', Expression::Number("100".to_string())));
+    }
+
+    #[test]
+    fn dialect_tokenize_okuma() {
+        let line = Line::new("VC1=10 G01 X=VC1+5 Y20.".to_string());
+        let result = Okuma.tokenize(&line).unwrap();
+        assert_eq!(result.len(), 4);
+        assert!(matches!(&result[0], Statement::Assignment(name, _) if name == "VC1"));
+        assert!(matches!(&result[2], Statement::Word('X', Expression::Binary(..))));
+
+        // ファナック系ではオークマの変数と"="の書式は解析できない
+        let result = Fanuc.tokenize(&Line::new("X=VC1".to_string()));
+        assert_eq!(result, Err(Error::InvalidParser("X=VC1".to_string())));
+    }
+
+    #[test]
+    fn dialect_canonical_haas() {
+        let datas = [
+            ("G154P12G00X1.", vec![('G', "54.1"), ('P', "12"), ('G', "00"), ('X', "1.")]),
+            ("G110", vec![('G', "54.1"), ('P', "1")]),
+            ("G129", vec![('G', "54.1"), ('P', "20")]),
+            ("G55", vec![('G', "55")]),
+        ];
+        datas.iter().for_each(|(line, expected)| {
+            let expected: Vec<(char, String)> = expected.iter().map(|&(code, value)| (code, value.to_string())).collect();
+            assert_eq!(canonical(&Haas, line), expected);
+        })
+    }
+
+    #[test]
+    fn dialect_canonical_okuma() {
+        let datas = [
+            ("G15H3", vec![('G', "54.1"), ('P', "3")]),
+            ("G56H1Z50.", vec![('G', "43"), ('H', "1"), ('Z', "50.")]),
+        ];
+        datas.iter().for_each(|(line, expected)| {
+            let expected: Vec<(char, String)> = expected.iter().map(|&(code, value)| (code, value.to_string())).collect();
+            assert_eq!(canonical(&Okuma, line), expected);
+        });
+        // ファナック系のG56はワーク座標系のまま
+        assert_eq!(canonical(&Fanuc, "G56"), [('G', "56".to_string())]);
+    }
+
+    #[test]
+    fn dialect_localize() {
+        let localize = |dialect: &dyn Dialect, line: &str| -> Result<String, Error> {
+            let block = Block::new(&Line::new(line.to_string())).unwrap();
+            Ok(dialect.localize(&block)?.get_words().iter()
+                .map(|word| format!("{}{}", word.get_code(), word.get_value()))
+                .collect())
+        };
+        let datas: [(&dyn Dialect, &str, &str); 6] = [
+            (&Haas, "G54.1P12G00", "G154P12G00"),
+            (&Okuma, "G54.1P3", "G15H3"),
+            (&Okuma, "G43H1Z50.", "G56H1Z50."),
+            (&sinumerik::Sinumerik, "G54.1P1G49", "G505D0"),
+            (&linuxcnc::LinuxCnc, "G54.1P2", "G59.2"),
+            (&grbl::Grbl, "G04P250", "G04P0.25"),
+        ];
+        datas.iter().for_each(|&(dialect, line, expected)| {
+            assert_eq!(localize(dialect, line), Ok(expected.to_string()));
+        });
+        let datas: [(&dyn Dialect, &str); 5] = [
+            (&Haas, "G54.1P100"),
+            (&sinumerik::Sinumerik, "G43H1"),
+            (&sinumerik::Sinumerik, "O1000"),
+            (&linuxcnc::LinuxCnc, "G54.1P4"),
+            (&marlin::Marlin, "G83Z-5.R1.Q1."),
+        ];
+        datas.iter().for_each(|&(dialect, line)| {
+            assert!(localize(dialect, line).is_err());
+        });
+    }
+
+    #[test]
+    fn dialect_kind_deserialize() {
+        let datas = [
+            ("\"fanuc\"", "Fanuc"),
+            ("\"haas\"", "Haas"),
+            ("\"mitsubishi\"", "Mitsubishi"),
+            ("\"okuma\"", "Okuma"),
+            ("\"sinumerik\"", "Sinumerik"),
+            ("\"heidenhain\"", "Heidenhain"),
+            ("\"linuxcnc\"", "LinuxCNC"),
+            ("\"grbl\"", "Grbl"),
+            ("\"marlin\"", "Marlin"),
+        ];
+        datas.iter().for_each(|&(source, expected)| {
+            let result: DialectKind = serde_json::from_str(source).unwrap();
+            assert_eq!(result.dialect().name(), expected);
+        })
+    }
+}
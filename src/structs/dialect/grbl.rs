@@ -0,0 +1,97 @@
+use crate::error::Error;
+use crate::structs::block::{Block, Word};
+use crate::structs::dialect::{basic_g_codes, code, semicolon_comments, words, Dialect, Statement};
+use crate::structs::line_parser::{g_code, Line};
+use crate::structs::machine_profile::DecimalInput;
+
+/// 行の途中でも即座に処理されるリアルタイムコマンド
+/// ステータス要求、送り保持、再開、ソフトリセット
+pub const REALTIME_COMMANDS: [char; 4] = ['?', '!', '~', '\u{18}'];
+
+/// Grbl
+/// 大文字と小文字を区別せず、小数点のない数値はmm単位、コメントは括弧の中と";"から行末までとする
+/// "$"で始まるシステムコマンド（設定、"$H"の原点復帰、"$J="のジョグ）とリアルタイムコマンドは位置を追跡しない
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub struct Grbl;
+
+impl Dialect for Grbl {
+    fn name(&self) -> &'static str {
+        "Grbl"
+    }
+
+    /// 小数点のない数値は常にmm単位とする
+    fn decimal_input(&self) -> Option<DecimalInput> {
+        Some(DecimalInput::Calculator)
+    }
+
+    fn tokenize(&self, line: &Line) -> Result<Vec<Statement>, Error> {
+        let code = code(line.get_line()).trim().to_ascii_uppercase();
+        if let Some(command) = system_command(&code) {
+            return Ok(vec![Statement::Command(command.to_string())]);
+        }
+        if !code.is_empty() && code.chars().all(|c| REALTIME_COMMANDS.contains(&c)) {
+            return Ok(code.chars().map(|c| Statement::Command(c.to_string())).collect());
+        }
+        words(&code, self.syntax(), false)
+    }
+
+    fn comments(&self, line: &Line) -> Vec<String> {
+        semicolon_comments(line.get_line())
+    }
+
+    /// G04のドウェルは秒単位のPで指令する
+    /// 追加のワーク座標系、H番号の工具長補正、固定サイクルはエラーとする
+    fn localize(&self, block: &Block) -> Result<Block, Error> {
+        let block = basic_g_codes(block)?;
+        if !block.words_of('G').any(|word| g_code(word.get_value()) == "G04") {
+            return Ok(block);
+        }
+        let words = block.get_words().iter().map(|word| match word.get_code() {
+            'P' => Ok(Word::new('P', &(word.to_f32()? / 1000.0).to_string())),
+            'X' => Ok(Word::new('P', word.get_value())),
+            _ => Ok(word.clone()),
+        }).collect::<Result<Vec<_>, Error>>()?;
+        Ok(Block::from_words(words, block.get_comments().to_vec()))
+    }
+
+    fn expressions(&self) -> bool {
+        false
+    }
+}
+
+/// "$"で始まるシステムコマンドであれば、"="より前のコマンド名を返す
+/// 例: "$$", "$H", "$110=5000" -> "$110", "$J=G91X10F100" -> "$J"
+pub fn system_command(code: &str) -> Option<&str> {
+    let code = code.trim();
+    code.starts_with('$').then(|| code.split_once('=').map_or(code, |(command, _)| command))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::structs::expression::Expression;
+
+    fn tokenize(line: &str) -> Vec<Statement> {
+        Grbl.tokenize(&Line::new(line.to_string())).unwrap()
+    }
+
+    #[test]
+    fn grbl_tokenize() {
+        let command = |name: &str| Statement::Command(name.to_string());
+        let datas = [
+            ("$H", vec![command("$H")]),
+            ("$110=5000.", vec![command("$110")]),
+            ("$j=g91 x10 f100", vec![command("$J")]),
+            ("?", vec![command("?")]),
+            ("!~", vec![command("!"), command("~")]),
+        ];
+        datas.iter().for_each(|(line, expected)| {
+            assert_eq!(tokenize(line), *expected);
+        });
+        assert_eq!(tokenize("g0 x10 ; RAPID")[1], Statement::Word('X', Expression::Number("10".to_string())));
+        assert_eq!(
+            Grbl.comments(&Line::new("G0 X10 (A) ; RAPID".to_string())),
+            ["A".to_string(), "RAPID".to_string()]
+        );
+    }
+}
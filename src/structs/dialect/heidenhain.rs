@@ -0,0 +1,422 @@
+use nom::branch::alt;
+use nom::bytes::complete::{tag, take_until};
+use nom::character::complete::{char, digit1, multispace0, multispace1, one_of, satisfy};
+use nom::combinator::{map, not, opt, recognize, rest, value};
+use nom::multi::many0;
+use nom::sequence::{delimited, pair, preceded, separated_pair, terminated, tuple};
+use nom::IResult;
+
+use crate::error::Error;
+use crate::structs::dialect::{Dialect, Direction, Jump, Statement};
+use crate::structs::expression::{expression, factor, Expression, Operator, Syntax, Variables};
+use crate::structs::line_parser::{m_code, number, Line};
+use crate::structs::machine_profile::DecimalInput;
+
+/// 穴あけサイクルを実行する呼び出しの名前
+const CYCLE_CALL: &str = "CYCL CALL";
+
+/// 位置の追跡に影響しないため解釈しない命令
+const IGNORED_COMMANDS: [&str; 6] = ["BEGIN PGM", "END PGM", "BLK FORM", "TOOL DEF", "LBL", "PLANE RESET"];
+
+/// ハイデンハイン（TNC 対話形式）
+/// 変数はQパラメータで、"FN 0: Q1 = +10"または"Q1 = Q2 * 2"で代入する
+/// コメントは";"から行末までと"*"で始まる行、"~"は次の行に続くことを示す
+/// 小数点のない数値はmm単位とし、直線"L"、円弧"C"、"CR"をG00、G01、G02、G03に置き換える
+/// "CC"で設定した円弧の中心は、"C"の円弧の中心として使用する
+/// 接線方向の円弧"CT"は終点までの直線として扱い、極座標の指令とサブプログラムの呼び出しには対応しない
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub struct Heidenhain;
+
+impl Dialect for Heidenhain {
+    fn name(&self) -> &'static str {
+        "Heidenhain"
+    }
+
+    fn syntax(&self) -> Syntax {
+        Syntax::new(q_parameter, ('(', ')'))
+    }
+
+    /// 小数点のない数値は常にmm単位とする
+    fn decimal_input(&self) -> Option<DecimalInput> {
+        Some(DecimalInput::Calculator)
+    }
+
+    fn tokenize(&self, line: &Line) -> Result<Vec<Statement>, Error> {
+        let (code, _) = split(line.get_line());
+        let syntax = self.syntax();
+        let (rest, statements) = tuple((
+            preceded(multispace0, opt(terminated(digit1, multispace0))),
+            alt((
+                value(Vec::new(), heading),
+                |input| command(input, syntax),
+                |input| motion(input, syntax),
+                map(|input| jump(input, syntax), |statement| vec![statement]),
+                map(|input| assignment(input, syntax), |statement| vec![statement]),
+                value(Vec::new(), multispace0),
+            )),
+            multispace0,
+        ))(code)
+            .map(|(rest, (number, statements, _))| {
+                let number = number.map(|number| Statement::Word('N', Expression::Number(number.to_string())));
+                (rest, number.into_iter().chain(statements).collect::<Vec<_>>())
+            })
+            .map_err(|e| Error::InvalidParser(format!("{:?}", e)))?;
+        if !rest.is_empty() {
+            return Err(Error::InvalidParser(rest.to_string()));
+        }
+        Ok(statements)
+    }
+
+    fn comments(&self, line: &Line) -> Vec<String> {
+        let (code, comment) = split(line.get_line());
+        let heading = preceded(pair(opt(digit1), multispace0), heading)(code)
+            .ok()
+            .map(|(_, heading)| heading);
+        heading.into_iter().chain(comment)
+            .map(|comment| comment.trim().to_string())
+            .filter(|comment| !comment.is_empty())
+            .collect()
+    }
+
+    fn label(&self, line: &str) -> Option<String> {
+        let (code, _) = split(line);
+        preceded(
+            tuple((multispace0, opt(digit1), multispace0, keyword("LBL"), multispace1)),
+            label,
+        )(code).ok().map(|(_, label)| label.to_string())
+    }
+
+    /// 穴あけサイクルは加工面の座標Q203から安全距離Q200と第2安全距離Q204の大きい方だけ上に復帰する
+    fn retract_plane(
+        &self,
+        name: &str,
+        _arguments: &[Option<Expression>],
+        variables: &Variables,
+    ) -> Result<Option<f32>, Error> {
+        let parameter = |name: &str| variables.get(name).copied();
+        if name != CYCLE_CALL {
+            return Ok(None);
+        }
+        Ok(parameter("Q203").map(|surface| {
+            surface + parameter("Q200").unwrap_or(0.0).max(parameter("Q204").unwrap_or(0.0))
+        }))
+    }
+
+    /// QパラメータQ1などを同じ番号の"#"に対応させる
+    fn variable_number(&self, name: &str) -> Option<u32> {
+        name.strip_prefix('Q')?.parse().ok()
+    }
+
+    fn variable_name(&self, number: u32) -> Option<String> {
+        Some(format!("Q{}", number))
+    }
+
+    fn comment(&self, text: &str) -> String {
+        format!("; {}", text)
+    }
+}
+
+/// 行をコメントの前後に分け、行末の"~"を除いて返す
+fn split(line: &str) -> (&str, Option<&str>) {
+    fn strip(part: &str) -> &str {
+        part.trim_end().trim_end_matches('~')
+    }
+    match line.split_once(';') {
+        Some((code, comment)) => (code, Some(strip(comment))),
+        None => (strip(line), None),
+    }
+}
+
+/// "*"で始まる見出しのコメントを返す
+fn heading(input: &str) -> IResult<&str, &str> {
+    preceded(char('*'), rest)(input)
+}
+
+/// Qパラメータ（"Q"、"QL"、"QR"、"QS"と番号）を認識して返す
+fn q_parameter(input: &str) -> IResult<&str, &str> {
+    recognize(pair(alt((tag("QL"), tag("QR"), tag("QS"), tag("Q"))), digit1))(input)
+}
+
+/// 名前の続きでないことを確認したキーワードを返す
+fn keyword<'a>(name: &'static str) -> impl Fn(&'a str) -> IResult<&'a str, &'a str> {
+    move |input| terminated(tag(name), not(satisfy(|c| c.is_ascii_alphanumeric() || c == '_')))(input)
+}
+
+/// ラベルの番号、または引用符で囲まれた名前を返す
+fn label(input: &str) -> IResult<&str, &str> {
+    alt((digit1, delimited(char('"'), take_until("\""), char('"'))))(input)
+}
+
+/// 命令に続く要素
+#[derive(Debug, PartialEq, Clone)]
+enum Element {
+    Statement(Statement),
+    /// 早送り（FMAX）
+    Rapid,
+    /// 円弧の回転方向（DR-を真とする時計回り）
+    Clockwise(bool),
+    /// 位置に影響しない要素
+    Ignored,
+}
+
+/// 空白で区切られた要素を順に返す
+fn elements(input: &str, syntax: Syntax) -> IResult<&str, Vec<Element>> {
+    many0(preceded(multispace1, |input| element(input, syntax)))(input)
+}
+
+/// 座標、送り速度、補助機能などの要素を返す
+/// M99は穴あけサイクルの呼び出し、M89はモーダルな呼び出しとする
+fn element(input: &str, syntax: Syntax) -> IResult<&str, Element> {
+    let sign = |input| one_of("+-")(input);
+    alt((
+        map(|input| axis(input, syntax), |(code, incremental, expression)| {
+            Element::Statement(match incremental {
+                true => Statement::Coordinate(code, true, expression),
+                false => Statement::Word(code, expression),
+            })
+        }),
+        value(Element::Ignored, alt((keyword("R0"), keyword("RL"), keyword("RR")))),
+        map(preceded(char('R'), factor(syntax)), |expression| Element::Statement(Statement::Word('R', expression))),
+        map(
+            terminated(preceded(tag("DR"), sign), not(satisfy(|c| c.is_ascii_digit() || c == '.'))),
+            |sign| Element::Clockwise(sign == '-'),
+        ),
+        value(Element::Ignored, pair(alt((tag("DR2"), tag("DL"), tag("DR"))), factor(syntax))),
+        value(Element::Rapid, keyword("FMAX")),
+        value(Element::Ignored, alt((keyword("FAUTO"), keyword("F AUTO")))),
+        map(
+            pair(one_of("FS"), factor(syntax)),
+            |(code, expression)| Element::Statement(Statement::Word(code, expression)),
+        ),
+        map(preceded(char('M'), number), |value| match m_code(value).as_str() {
+            "M99" => Element::Statement(cycle_call(false)),
+            "M89" => Element::Statement(cycle_call(true)),
+            _ => Element::Statement(Statement::Word('M', Expression::Number(value.to_string()))),
+        }),
+    ))(input)
+}
+
+/// 軸のアドレスと、増分値（"I"で始まる場合）かの真偽値、値を返す
+fn axis(input: &str, syntax: Syntax) -> IResult<&str, (char, bool, Expression)> {
+    map(
+        tuple((opt(char('I')), one_of("XYZABC"), factor(syntax))),
+        |(incremental, code, expression)| (code, incremental.is_some(), expression),
+    )(input)
+}
+
+/// 穴あけサイクルの呼び出しの文を返す
+fn cycle_call(modal: bool) -> Statement {
+    Statement::Call { name: CYCLE_CALL.to_string(), arguments: Vec::new(), modal }
+}
+
+/// 位置に影響しない命令、工具の呼び出し、サイクルの定義と呼び出しの文を返す
+/// 工具の呼び出しは工具交換と工具長補正の指令とする
+fn command(input: &str, syntax: Syntax) -> IResult<&str, Vec<Statement>> {
+    alt((
+        map(terminated(ignored, rest), |name| vec![Statement::Command(name.to_string())]),
+        // 新しいサイクルの定義はモーダルな呼び出しを取り消す
+        map(
+            terminated(keyword("CYCL DEF"), rest),
+            |name| vec![Statement::CancelModalCall, Statement::Command(name.to_string())],
+        ),
+        map(
+            preceded(keyword(CYCLE_CALL), |input| elements(input, syntax)),
+            |elements| std::iter::once(cycle_call(false)).chain(statements(elements)).collect(),
+        ),
+        map(
+            preceded(keyword("STOP"), |input| elements(input, syntax)),
+            |elements| std::iter::once(Statement::Command("STOP".to_string())).chain(statements(elements)).collect(),
+        ),
+        map(
+            tuple((
+                keyword("TOOL CALL"),
+                opt(preceded(multispace1, label)),
+                opt(preceded(multispace1, terminated(one_of("XYZ"), not(satisfy(|c| !c.is_whitespace()))))),
+                |input| elements(input, syntax),
+            )),
+            |(_, tool, _, elements)| {
+                let tool = tool.map(|tool| match tool.parse::<u32>() {
+                    Ok(_) => vec![
+                        Statement::Word('T', Expression::Number(tool.to_string())),
+                        Statement::Word('M', Expression::Number("06".to_string())),
+                        Statement::Word('G', Expression::Number("43".to_string())),
+                        Statement::Word('H', Expression::Number(tool.to_string())),
+                    ],
+                    Err(_) => vec![
+                        Statement::Command(tool.to_string()),
+                        Statement::Word('M', Expression::Number("06".to_string())),
+                    ],
+                });
+                tool.into_iter().flatten().chain(statements(elements)).collect()
+            },
+        ),
+    ))(input)
+}
+
+/// 直線、円弧の中心、円弧の文を返す
+/// 直線はFMAXがある場合に早送り、円弧の回転方向の指定がない場合は反時計回りとする
+fn motion(input: &str, syntax: Syntax) -> IResult<&str, Vec<Statement>> {
+    alt((
+        map(
+            preceded(keyword("CC"), many0(preceded(multispace1, |input| axis(input, syntax)))),
+            |axes| vec![Statement::SetCenter(axes)],
+        ),
+        map(
+            pair(
+                alt((keyword("L"), keyword("CR"), keyword("CT"), keyword("C"))),
+                |input| elements(input, syntax),
+            ),
+            |(command, elements)| {
+                let rapid = elements.contains(&Element::Rapid);
+                let clockwise = elements.contains(&Element::Clockwise(true));
+                let motion = match command {
+                    "L" if rapid => "0",
+                    "L" | "CT" => "1",
+                    _ if clockwise => "2",
+                    _ => "3",
+                };
+                let center = (command == "C").then_some(Statement::UseCenter);
+                std::iter::once(Statement::Word('G', Expression::Number(motion.to_string())))
+                    .chain(center)
+                    .chain(statements(elements))
+                    .collect()
+            },
+        ),
+    ))(input)
+}
+
+/// 要素のうち文であるものを返す
+fn statements(elements: Vec<Element>) -> impl Iterator<Item = Statement> {
+    elements.into_iter().filter_map(|element| match element {
+        Element::Statement(statement) => Some(statement),
+        _ => None,
+    })
+}
+
+/// 位置に影響しない命令の名前を返す
+fn ignored(input: &str) -> IResult<&str, &str> {
+    IGNORED_COMMANDS.iter()
+        .find_map(|name| keyword(name)(input).ok())
+        .ok_or_else(|| nom::Err::Error(nom::error::Error::new(input, nom::error::ErrorKind::Tag)))
+}
+
+/// 条件付きジャンプの文を返す
+/// 例: "FN 9: IF +Q1 EQU +Q2 GOTO LBL 5"
+fn jump(input: &str, syntax: Syntax) -> IResult<&str, Statement> {
+    let (input, operator) = delimited(
+        pair(keyword("FN"), multispace0),
+        alt((
+            value(Operator::Equal, tag("9")),
+            value(Operator::NotEqual, tag("10")),
+            value(Operator::Greater, tag("11")),
+            value(Operator::Less, tag("12")),
+        )),
+        tuple((char(':'), multispace0, keyword("IF"), multispace1)),
+    )(input)?;
+    let (input, (left, right, label)) = tuple((
+        expression(syntax),
+        preceded(
+            delimited(multispace1, alt((tag("EQU"), tag("NE"), tag("GT"), tag("LT"))), multispace1),
+            expression(syntax),
+        ),
+        preceded(tuple((multispace1, keyword("GOTO"), multispace1, keyword("LBL"), multispace1)), label),
+    ))(input)?;
+    let condition = Expression::Binary(operator, Box::new(left), Box::new(right));
+    Ok((input, Statement::Goto(Some(condition), Jump::new(label, Direction::Both))))
+}
+
+/// Qパラメータへの代入の文を返す
+/// 四則演算の関数"FN 0"から"FN 4"は式として記述されているため、式の代入とする
+fn assignment(input: &str, syntax: Syntax) -> IResult<&str, Statement> {
+    map(
+        preceded(
+            opt(tuple((keyword("FN"), multispace0, one_of("01234"), char(':'), multispace0))),
+            separated_pair(q_parameter, delimited(multispace0, char('='), multispace0), expression(syntax)),
+        ),
+        |(name, expression)| Statement::Assignment(name.to_string(), expression),
+    )(input)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tokenize(line: &str) -> Vec<Statement> {
+        Heidenhain.tokenize(&Line::new(line.to_string())).unwrap()
+    }
+
+    fn word(code: char, value: &str) -> Statement {
+        Statement::Word(code, Expression::Number(value.to_string()))
+    }
+
+    #[test]
+    fn heidenhain_tokenize_motion() {
+        assert_eq!(tokenize("5 L X+10 Y-5.5 R0 FMAX M3"), [
+            word('N', "5"),
+            word('G', "0"),
+            word('X', "+10"),
+            word('Y', "-5.5"),
+            word('M', "3"),
+        ]);
+        assert_eq!(tokenize("L IZ-2 RL F200"), [
+            word('G', "1"),
+            Statement::Coordinate('Z', true, Expression::Number("-2".to_string())),
+            word('F', "200"),
+        ]);
+        assert_eq!(tokenize("CC X+25 IY+5"), [Statement::SetCenter(vec![
+            ('X', false, Expression::Number("+25".to_string())),
+            ('Y', true, Expression::Number("+5".to_string())),
+        ])]);
+        assert_eq!(tokenize("C X+50 Y+25 DR-"), [word('G', "2"), Statement::UseCenter, word('X', "+50"), word('Y', "+25")]);
+        assert_eq!(tokenize("CR X+0 Y+0 R+20 DR+"), [word('G', "3"), word('X', "+0"), word('Y', "+0"), word('R', "+20")]);
+        assert_eq!(tokenize("L X+Q1 Y-Q2")[1], Statement::Word('X', Expression::Variable("Q1".to_string())));
+    }
+
+    #[test]
+    fn heidenhain_tokenize_commands() {
+        assert_eq!(tokenize("0 BEGIN PGM DRILL MM"), [word('N', "0"), Statement::Command("BEGIN PGM".to_string())]);
+        assert_eq!(tokenize("3 TOOL CALL 12 Z S3000 F150 DL+0.1"), [
+            word('N', "3"),
+            word('T', "12"),
+            word('M', "06"),
+            word('G', "43"),
+            word('H', "12"),
+            word('S', "3000"),
+            word('F', "150"),
+        ]);
+        assert_eq!(tokenize("TOOL CALL Z S5000"), [word('S', "5000")]);
+        assert_eq!(tokenize("7 CYCL DEF 200 DRILLING ~"), [
+            word('N', "7"),
+            Statement::CancelModalCall,
+            Statement::Command("CYCL DEF".to_string()),
+        ]);
+        assert_eq!(tokenize("  Q200=2 ;SET-UP CLEARANCE ~"), [
+            Statement::Assignment("Q200".to_string(), Expression::Number("2".to_string())),
+        ]);
+        assert!(matches!(&tokenize("FN 1: Q1 = +Q2 + +10")[0], Statement::Assignment(name, Expression::Binary(..)) if name == "Q1"));
+        assert!(matches!(
+            &tokenize("FN 11: IF +Q1 GT +0 GOTO LBL 2")[0],
+            Statement::Goto(Some(Expression::Binary(Operator::Greater, ..)), jump) if jump.get_label() == "2"
+        ));
+        assert_eq!(tokenize("L X+10 FMAX M99")[2], cycle_call(false));
+        assert_eq!(tokenize("9 * - ROUGHING"), [word('N', "9")]);
+
+        let result = Heidenhain.tokenize(&Line::new("CALL LBL 1".to_string()));
+        assert_eq!(result, Err(Error::InvalidParser("CALL LBL 1".to_string())));
+    }
+
+    #[test]
+    fn heidenhain_comments_and_label() {
+        let datas = [
+            ("9 * - ROUGHING", vec!["- ROUGHING"]),
+            ("Q200=2 ;SET-UP CLEARANCE ~", vec!["SET-UP CLEARANCE"]),
+            ("L X+1", vec![]),
+        ];
+        datas.iter().for_each(|(line, expected)| {
+            assert_eq!(Heidenhain.comments(&Line::new(line.to_string())), *expected);
+        });
+        assert_eq!(Heidenhain.label("12 LBL 2"), Some("2".to_string()));
+        assert_eq!(Heidenhain.label("LBL \"CONTOUR\""), Some("CONTOUR".to_string()));
+        assert_eq!(Heidenhain.label("L X+1"), None);
+    }
+}
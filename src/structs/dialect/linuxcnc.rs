@@ -0,0 +1,202 @@
+use nom::branch::alt;
+use nom::bytes::complete::{tag, take_until};
+use nom::character::complete::{char, digit1, multispace0, satisfy};
+use nom::combinator::{map, not, opt, recognize};
+use nom::multi::many0;
+use nom::sequence::{delimited, pair, preceded, terminated, tuple};
+use nom::IResult;
+
+use crate::error::Error;
+use crate::structs::block::{Block, Word};
+use crate::structs::dialect::{block_label, code, extended_work_offset, semicolon_comments, words, Dialect, Statement};
+use crate::structs::expression::{bracket, sharp_variable, Function, Syntax};
+use crate::structs::line_parser::{g_code, separator, Line};
+use crate::structs::machine_profile::DecimalInput;
+
+/// LinuxCNC（RS274NGC）
+/// 大文字と小文字を区別せず、小数点のない数値はmm単位、変数は"#1"と名前付きの"#<name>"（"_"で始まる名前はグローバル）とする
+/// コメントは括弧の中と";"から行末まで、"(DEBUG,...)"などのメッセージもコメントとして扱う
+/// Oワードのサブプログラム、条件分岐、繰り返しの行は"O100 SUB"のように番号とキーワードをラベルとする
+/// 追加のワーク座標系はG59.1-G59.3の3つとする
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub struct LinuxCnc;
+
+impl Dialect for LinuxCnc {
+    fn name(&self) -> &'static str {
+        "LinuxCNC"
+    }
+
+    fn syntax(&self) -> Syntax {
+        Syntax::new(variable, ('[', ']'))
+    }
+
+    /// 小数点のない数値は常にmm単位とする
+    fn decimal_input(&self) -> Option<DecimalInput> {
+        Some(DecimalInput::Calculator)
+    }
+
+    fn tokenize(&self, line: &Line) -> Result<Vec<Statement>, Error> {
+        let code = code(line.get_line()).to_ascii_uppercase();
+        let syntax = self.syntax();
+        let result = delimited(separator, |input| o_word(input, syntax), separator)(&code);
+        match result {
+            Ok(("", statement)) => Ok(vec![statement]),
+            Ok((rest, _)) => Err(Error::InvalidParser(rest.to_string())),
+            Err(_) => words(&code, syntax, false),
+        }
+    }
+
+    fn comments(&self, line: &Line) -> Vec<String> {
+        semicolon_comments(line.get_line())
+    }
+
+    fn label(&self, line: &str) -> Option<String> {
+        let code = code(line).to_ascii_uppercase();
+        let result = preceded(separator, pair(o_number, preceded(multispace0, keyword)))(code.as_str())
+            .ok()
+            .map(|(_, (number, keyword))| block_label(number, keyword));
+        result
+    }
+
+    /// G54.1 P1-P3をG59.1-G59.3に置き換える
+    fn localize(&self, block: &Block) -> Result<Block, Error> {
+        let extended = extended_work_offset(block)?;
+        let mut words = Vec::new();
+        for word in block.get_words() {
+            match (word.get_code(), g_code(word.get_value()).as_str()) {
+                ('G', "G54.1") => match extended {
+                    Some(number @ 1..=3) => words.push(Word::new('G', &format!("59.{}", number))),
+                    _ => return Err(Error::InvalidCode('P')),
+                },
+                ('P', _) if extended.is_some() => {},
+                _ => words.push(word.clone()),
+            }
+        }
+        Ok(Block::from_words(words, block.get_comments().to_vec()))
+    }
+
+    fn function(&self, function: Function, argument: &str) -> String {
+        match function {
+            Function::Atan => format!("ATAN[{}]/[1]", argument),
+            _ => format!("{}[{}]", function.name(), argument),
+        }
+    }
+}
+
+/// 番号の変数と名前付きの変数を認識して返す
+/// 例: "#1", "#<_DEPTH>"
+fn variable(input: &str) -> IResult<&str, &str> {
+    alt((
+        recognize(tuple((tag("#<"), take_until(">"), char('>')))),
+        sharp_variable,
+    ))(input)
+}
+
+/// Oワードの番号または名前を返す
+/// 例: "O100", "O<DRILL>"
+fn o_number(input: &str) -> IResult<&str, &str> {
+    recognize(preceded(
+        char('O'),
+        alt((digit1, recognize(tuple((char('<'), take_until(">"), char('>')))))),
+    ))(input)
+}
+
+/// Oワードに続くキーワードを返す
+fn keyword(input: &str) -> IResult<&str, &str> {
+    terminated(
+        recognize(many0(satisfy(|c| c.is_ascii_uppercase()))),
+        not(satisfy(|c| c.is_ascii_alphanumeric())),
+    )(input)
+}
+
+/// Oワードの行の文を返す
+/// 条件と引数は角括弧で囲む
+fn o_word(input: &str, syntax: Syntax) -> IResult<&str, Statement> {
+    let (input, (number, keyword)) = pair(o_number, preceded(multispace0, keyword))(input)?;
+    let argument = |input| preceded(multispace0, bracket(syntax))(input);
+    let number = number.to_string();
+    match keyword {
+        "SUB" => Ok((input, Statement::Sub(number))),
+        "ENDSUB" => map(opt(argument), |value| Statement::EndSub(number.clone(), value))(input),
+        "RETURN" => map(opt(argument), |value| Statement::Return(number.clone(), value))(input),
+        "CALL" => map(many0(argument), |arguments| Statement::SubCall(number.clone(), arguments))(input),
+        "IF" => map(argument, Statement::If)(input),
+        "ELSEIF" => map(argument, Statement::ElseIf)(input),
+        "ELSE" => Ok((input, Statement::Else)),
+        "ENDIF" => Ok((input, Statement::EndIf)),
+        "WHILE" => map(argument, |condition| Statement::While(number.clone(), condition))(input),
+        "DO" => Ok((input, Statement::Do(number))),
+        "REPEAT" => map(argument, |count| Statement::Repeat(number.clone(), count))(input),
+        "ENDWHILE" | "ENDREPEAT" => Ok((input, Statement::EndLoop(number))),
+        "BREAK" => Ok((input, Statement::Break(number))),
+        "CONTINUE" => Ok((input, Statement::Continue(number))),
+        _ => Err(nom::Err::Error(nom::error::Error::new(input, nom::error::ErrorKind::Tag))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::structs::expression::Expression;
+
+    fn tokenize(line: &str) -> Vec<Statement> {
+        LinuxCnc.tokenize(&Line::new(line.to_string())).unwrap()
+    }
+
+    #[test]
+    fn linuxcnc_tokenize_o_word() {
+        let variable = |name: &str| Expression::Variable(name.to_string());
+        assert_eq!(tokenize("o100 sub"), [Statement::Sub("O100".to_string())]);
+        assert_eq!(
+            tokenize("o<drill> call [10] [#<_depth>] (DEBUG, CALL)"),
+            [Statement::SubCall("O<DRILL>".to_string(), vec![
+                Expression::Number("10".to_string()),
+                variable("#<_DEPTH>"),
+            ])]
+        );
+        assert_eq!(tokenize("O100 ENDSUB"), [Statement::EndSub("O100".to_string(), None)]);
+        assert_eq!(tokenize("o100 return [#1*2]")[0], Statement::Return("O100".to_string(), Some(
+            Expression::Binary(
+                crate::structs::expression::Operator::Multiply,
+                Box::new(variable("#1")),
+                Box::new(Expression::Number("2".to_string())),
+            )
+        )));
+        assert!(matches!(&tokenize("o101 if [#1 GT 5]")[0], Statement::If(Expression::Binary(..))));
+        assert!(matches!(&tokenize("o101 elseif [#1 EQ 5]")[0], Statement::ElseIf(_)));
+        assert_eq!(tokenize("o101 else"), [Statement::Else]);
+        assert_eq!(tokenize("o101 endif"), [Statement::EndIf]);
+        assert!(matches!(&tokenize("o102 while [#<i> LT 3]")[0], Statement::While(number, _) if number == "O102"));
+        assert_eq!(tokenize("o103 do"), [Statement::Do("O103".to_string())]);
+        assert!(matches!(&tokenize("o104 repeat [5]")[0], Statement::Repeat(number, _) if number == "O104"));
+        assert_eq!(tokenize("o104 endrepeat"), [Statement::EndLoop("O104".to_string())]);
+        assert_eq!(tokenize("o102 break"), [Statement::Break("O102".to_string())]);
+
+        let result = LinuxCnc.tokenize(&Line::new("o105 goto".to_string()));
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn linuxcnc_tokenize_words() {
+        let result = tokenize("#<depth> = -2 g1 z#<depth> f100 ; PLUNGE");
+        assert_eq!(result[0], Statement::Assignment("#<DEPTH>".to_string(), Expression::Number("-2".to_string())));
+        assert_eq!(result[2], Statement::Word('Z', Expression::Variable("#<DEPTH>".to_string())));
+        assert_eq!(
+            LinuxCnc.comments(&Line::new("g0 x1 (DEBUG, X=#1) ; NOTE".to_string())),
+            ["DEBUG, X=#1".to_string(), "NOTE".to_string()]
+        );
+    }
+
+    #[test]
+    fn linuxcnc_label() {
+        let datas = [
+            ("o100 sub", Some("O100 SUB")),
+            ("  O<drill> sub (DRILL)", Some("O<DRILL> SUB")),
+            ("o102 while [#1 LT 3]", Some("O102 WHILE")),
+            ("g0 x1", None),
+        ];
+        datas.iter().for_each(|&(line, expected)| {
+            assert_eq!(LinuxCnc.label(line), expected.map(str::to_string));
+        });
+    }
+}
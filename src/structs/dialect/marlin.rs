@@ -0,0 +1,147 @@
+use nom::character::complete::{multispace0, satisfy};
+use nom::combinator::opt;
+use nom::sequence::{delimited, pair};
+
+use crate::error::Error;
+use crate::structs::block::{Block, Word};
+use crate::structs::dialect::{basic_g_codes, code, semicolon_comments, Dialect, Statement};
+use crate::structs::expression::Expression;
+use crate::structs::line_parser::{g_code, m_code, number, Line};
+use crate::structs::machine_profile::DecimalInput;
+
+/// 文字列を引数とするMコード（表示するメッセージ、ファイル名）
+const TEXT_M_CODES: [&str; 5] = ["M23", "M28", "M30", "M117", "M118"];
+
+/// 原点復帰で値を指定しない軸
+const HOMING_AXES: [char; 3] = ['X', 'Y', 'Z'];
+
+/// Marlin（3Dプリンタ）
+/// 小数点のない数値はmm単位、コメントは";"から行末までとし、押出し軸Eと温度の指令（M104、M109など）はワードとして保持する
+/// 原点復帰G28は指定した軸（指定がない場合は全軸）の原点への早送りに置き換える
+/// 現在位置の設定G92は押出し軸のみに対応する
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub struct Marlin;
+
+impl Dialect for Marlin {
+    fn name(&self) -> &'static str {
+        "Marlin"
+    }
+
+    /// 小数点のない数値は常にmm単位とする
+    fn decimal_input(&self) -> Option<DecimalInput> {
+        Some(DecimalInput::Calculator)
+    }
+
+    /// G28の軸は"G28 X Y"のように値を省略でき、M117などは以降の文字列を引数とする
+    fn tokenize(&self, line: &Line) -> Result<Vec<Statement>, Error> {
+        let mut input = code(line.get_line()).trim();
+        let mut words = Vec::new();
+        let mut text = None;
+        while !input.is_empty() {
+            let (rest, (code, value)) = delimited(
+                multispace0,
+                pair(satisfy(|c| c.is_ascii_alphabetic()), opt(number)),
+                multispace0,
+            )(input)
+                .map_err(|_: nom::Err<nom::error::Error<&str>>| Error::InvalidParser(input.to_string()))?;
+            input = rest;
+            words.push((code.to_ascii_uppercase(), value));
+            if code.eq_ignore_ascii_case(&'M') && value.is_some_and(|value| TEXT_M_CODES.contains(&m_code(value).as_str())) {
+                text = Some(Statement::Command(input.to_string()));
+                break;
+            }
+        }
+        let homing = words.iter().any(|&(code, value)| code == 'G' && value.is_some_and(|value| g_code(value) == "G28"));
+        let mut statements = Vec::new();
+        for (code, value) in words {
+            let value = match value {
+                Some(value) => value,
+                None if homing && HOMING_AXES.contains(&code) => "0",
+                None => return Err(Error::InvalidCode(code)),
+            };
+            statements.push(Statement::Word(code, Expression::Number(value.to_string())));
+        }
+        statements.extend(text);
+        Ok(statements)
+    }
+
+    fn comments(&self, line: &Line) -> Vec<String> {
+        semicolon_comments(line.get_line())
+    }
+
+    fn canonical(&self, block: &Block) -> Result<Block, Error> {
+        let g_codes: Vec<String> = block.words_of('G').map(|word| g_code(word.get_value())).collect();
+        if g_codes.iter().any(|code| code == "G92") {
+            if let Some(word) = block.get_words().iter().find(|word| HOMING_AXES.contains(&word.get_code())) {
+                return Err(Error::InvalidCode(word.get_code()));
+            }
+        }
+        if !g_codes.iter().any(|code| code == "G28") {
+            return Ok(block.clone());
+        }
+        let mut words: Vec<Word> = block.get_words().iter().map(|word| match word.get_code() {
+            'G' if g_code(word.get_value()) == "G28" => Word::new('G', "00"),
+            code if HOMING_AXES.contains(&code) => Word::new(code, "0."),
+            _ => word.clone(),
+        }).collect();
+        if !words.iter().any(|word| HOMING_AXES.contains(&word.get_code())) {
+            words.extend(HOMING_AXES.iter().map(|&code| Word::new(code, "0.")));
+        }
+        Ok(Block::from_words(words, block.get_comments().to_vec()))
+    }
+
+    /// 追加のワーク座標系、H番号の工具長補正、固定サイクルはエラーとする
+    fn localize(&self, block: &Block) -> Result<Block, Error> {
+        basic_g_codes(block)
+    }
+
+    fn expressions(&self) -> bool {
+        false
+    }
+
+    fn comment(&self, text: &str) -> String {
+        format!("; {}", text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn canonical(line: &str) -> Result<Vec<(char, String)>, Error> {
+        let mut words = Vec::new();
+        for statement in Marlin.tokenize(&Line::new(line.to_string()))? {
+            if let Statement::Word(code, Expression::Number(value)) = statement {
+                words.push(Word::new(code, &value));
+            }
+        }
+        let block = Marlin.canonical(&Block::from_words(words, Vec::new()))?;
+        Ok(block.get_words().iter().map(|word| (word.get_code(), word.get_value().to_string())).collect())
+    }
+
+    #[test]
+    fn marlin_canonical() {
+        let datas = [
+            ("G28", vec![('G', "00"), ('X', "0."), ('Y', "0."), ('Z', "0.")]),
+            ("G28 X Y", vec![('G', "00"), ('X', "0."), ('Y', "0.")]),
+            ("G1 X10.5 E0.42 F1200 ; PERIMETER", vec![('G', "1"), ('X', "10.5"), ('E', "0.42"), ('F', "1200")]),
+            ("M109 S210", vec![('M', "109"), ('S', "210")]),
+            ("G92 E0", vec![('G', "92"), ('E', "0")]),
+        ];
+        datas.iter().for_each(|(line, expected)| {
+            let expected: Vec<(char, String)> = expected.iter().map(|&(code, value)| (code, value.to_string())).collect();
+            assert_eq!(canonical(line).unwrap(), expected, "{}", line);
+        });
+        assert_eq!(canonical("G1 X"), Err(Error::InvalidCode('X')));
+        assert_eq!(canonical("G92 X0"), Err(Error::InvalidCode('X')));
+    }
+
+    #[test]
+    fn marlin_tokenize_message() {
+        let result = Marlin.tokenize(&Line::new("M117 Printing 1/2".to_string())).unwrap();
+        assert_eq!(result, [
+            Statement::Word('M', Expression::Number("117".to_string())),
+            Statement::Command("Printing 1/2".to_string()),
+        ]);
+    }
+}
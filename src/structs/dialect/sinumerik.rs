@@ -0,0 +1,432 @@
+use nom::branch::alt;
+use nom::bytes::complete::{tag, take_until};
+use nom::character::complete::{alpha1, alphanumeric1, char, multispace0, multispace1, satisfy};
+use nom::combinator::{map, not, opt, recognize, value};
+use nom::multi::{many0, separated_list0, separated_list1};
+use nom::sequence::{delimited, pair, preceded, separated_pair, terminated, tuple};
+use nom::IResult;
+
+use crate::error::Error;
+use crate::structs::block::{Block, Word};
+use crate::structs::dialect::{extended_work_offset, Dialect, Direction, FrameCommand, Jump, Statement};
+use crate::structs::expression::{condition, expression, Expression, Function, Syntax, Variables};
+use crate::structs::line_parser::{g_code, m_code, number, Line};
+use crate::structs::machine_profile::DecimalInput;
+
+/// 位置の追跡に影響しない複数文字のアドレス
+const IGNORED_ADDRESSES: [&str; 8] = ["AR", "TURN", "RND", "RNDM", "CHF", "CHR", "CT", "SF"];
+
+/// シーメンス（SINUMERIK 840D）
+/// 変数はRパラメータと"DEF"で定義した名前で、"X=R1+10"のように式を書ける
+/// コメントは";"から行末まで、行の先頭の"LABEL:"をジャンプ先のラベルとする
+/// 小数点のない数値はmm単位、CR=は円弧の半径、AC()とIC()は座標ごとの絶対値と増分値の指定とする
+/// 追加のワーク座標系G505-G599をG54.1 P1-P95に置き換える
+/// RパラメータR0-R99はファナック系の#0-#99に対応させる
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub struct Sinumerik;
+
+impl Dialect for Sinumerik {
+    fn name(&self) -> &'static str {
+        "Sinumerik"
+    }
+
+    fn syntax(&self) -> Syntax {
+        Syntax::new(identifier, ('(', ')'))
+    }
+
+    fn word_assignment(&self) -> bool {
+        true
+    }
+
+    /// 小数点のない数値は常にmm単位とする
+    fn decimal_input(&self) -> Option<DecimalInput> {
+        Some(DecimalInput::Calculator)
+    }
+
+    fn tokenize(&self, line: &Line) -> Result<Vec<Statement>, Error> {
+        let code = code(line.get_line());
+        let syntax = self.syntax();
+        let (rest, statements) = preceded(
+            opt(pair(multispace0, char('/'))),
+            many0(delimited(multispace0, |input| statements(input, syntax), multispace0)),
+        )(code)
+            .map_err(|e| Error::InvalidParser(format!("{:?}", e)))?;
+        if !rest.is_empty() {
+            return Err(Error::InvalidParser(rest.to_string()));
+        }
+        Ok(statements.into_iter().flatten().collect())
+    }
+
+    fn comments(&self, line: &Line) -> Vec<String> {
+        line.get_line().split_once(';')
+            .map(|(_, comment)| vec![comment.trim().to_string()])
+            .unwrap_or_default()
+    }
+
+    fn label(&self, line: &str) -> Option<String> {
+        label(code(line)).ok().map(|(_, label)| label.to_string())
+    }
+
+    /// CYCLE81からCYCLE89の穴あけサイクルは第1引数（RTP）に復帰する
+    fn retract_plane(
+        &self,
+        name: &str,
+        arguments: &[Option<Expression>],
+        variables: &Variables,
+    ) -> Result<Option<f32>, Error> {
+        let drilling = name.strip_prefix("CYCLE8")
+            .is_some_and(|number| matches!(number.as_bytes(), [b'1'..=b'9']));
+        match arguments.first() {
+            Some(Some(expression)) if drilling => expression.evaluate(variables),
+            _ => Ok(None),
+        }
+    }
+
+    fn canonical(&self, block: &Block) -> Result<Block, Error> {
+        let words = block.get_words().iter().flat_map(|word| {
+            let code = g_code(word.get_value());
+            match (word.get_code(), code[1..].parse::<u32>()) {
+                ('G', Ok(number @ 505..=599)) => {
+                    vec![Word::new('G', "54.1"), Word::new('P', &(number - 504).to_string())]
+                },
+                _ => vec![word.clone()],
+            }
+        }).collect();
+        Ok(Block::from_words(words, block.get_comments().to_vec()))
+    }
+
+    /// G54.1 P1-P95をG505-G599に、G04のドウェルを秒単位のG4 Fに、G49をD0に置き換える
+    /// 工具長補正のG43、G44、ローカル座標系のG52、固定サイクル、プログラム番号とサブプログラムの呼び出しはエラーとする
+    fn localize(&self, block: &Block) -> Result<Block, Error> {
+        let extended = extended_work_offset(block)?;
+        let dwell = block.words_of('G').any(|word| g_code(word.get_value()) == "G04");
+        let mut words = Vec::new();
+        for word in block.get_words() {
+            let code = g_code(word.get_value());
+            match (word.get_code(), code.as_str()) {
+                ('G', "G54.1") => match extended {
+                    Some(number @ 1..=95) => words.push(Word::new('G', &(number + 504).to_string())),
+                    _ => return Err(Error::InvalidCode('P')),
+                },
+                ('P', _) if extended.is_some() => {},
+                ('G', "G49") => words.push(Word::new('D', "0")),
+                ('G', "G43" | "G44" | "G52" | "G98" | "G99") => return Err(Error::InvalidCode('G')),
+                ('G', _) if code[1..].parse::<u32>().is_ok_and(|number| (73..=89).contains(&number)) => {
+                    return Err(Error::InvalidCode('G'));
+                },
+                ('P', _) if dwell => words.push(Word::new('F', &(word.to_f32()? / 1000.0).to_string())),
+                ('X', _) if dwell => words.push(Word::new('F', word.get_value())),
+                ('M', _) if matches!(m_code(word.get_value()).as_str(), "M98" | "M99") => {
+                    return Err(Error::InvalidCode('M'));
+                },
+                ('O' | 'H', _) => return Err(Error::InvalidCode(word.get_code())),
+                _ => words.push(word.clone()),
+            }
+        }
+        Ok(Block::from_words(words, block.get_comments().to_vec()))
+    }
+
+    /// RパラメータR0-R99を#0-#99に対応させる
+    fn variable_number(&self, name: &str) -> Option<u32> {
+        match name.strip_prefix('R')?.parse::<u32>().ok()? {
+            number @ 0..=99 => Some(number),
+            _ => None,
+        }
+    }
+
+    fn variable_name(&self, number: u32) -> Option<String> {
+        (number <= 99).then(|| format!("R{}", number))
+    }
+
+    fn function(&self, function: Function, argument: &str) -> String {
+        match function {
+            Function::Atan => format!("ATAN2({},1)", argument),
+            Function::Fix => format!("TRUNC({})", argument),
+            Function::Fup => format!("ROUNDUP({})", argument),
+            _ => format!("{}({})", function.name(), argument),
+        }
+    }
+
+    fn comment(&self, text: &str) -> String {
+        format!("; {}", text)
+    }
+}
+
+/// コメントを除いた文字列を返す
+fn code(line: &str) -> &str {
+    line.split_once(';').map_or(line, |(code, _)| code)
+}
+
+/// 英字または"_"で始まる名前を認識して返す
+fn identifier(input: &str) -> IResult<&str, &str> {
+    recognize(pair(
+        alt((alpha1, tag("_"))),
+        many0(alt((alphanumeric1, tag("_")))),
+    ))(input)
+}
+
+/// 名前の続きでないことを確認したキーワードを返す
+fn keyword<'a>(name: &'static str) -> impl Fn(&'a str) -> IResult<&'a str, &'a str> {
+    move |input| terminated(tag(name), not(satisfy(|c| c.is_ascii_alphanumeric() || c == '_')))(input)
+}
+
+/// 行の先頭のブロック番号に続くラベルを返す
+fn label(input: &str) -> IResult<&str, &str> {
+    preceded(
+        tuple((multispace0, opt(tuple((char('N'), number, multispace1))))),
+        terminated(identifier, char(':')),
+    )(input)
+}
+
+/// 1つの命令に含まれる文を返す
+fn statements(input: &str, syntax: Syntax) -> IResult<&str, Vec<Statement>> {
+    alt((
+        map(terminated(identifier, char(':')), |_| Vec::new()),
+        definition(syntax),
+        map(|input| control(input, syntax), |statement| vec![statement]),
+        map(|input| frame(input, syntax), |statement| vec![statement]),
+        map(|input| call(input, syntax), |statement| vec![statement]),
+        map(|input| assignment(input, syntax), |statement| vec![statement]),
+        map(
+            pair(satisfy(|c| c.is_ascii_uppercase()), number),
+            |(code, value)| vec![Statement::Word(code, Expression::Number(value.to_string()))],
+        ),
+        map(identifier, |name| vec![Statement::Command(name.to_string())]),
+    ))(input)
+}
+
+/// 変数の定義を代入の文として返す
+/// 初期値を指定しない変数は0とする
+/// 例: "DEF REAL DEPTH=5, FEED"
+fn definition(syntax: Syntax) -> impl Fn(&str) -> IResult<&str, Vec<Statement>> {
+    move |input| {
+        preceded(
+            tuple((keyword("DEF"), multispace1, identifier, multispace1)),
+            separated_list1(
+                tuple((multispace0, char(','), multispace0)),
+                map(
+                    pair(identifier, opt(preceded(delimited(multispace0, char('='), multispace0), expression(syntax)))),
+                    |(name, expression)| {
+                        let expression = expression.unwrap_or(Expression::Number("0".to_string()));
+                        Statement::Assignment(name.to_string(), expression)
+                    },
+                ),
+            ),
+        )(input)
+    }
+}
+
+/// 条件分岐とジャンプの文を返す
+fn control(input: &str, syntax: Syntax) -> IResult<&str, Statement> {
+    alt((
+        map(
+            pair(
+                preceded(pair(keyword("IF"), multispace1), condition(syntax)),
+                opt(preceded(multispace1, jump)),
+            ),
+            |(condition, jump)| match jump {
+                Some(jump) => Statement::Goto(Some(condition), jump),
+                None => Statement::If(condition),
+            },
+        ),
+        value(Statement::Else, keyword("ELSE")),
+        value(Statement::EndIf, keyword("ENDIF")),
+        map(jump, |jump| Statement::Goto(None, jump)),
+    ))(input)
+}
+
+/// ジャンプ先を返す
+fn jump(input: &str) -> IResult<&str, Jump> {
+    map(
+        separated_pair(
+            alt((
+                value(Direction::Forward, keyword("GOTOF")),
+                value(Direction::Backward, keyword("GOTOB")),
+                value(Direction::Both, keyword("GOTO")),
+            )),
+            multispace1,
+            identifier,
+        ),
+        |(direction, label)| Jump::new(label, direction),
+    )(input)
+}
+
+/// 座標変換の文を返す
+/// 回転はXY平面のみとし、"RPL="または"Z"で角度を指定する
+fn frame(input: &str, syntax: Syntax) -> IResult<&str, Statement> {
+    let (input, command) = alt((
+        value(FrameCommand::Trans, keyword("TRANS")),
+        value(FrameCommand::Atrans, keyword("ATRANS")),
+        value(FrameCommand::Rot, keyword("ROT")),
+        value(FrameCommand::Arot, keyword("AROT")),
+    ))(input)?;
+    let (input, words) = many0(preceded(
+        multispace1,
+        alt((
+            map(
+                preceded(tag("RPL"), preceded(char('='), expression(syntax))),
+                |expression| ('Z', expression),
+            ),
+            pair(
+                satisfy(|c| c.is_ascii_uppercase()),
+                alt((preceded(char('='), expression(syntax)), map(number, |value| Expression::Number(value.to_string())))),
+            ),
+        )),
+    ))(input)?;
+    Ok((input, Statement::Frame(command, words)))
+}
+
+/// サイクルの呼び出しの文を返す
+/// 引数を解析できない呼び出し（"MSG"など）は解釈しない命令とする
+fn call(input: &str, syntax: Syntax) -> IResult<&str, Statement> {
+    let (input, modal) = opt(terminated(keyword("MCALL"), multispace0))(input)?;
+    let arguments = delimited(
+        char('('),
+        separated_list0(char(','), delimited(multispace0, opt(expression(syntax)), multispace0)),
+        char(')'),
+    );
+    let result = alt((
+        map(pair(identifier, arguments), |(name, arguments)| Statement::Call {
+            name: name.to_string(),
+            arguments,
+            modal: modal.is_some(),
+        }),
+        map(
+            terminated(identifier, delimited(char('('), take_until(")"), char(')'))),
+            |name| Statement::Command(name.to_string()),
+        ),
+    ))(input);
+    match (result, modal) {
+        (Ok(result), _) => Ok(result),
+        (Err(_), Some(_)) => Ok((input, Statement::CancelModalCall)),
+        (Err(e), None) => Err(e),
+    }
+}
+
+/// "="で値を指定する文を返す
+/// 1文字のアドレスはワード、CRは円弧の半径、それ以外は変数への代入とする
+fn assignment(input: &str, syntax: Syntax) -> IResult<&str, Statement> {
+    let (input, name) = terminated(identifier, delimited(multispace0, char('='), multispace0))(input)?;
+    if name.len() == 1 {
+        let code = name.chars().next().unwrap_or_default();
+        return alt((
+            map(preceded(keyword("AC"), delimited(char('('), expression(syntax), char(')'))), move |expression| {
+                Statement::Coordinate(code, false, expression)
+            }),
+            map(preceded(keyword("IC"), delimited(char('('), expression(syntax), char(')'))), move |expression| {
+                Statement::Coordinate(code, true, expression)
+            }),
+            map(
+                delimited(char('"'), take_until("\""), char('"')),
+                move |_| Statement::Command(code.to_string()),
+            ),
+            map(expression(syntax), move |expression| Statement::Word(code, expression)),
+        ))(input);
+    }
+    let (input, expression) = expression(syntax)(input)?;
+    let statement = match name {
+        "CR" => Statement::Word('R', expression),
+        _ if IGNORED_ADDRESSES.contains(&name) => Statement::Command(name.to_string()),
+        _ => Statement::Assignment(name.to_string(), expression),
+    };
+    Ok((input, statement))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tokenize(line: &str) -> Vec<Statement> {
+        Sinumerik.tokenize(&Line::new(line.to_string())).unwrap()
+    }
+
+    fn number(value: &str) -> Expression {
+        Expression::Number(value.to_string())
+    }
+
+    #[test]
+    fn sinumerik_tokenize_words() {
+        let result = tokenize("N10 G1 X=R1+5 Y-20. Z=IC(-2) CR=5 F200 ; FINISH");
+        assert_eq!(result.len(), 7);
+        assert_eq!(result[0], Statement::Word('N', number("10")));
+        assert_eq!(result[1], Statement::Word('G', number("1")));
+        assert!(matches!(&result[2], Statement::Word('X', Expression::Binary(..))));
+        assert_eq!(result[3], Statement::Word('Y', number("-20.")));
+        assert_eq!(result[4], Statement::Coordinate('Z', true, number("-2")));
+        assert_eq!(result[5], Statement::Word('R', number("5")));
+        assert_eq!(
+            Sinumerik.comments(&Line::new("G0 X1 ; FINISH".to_string())),
+            ["FINISH".to_string()]
+        );
+    }
+
+    #[test]
+    fn sinumerik_tokenize_variables() {
+        let result = tokenize("DEF REAL DEPTH=-5, FEED");
+        assert_eq!(result, [
+            Statement::Assignment("DEPTH".to_string(), number("-5")),
+            Statement::Assignment("FEED".to_string(), number("0")),
+        ]);
+        let result = tokenize("R1=SIN(30)*(R2+1)");
+        assert!(matches!(&result[0], Statement::Assignment(name, Expression::Binary(..)) if name == "R1"));
+    }
+
+    #[test]
+    fn sinumerik_tokenize_control() {
+        assert!(matches!(&tokenize("IF R1>5")[0], Statement::If(_)));
+        assert_eq!(tokenize("ELSE"), [Statement::Else]);
+        assert_eq!(tokenize("ENDIF"), [Statement::EndIf]);
+        assert_eq!(
+            tokenize("GOTOB LOOP1"),
+            [Statement::Goto(None, Jump::new("LOOP1", Direction::Backward))]
+        );
+        assert!(matches!(&tokenize("IF R1==R2 GOTOF END")[0], Statement::Goto(Some(_), jump) if jump.get_label() == "END"));
+        assert_eq!(tokenize("LOOP1: G0 X1"), [
+            Statement::Word('G', number("0")),
+            Statement::Word('X', number("1")),
+        ]);
+        assert_eq!(Sinumerik.label("N20 LOOP1: G0 X1"), Some("LOOP1".to_string()));
+        assert_eq!(Sinumerik.label("G0 X1"), None);
+    }
+
+    #[test]
+    fn sinumerik_tokenize_frame_and_call() {
+        assert_eq!(
+            tokenize("TRANS X10 Y=R1"),
+            [Statement::Frame(FrameCommand::Trans, vec![
+                ('X', number("10")),
+                ('Y', Expression::Variable("R1".to_string())),
+            ])]
+        );
+        assert_eq!(tokenize("AROT RPL=30"), [Statement::Frame(FrameCommand::Arot, vec![('Z', number("30"))])]);
+        assert_eq!(tokenize("ROT"), [Statement::Frame(FrameCommand::Rot, Vec::new())]);
+        assert_eq!(
+            tokenize("MCALL CYCLE81(10, 0, 2, -5,)"),
+            [Statement::Call {
+                name: "CYCLE81".to_string(),
+                arguments: vec![Some(number("10")), Some(number("0")), Some(number("2")), Some(number("-5")), None],
+                modal: true,
+            }]
+        );
+        assert_eq!(tokenize("MCALL"), [Statement::CancelModalCall]);
+        assert_eq!(tokenize("MSG(\"ROUGHING\") SOFT T=\"DRILL\""), [
+            Statement::Command("MSG".to_string()),
+            Statement::Command("SOFT".to_string()),
+            Statement::Command("T".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn sinumerik_canonical() {
+        let block = Block::new(&Line::new("G505G0X1.".to_string())).unwrap();
+        let result: Vec<(char, String)> = Sinumerik.canonical(&block).unwrap().get_words().iter()
+            .map(|word| (word.get_code(), word.get_value().to_string()))
+            .collect();
+        assert_eq!(result, [
+            ('G', "54.1".to_string()),
+            ('P', "1".to_string()),
+            ('G', "0".to_string()),
+            ('X', "1.".to_string()),
+        ]);
+    }
+}
@@ -0,0 +1,373 @@
+use std::collections::BTreeMap;
+
+use nom::branch::alt;
+use nom::bytes::complete::tag;
+use nom::character::complete::{char, digit1, multispace0};
+use nom::combinator::{map, opt, recognize, value};
+use nom::multi::many0;
+use nom::sequence::{delimited, pair, preceded, tuple};
+use nom::IResult;
+use serde::{Deserialize, Serialize};
+
+use crate::error::Error;
+use crate::structs::line_parser::number;
+
+/// 変数の参照を認識するパーサ
+/// 例: ファナック系の"#100"、オークマの"VC1"
+pub type VariableParser = fn(&str) -> IResult<&str, &str>;
+
+/// 式の書式
+#[derive(Debug, Clone, Copy)]
+pub struct Syntax {
+    variable: VariableParser,
+    /// 式を囲む括弧の開始と終了の文字
+    brackets: (char, char),
+}
+
+impl Syntax {
+    /// 変数のパーサと括弧の文字からインスタンス化
+    pub fn new(variable: VariableParser, brackets: (char, char)) -> Self {
+        Self { variable, brackets }
+    }
+
+    /// 変数のパーサを返す
+    pub fn variable(&self) -> VariableParser {
+        self.variable
+    }
+
+    /// 括弧の開始と終了の文字を返す
+    pub fn brackets(&self) -> (char, char) {
+        self.brackets
+    }
+}
+
+/// 変数名と値の表
+pub type Variables = BTreeMap<String, f32>;
+
+/// 二項演算子
+/// 比較と論理演算は真を1、偽を0とする
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Serialize, Deserialize)]
+pub enum Operator {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Equal,
+    NotEqual,
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+    And,
+    Or,
+}
+
+/// 関数
+/// 角度は度で扱う
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Serialize, Deserialize)]
+pub enum Function {
+    Sin,
+    Cos,
+    Tan,
+    Atan,
+    Sqrt,
+    Abs,
+    Round,
+    /// 小数点以下の切り捨て
+    Fix,
+    /// 小数点以下の切り上げ
+    Fup,
+}
+
+impl Function {
+    /// ファナック系の関数名を返す
+    pub fn name(self) -> &'static str {
+        match self {
+            Function::Sin => "SIN",
+            Function::Cos => "COS",
+            Function::Tan => "TAN",
+            Function::Atan => "ATAN",
+            Function::Sqrt => "SQRT",
+            Function::Abs => "ABS",
+            Function::Round => "ROUND",
+            Function::Fix => "FIX",
+            Function::Fup => "FUP",
+        }
+    }
+
+    /// 関数を適用した値を返す
+    fn apply(self, value: f32) -> f32 {
+        match self {
+            Function::Sin => value.to_radians().sin(),
+            Function::Cos => value.to_radians().cos(),
+            Function::Tan => value.to_radians().tan(),
+            Function::Atan => value.atan().to_degrees(),
+            Function::Sqrt => value.sqrt(),
+            Function::Abs => value.abs(),
+            Function::Round => value.round(),
+            Function::Fix => value.trunc(),
+            Function::Fup => if value < 0.0 { value.floor() } else { value.ceil() },
+        }
+    }
+}
+
+/// ワードの値や変数に代入する式
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub enum Expression {
+    /// 数値、小数点の有無を判別するため記述のまま保持する
+    Number(String),
+    Variable(String),
+    Negative(Box<Expression>),
+    Binary(Operator, Box<Expression>, Box<Expression>),
+    Call(Function, Box<Expression>),
+}
+
+impl Expression {
+    /// 式を評価する
+    /// 変数のみの式で変数が空の場合はNoneを返し、演算の中の空の変数は0とする
+    pub fn evaluate(&self, variables: &Variables) -> Result<Option<f32>, Error> {
+        match self {
+            Expression::Number(value) => Ok(Some(value.parse::<f32>()?)),
+            Expression::Variable(name) => Ok(variables.get(name).copied()),
+            Expression::Negative(expression) => {
+                Ok(Some(-expression.evaluate(variables)?.unwrap_or(0.0)))
+            },
+            Expression::Binary(operator, left, right) => {
+                let left = left.evaluate(variables)?.unwrap_or(0.0);
+                let right = right.evaluate(variables)?.unwrap_or(0.0);
+                let result = match operator {
+                    Operator::Add => left + right,
+                    Operator::Subtract => left - right,
+                    Operator::Multiply => left * right,
+                    Operator::Divide if right == 0.0 => {
+                        return Err(Error::InvalidExpression("division by zero".to_string()));
+                    },
+                    Operator::Divide => left / right,
+                    Operator::Equal => truth(left == right),
+                    Operator::NotEqual => truth(left != right),
+                    Operator::Less => truth(left < right),
+                    Operator::LessEqual => truth(left <= right),
+                    Operator::Greater => truth(left > right),
+                    Operator::GreaterEqual => truth(left >= right),
+                    Operator::And => truth(left != 0.0 && right != 0.0),
+                    Operator::Or => truth(left != 0.0 || right != 0.0),
+                };
+                Ok(Some(result))
+            },
+            Expression::Call(function, expression) => {
+                let result = function.apply(expression.evaluate(variables)?.unwrap_or(0.0));
+                if result.is_nan() {
+                    return Err(Error::InvalidExpression(format!("{:?} is undefined", function)));
+                }
+                Ok(Some(result))
+            },
+        }
+    }
+
+    /// 条件式として評価し、真偽値を返す
+    pub fn is_true(&self, variables: &Variables) -> Result<bool, Error> {
+        Ok(self.evaluate(variables)?.is_some_and(|value| value != 0.0))
+    }
+
+    /// ワードの値の文字列を返す
+    /// 数値はそのまま、式は評価した値を小数点付きで返す
+    pub fn word_value(&self, variables: &Variables) -> Result<Option<String>, Error> {
+        if let Expression::Number(value) = self {
+            return Ok(Some(value.clone()));
+        }
+        Ok(self.evaluate(variables)?.map(decimal))
+    }
+}
+
+/// 数値を小数点付きの文字列で返す
+/// 例: 100.0 -> "100.", 12.5 -> "12.5"
+pub fn decimal(value: f32) -> String {
+    let value = value.to_string();
+    if value.contains('.') { value } else { value + "." }
+}
+
+/// 比較と論理演算を含む条件式を返す
+/// 比較演算子は"=="、"<>"などの記号と"EQ"、"NE"などの略語に対応する
+pub fn condition(syntax: Syntax) -> impl Fn(&str) -> IResult<&str, Expression> {
+    move |input| {
+        let (input, first) = comparison(syntax)(input)?;
+        let (input, rest) = many0(pair(
+            delimited(multispace0, alt((
+                value(Operator::And, tag("AND")),
+                value(Operator::Or, tag("OR")),
+            )), multispace0),
+            comparison(syntax),
+        ))(input)?;
+        Ok((input, fold(first, rest)))
+    }
+}
+
+/// 比較の式を返す
+fn comparison(syntax: Syntax) -> impl Fn(&str) -> IResult<&str, Expression> {
+    move |input| {
+        let (input, first) = expression(syntax)(input)?;
+        let (input, rest) = opt(pair(
+            delimited(multispace0, alt((
+                value(Operator::Equal, alt((tag("=="), tag("EQ")))),
+                value(Operator::NotEqual, alt((tag("<>"), tag("NE")))),
+                value(Operator::LessEqual, alt((tag("<="), tag("LE")))),
+                value(Operator::GreaterEqual, alt((tag(">="), tag("GE")))),
+                value(Operator::Less, alt((tag("<"), tag("LT")))),
+                value(Operator::Greater, alt((tag(">"), tag("GT")))),
+            )), multispace0),
+            expression(syntax),
+        ))(input)?;
+        Ok((input, fold(first, rest.into_iter().collect())))
+    }
+}
+
+/// 加減算の式を返す
+pub fn expression(syntax: Syntax) -> impl Fn(&str) -> IResult<&str, Expression> {
+    move |input| {
+        let (input, first) = term(syntax)(input)?;
+        let (input, rest) = many0(pair(
+            delimited(multispace0, alt((
+                value(Operator::Add, char('+')),
+                value(Operator::Subtract, char('-')),
+            )), multispace0),
+            term(syntax),
+        ))(input)?;
+        Ok((input, fold(first, rest)))
+    }
+}
+
+/// 乗除算の式を返す
+fn term(syntax: Syntax) -> impl Fn(&str) -> IResult<&str, Expression> {
+    move |input| {
+        let (input, first) = factor(syntax)(input)?;
+        let (input, rest) = many0(pair(
+            delimited(multispace0, alt((
+                value(Operator::Multiply, char('*')),
+                value(Operator::Divide, char('/')),
+            )), multispace0),
+            factor(syntax),
+        ))(input)?;
+        Ok((input, fold(first, rest)))
+    }
+}
+
+/// 数値、変数、括弧で囲まれた式、関数と、それらに符号を付けた値を返す
+pub fn factor(syntax: Syntax) -> impl Fn(&str) -> IResult<&str, Expression> {
+    move |input| {
+        alt((
+            map(number, |value| Expression::Number(value.to_string())),
+            map(
+                pair(function, bracket(syntax)),
+                |(function, expression)| Expression::Call(function, Box::new(expression)),
+            ),
+            map(syntax.variable(), |name| Expression::Variable(name.to_string())),
+            bracket(syntax),
+            map(
+                preceded(char('-'), factor(syntax)),
+                |expression| Expression::Negative(Box::new(expression)),
+            ),
+            preceded(char('+'), factor(syntax)),
+        ))(input)
+    }
+}
+
+/// 括弧で囲まれた式を返す
+pub fn bracket(syntax: Syntax) -> impl Fn(&str) -> IResult<&str, Expression> {
+    move |input| {
+        let (open, close) = syntax.brackets;
+        delimited(
+            tuple((char(open), multispace0)),
+            condition(syntax),
+            tuple((multispace0, char(close))),
+        )(input)
+    }
+}
+
+/// 関数名を返す
+fn function(input: &str) -> IResult<&str, Function> {
+    alt((
+        value(Function::Sin, tag("SIN")),
+        value(Function::Cos, tag("COS")),
+        value(Function::Tan, tag("TAN")),
+        value(Function::Atan, tag("ATAN")),
+        value(Function::Sqrt, tag("SQRT")),
+        value(Function::Abs, tag("ABS")),
+        value(Function::Round, tag("ROUND")),
+        value(Function::Fix, tag("FIX")),
+        value(Function::Fup, tag("FUP")),
+        value(Function::Fix, tag("TRUNC")),
+    ))(input)
+}
+
+/// 左結合で二項演算の式にまとめる
+fn fold(first: Expression, rest: Vec<(Operator, Expression)>) -> Expression {
+    rest.into_iter().fold(first, |left, (operator, right)| {
+        Expression::Binary(operator, Box::new(left), Box::new(right))
+    })
+}
+
+/// 真偽値を1と0で返す
+fn truth(value: bool) -> f32 {
+    if value { 1.0 } else { 0.0 }
+}
+
+/// ファナック系の変数（"#"と番号）を認識して返す
+pub fn sharp_variable(input: &str) -> IResult<&str, &str> {
+    recognize(pair(char('#'), digit1))(input)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SYNTAX: Syntax = Syntax { variable: sharp_variable, brackets: ('[', ']') };
+
+    fn evaluate(source: &str, variables: &Variables) -> Result<Option<f32>, Error> {
+        let (rest, expression) = condition(SYNTAX)(source).unwrap();
+        assert_eq!(rest, "");
+        expression.evaluate(variables)
+    }
+
+    #[test]
+    fn expression_evaluate_normal() {
+        let variables = Variables::from([("#1".to_string(), 10.0), ("#2".to_string(), 4.0)]);
+        let datas = [
+            ("1.5", Some(1.5)),
+            ("#1", Some(10.0)),
+            ("#3", None),
+            ("#1+#2*2", Some(18.0)),
+            ("[#1+#2]*2", Some(28.0)),
+            ("#1 - #2 - 1", Some(5.0)),
+            ("-#2/2", Some(-2.0)),
+            ("#3+1", Some(1.0)),
+            ("ABS[-#1]", Some(10.0)),
+            ("SQRT[#2]", Some(2.0)),
+            ("ROUND[SIN[90.0]]", Some(1.0)),
+            ("FIX[-2.5]+FUP[1.2]", Some(0.0)),
+            ("#1 GT #2", Some(1.0)),
+            ("#1<=#2 OR #2==4", Some(1.0)),
+            ("[#1 EQ 10] AND [#3 NE 0]", Some(0.0)),
+        ];
+        datas.iter().for_each(|&(source, expected)| {
+            assert_eq!(evaluate(source, &variables), Ok(expected), "{}", source);
+        })
+    }
+
+    #[test]
+    fn expression_evaluate_abnormality() {
+        let variables = Variables::new();
+        assert!(matches!(evaluate("1/#1", &variables), Err(Error::InvalidExpression(_))));
+        assert!(matches!(evaluate("SQRT[-1]", &variables), Err(Error::InvalidExpression(_))));
+    }
+
+    #[test]
+    fn expression_word_value() {
+        let variables = Variables::from([("#1".to_string(), 100.0)]);
+        let datas = [("100", Some("100")), ("#1", Some("100.")), ("[#1/8]", Some("12.5")), ("#2", None)];
+        datas.iter().for_each(|&(source, expected)| {
+            let (_, expression) = factor(SYNTAX)(source).unwrap();
+            let result = expression.word_value(&variables).unwrap();
+            assert_eq!(result.as_deref(), expected);
+        })
+    }
+}
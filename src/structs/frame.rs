@@ -0,0 +1,134 @@
+use serde::{Deserialize, Serialize};
+
+use crate::structs::state::State;
+
+/// プログラムの座標系からワーク座標系への座標変換
+/// XY平面の回転の後に平行移動する
+#[derive(Debug, Default, PartialEq, Clone, Copy, Serialize, Deserialize)]
+pub struct Frame {
+    translation: [f32; 3],
+    /// XY平面の回転角度（度、反時計回りを正）
+    rotation: f32,
+}
+
+impl Frame {
+    /// 平行移動と回転角度からインスタンス化
+    pub fn new(translation: [f32; 3], rotation: f32) -> Self {
+        Self { translation, rotation }
+    }
+
+    /// 平行移動量を返す
+    pub fn get_translation(&self) -> [f32; 3] {
+        self.translation
+    }
+
+    /// 回転角度を返す
+    pub fn get_rotation(&self) -> f32 {
+        self.rotation
+    }
+
+    /// 座標変換がないかの真偽値を返す
+    pub fn is_identity(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// 現在の座標系で平行移動を追加する
+    pub fn translate(&mut self, translation: [f32; 3]) {
+        let (x, y) = rotate((translation[0], translation[1]), self.rotation);
+        self.translation[0] += x;
+        self.translation[1] += y;
+        self.translation[2] += translation[2];
+    }
+
+    /// 現在の座標系で回転を追加する
+    pub fn rotate(&mut self, rotation: f32) {
+        self.rotation += rotation;
+    }
+
+    /// プログラムの座標をワーク座標系に変換する
+    pub fn apply(&self, [x, y, z]: [f32; 3]) -> [f32; 3] {
+        let (x, y) = rotate((x, y), self.rotation);
+        [x + self.translation[0], y + self.translation[1], z + self.translation[2]]
+    }
+
+    /// ワーク座標系の座標をプログラムの座標に変換する
+    pub fn invert(&self, [x, y, z]: [f32; 3]) -> [f32; 3] {
+        let (x, y) = (x - self.translation[0], y - self.translation[1]);
+        let (x, y) = rotate((x, y), -self.rotation);
+        [x, y, z - self.translation[2]]
+    }
+
+    /// プログラムの座標系の状態をワーク座標系の状態に変換する
+    pub fn apply_state(&self, state: &State) -> State {
+        self.transform_state(state, |position| self.apply(position), self.rotation)
+    }
+
+    /// ワーク座標系の状態をプログラムの座標系の状態に変換する
+    pub fn invert_state(&self, state: &State) -> State {
+        self.transform_state(state, |position| self.invert(position), -self.rotation)
+    }
+
+    /// 位置、経由した位置と円弧の中心までの増分を変換した状態を返す
+    fn transform_state<F>(&self, state: &State, f: F, rotation: f32) -> State
+    where
+        F: Fn([f32; 3]) -> [f32; 3],
+    {
+        if self.is_identity() {
+            return *state;
+        }
+        let mut result = *state;
+        result.position_set(f([state.get_x(), state.get_y(), state.get_z()]));
+        result.via_map(&f);
+        let (i, j) = rotate((state.get_i(), state.get_j()), rotation);
+        result.center_offset_set(i, j);
+        result
+    }
+}
+
+/// 原点を中心に回転した座標を返す
+fn rotate((x, y): (f32, f32), degrees: f32) -> (f32, f32) {
+    if degrees == 0.0 {
+        return (x, y);
+    }
+    let (sin, cos) = degrees.to_radians().sin_cos();
+    (x * cos - y * sin, x * sin + y * cos)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round([x, y, z]: [f32; 3]) -> [f32; 3] {
+        [x, y, z].map(|value| (value * 1000.0).round() / 1000.0)
+    }
+
+    #[test]
+    fn frame_apply() {
+        let mut frame = Frame::new([10.0, 20.0, 0.0], 0.0);
+        frame.rotate(90.0);
+        frame.translate([5.0, 0.0, -1.0]);
+        assert_eq!(frame.get_translation(), [10.0, 25.0, -1.0]);
+        let datas = [
+            ([0.0, 0.0, 0.0], [10.0, 25.0, -1.0]),
+            ([1.0, 0.0, 5.0], [10.0, 26.0, 4.0]),
+            ([0.0, 2.0, 0.0], [8.0, 25.0, -1.0]),
+        ];
+        datas.iter().for_each(|&(position, expected)| {
+            assert_eq!(round(frame.apply(position)), expected);
+            assert_eq!(round(frame.invert(expected)), position);
+        })
+    }
+
+    #[test]
+    fn frame_apply_state() {
+        let frame = Frame::new([100.0, 0.0, 0.0], 90.0);
+        let mut state = State::default();
+        state.position_set([10.0, 0.0, -5.0]);
+        state.center_offset_set(-10.0, 0.0);
+        let result = frame.apply_state(&state);
+        assert_eq!(round([result.get_x(), result.get_y(), result.get_z()]), [100.0, 10.0, -5.0]);
+        assert_eq!(round([result.get_i(), result.get_j(), 0.0]), [0.0, -10.0, 0.0]);
+        assert_eq!(frame.invert_state(&result).get_x().round(), 10.0);
+        assert_eq!(Frame::default().apply_state(&state), state);
+    }
+}
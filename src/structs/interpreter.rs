@@ -0,0 +1,653 @@
+use crate::error::Error;
+use crate::structs::block::{Block, Word};
+use crate::structs::dialect::{block_label, Direction, FrameCommand, Jump, Statement};
+use crate::structs::expression::{decimal, Expression, Variables};
+use crate::structs::frame::Frame;
+use crate::structs::line_parser::{g_code, Line};
+use crate::structs::machine_profile::MachineProfile;
+use crate::structs::state::State;
+
+/// サブプログラムの戻り値を代入する変数
+const RETURN_VALUE: &str = "#<_VALUE>";
+
+/// 実行する行の移動
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Transfer {
+    /// ラベルの行へ移動する
+    Jump(Jump),
+    /// 次の行を戻り先として記録し、ラベルの行へ移動する
+    Call(Jump),
+    /// 記録した戻り先の行へ戻る
+    Return,
+}
+
+/// 条件分岐、繰り返し、サブプログラムの範囲の種類
+#[derive(Debug, PartialEq, Eq, Clone)]
+enum ScopeKind {
+    Condition,
+    While(String),
+    Do(String),
+    /// 残りの繰り返し回数
+    Repeat(String, u32),
+    /// 呼び出し中（真）または読み飛ばす定義（偽）のサブプログラム
+    Sub(bool),
+}
+
+/// 入れ子の範囲
+#[derive(Debug, PartialEq, Eq, Clone)]
+struct Scope {
+    kind: ScopeKind,
+    /// 外側の範囲を実行するか
+    outer: bool,
+    /// 現在の範囲を実行するか
+    current: bool,
+    /// 条件分岐のいずれかの範囲を実行したか
+    taken: bool,
+}
+
+impl Scope {
+    /// インスタンス化
+    fn new(kind: ScopeKind, outer: bool, current: bool) -> Self {
+        Self { kind, outer, current, taken: current }
+    }
+
+    /// 繰り返しの名前を返す
+    fn loop_name(&self) -> Option<&str> {
+        match &self.kind {
+            ScopeKind::While(name) | ScopeKind::Do(name) | ScopeKind::Repeat(name, _) => Some(name),
+            _ => None,
+        }
+    }
+}
+
+/// 機械の設定に従って行を解釈し、状態を更新する
+#[derive(Debug, Default, PartialEq, Clone)]
+pub struct Interpreter {
+    profile: MachineProfile,
+    /// ワーク座標系の状態
+    state: State,
+    /// 座標変換前のプログラムの座標系の状態
+    programmed: State,
+    frame: Frame,
+    variables: Variables,
+    scopes: Vec<Scope>,
+    /// 呼び出し中のサブプログラムの名前
+    calling: Option<String>,
+    /// サブプログラムの呼び出しごとに退避したローカル変数
+    locals: Vec<Variables>,
+    /// モーダルな穴あけサイクルの復帰点
+    modal_cycle: Option<f32>,
+    /// プログラムの座標系の円弧の中心
+    center: Option<(f32, f32)>,
+    transfer: Option<Transfer>,
+}
+
+impl Interpreter {
+    /// 機械の設定からインスタンス化
+    pub fn new(profile: MachineProfile) -> Self {
+        Self::with_state(profile, State::default())
+    }
+
+    /// 機械の設定と開始時の状態からインスタンス化
+    /// 制御装置で小数点のない数値の解釈が決まっている場合は機械の設定より優先する
+    pub fn with_state(mut profile: MachineProfile, state: State) -> Self {
+        if let Some(decimal_input) = profile.get_dialect().dialect().decimal_input() {
+            profile.decimal_input_set(decimal_input);
+        }
+        Self { profile, state, programmed: state, ..Default::default() }
+    }
+
+    /// 機械の設定を返す
+    pub fn get_profile(&self) -> &MachineProfile {
+        &self.profile
+    }
+
+    /// 現在の状態を返す
+    pub fn get_state(&self) -> State {
+        self.state
+    }
+
+    /// 変数の値を返す
+    pub fn get_variables(&self) -> &Variables {
+        &self.variables
+    }
+
+    /// 座標変換を返す
+    pub fn get_frame(&self) -> Frame {
+        self.frame
+    }
+
+    /// 直前の行で指令された行の移動を取り出す
+    pub fn take_transfer(&mut self) -> Option<Transfer> {
+        self.transfer.take()
+    }
+
+    /// 行を解析し、式を評価したブロックと、軸の移動がある場合は更新後の状態を返す
+    /// 変数への代入は記述の順に実行し、空の変数を値とするワードは指令しなかったものとする
+    /// 条件が偽の範囲と、呼び出されていないサブプログラムの行は解釈しない
+    pub fn execute(&mut self, line: &Line) -> Result<(Block, Option<State>), Error> {
+        let dialect = self.profile.get_dialect().dialect();
+        let mut words = Vec::new();
+        let mut coordinates = Vec::new();
+        for statement in dialect.tokenize(line)? {
+            if !self.is_active() && !is_structure(&statement) {
+                continue;
+            }
+            match statement {
+                Statement::Assignment(name, expression) => match expression.evaluate(&self.variables)? {
+                    Some(value) => {
+                        self.variables.insert(name, value);
+                    },
+                    None => {
+                        self.variables.remove(&name);
+                    },
+                },
+                Statement::Word(code, expression) => {
+                    if let Some(value) = expression.word_value(&self.variables)? {
+                        words.push(Word::new(code, &value));
+                    }
+                },
+                Statement::Coordinate(code, incremental, expression) => {
+                    if let Some(value) = expression.evaluate(&self.variables)? {
+                        coordinates.push((code, incremental, value));
+                    }
+                },
+                Statement::Frame(command, words) => self.frame_update(command, &words)?,
+                Statement::Call { name, arguments, modal } => {
+                    let retract_plane = dialect.retract_plane(&name, &arguments, &self.variables)?;
+                    match (retract_plane, modal) {
+                        (Some(retract_plane), true) => self.modal_cycle = Some(retract_plane),
+                        (Some(retract_plane), false) => coordinates.push(('Z', false, retract_plane)),
+                        (None, _) => {},
+                    }
+                },
+                Statement::CancelModalCall => self.modal_cycle = None,
+                Statement::SetCenter(axes) => self.center_update(&axes)?,
+                Statement::UseCenter => {
+                    let (x, y) = self.center.ok_or(Error::InvalidArc)?;
+                    coordinates.extend([('I', false, x), ('J', false, y)]);
+                },
+                Statement::If(condition) => {
+                    let outer = self.is_active();
+                    let current = outer && condition.is_true(&self.variables)?;
+                    self.scopes.push(Scope::new(ScopeKind::Condition, outer, current));
+                },
+                Statement::ElseIf(condition) => {
+                    let scope = self.scopes.last_mut()
+                        .ok_or_else(|| Error::InvalidParser("ELSEIF".to_string()))?;
+                    scope.current = scope.outer && !scope.taken && condition.is_true(&self.variables)?;
+                    scope.taken |= scope.current;
+                },
+                Statement::Else => {
+                    let scope = self.scopes.last_mut()
+                        .ok_or_else(|| Error::InvalidParser("ELSE".to_string()))?;
+                    scope.current = scope.outer && !scope.taken;
+                    scope.taken = true;
+                },
+                Statement::EndIf => {
+                    self.scopes.pop().ok_or_else(|| Error::InvalidParser("ENDIF".to_string()))?;
+                },
+                Statement::While(name, condition) => self.while_update(name, &condition)?,
+                Statement::Do(name) => {
+                    let outer = self.is_active();
+                    self.scopes.push(Scope::new(ScopeKind::Do(name), outer, outer));
+                },
+                Statement::Repeat(name, count) => self.repeat_update(name, &count)?,
+                Statement::EndLoop(name) => {
+                    let scope = self.scopes.last()
+                        .filter(|scope| scope.loop_name() == Some(&name))
+                        .ok_or_else(|| Error::InvalidParser(name.clone()))?;
+                    let keyword = match scope.kind {
+                        ScopeKind::Repeat(..) => "REPEAT",
+                        _ => "WHILE",
+                    };
+                    if scope.current {
+                        self.transfer = Some(Transfer::Jump(Jump::new(&block_label(&name, keyword), Direction::Backward)));
+                    } else {
+                        self.scopes.pop();
+                    }
+                },
+                Statement::Break(name) => {
+                    let index = self.loop_index(&name)?;
+                    for scope in &mut self.scopes[index..] {
+                        scope.current = false;
+                    }
+                    for scope in &mut self.scopes[index + 1..] {
+                        scope.outer = false;
+                    }
+                },
+                Statement::Continue(name) => {
+                    let index = self.loop_index(&name)?;
+                    self.scopes.truncate(index + 1);
+                    let jump = match self.scopes[index].kind {
+                        ScopeKind::Do(_) => Jump::new(&block_label(&name, "WHILE"), Direction::Forward),
+                        ScopeKind::Repeat(..) => Jump::new(&block_label(&name, "REPEAT"), Direction::Backward),
+                        _ => Jump::new(&block_label(&name, "WHILE"), Direction::Backward),
+                    };
+                    self.transfer = Some(Transfer::Jump(jump));
+                    break;
+                },
+                Statement::Sub(name) => {
+                    let calling = self.calling.take() == Some(name);
+                    let outer = self.is_active();
+                    self.scopes.push(Scope::new(ScopeKind::Sub(calling), outer, calling));
+                },
+                Statement::SubCall(name, arguments) => {
+                    let arguments = arguments.iter()
+                        .map(|argument| argument.evaluate(&self.variables))
+                        .collect::<Result<Vec<_>, Error>>()?;
+                    let (locals, globals) = std::mem::take(&mut self.variables).into_iter()
+                        .partition(|(name, _)| is_local(name));
+                    self.variables = globals;
+                    self.locals.push(locals);
+                    arguments.into_iter().enumerate().for_each(|(i, argument)| {
+                        if let Some(value) = argument {
+                            self.variables.insert(format!("#{}", i + 1), value);
+                        }
+                    });
+                    self.transfer = Some(Transfer::Call(Jump::new(&block_label(&name, "SUB"), Direction::Both)));
+                    self.calling = Some(name);
+                    break;
+                },
+                Statement::Return(_, value) => {
+                    self.return_update(value.as_ref())?;
+                    break;
+                },
+                Statement::EndSub(_, value) => {
+                    if self.scopes.last().is_some_and(|scope| scope.kind == ScopeKind::Sub(false)) {
+                        self.scopes.pop();
+                    } else {
+                        self.return_update(value.as_ref())?;
+                        break;
+                    }
+                },
+                Statement::Goto(condition, jump) => {
+                    let jumps = match condition {
+                        Some(condition) => condition.is_true(&self.variables)?,
+                        None => true,
+                    };
+                    if jumps {
+                        self.transfer = Some(Transfer::Jump(jump));
+                        break;
+                    }
+                },
+                Statement::Command(_) => {},
+            }
+        }
+        words.extend(self.coordinate_words(&words, &coordinates));
+        let block = Block::from_words(words, dialect.comments(line));
+
+        let canonical = self.profile.canonical(&dialect.canonical(&block)?)?;
+        let result = self.programmed.block_update(&canonical)?;
+        if let Some((number, axes)) = self.programmed.take_reference() {
+            let position = self.profile.reference_position(number, &self.programmed);
+            self.programmed.reference_return(self.frame.invert(position), axes);
+        }
+        if let (Some(_), Some(retract_plane)) = (result, self.modal_cycle) {
+            // 位置決めの後に穴あけサイクルを実行し、復帰点に戻る
+            let (x, y) = (self.programmed.get_x(), self.programmed.get_y());
+            self.programmed.position_set([x, y, retract_plane]);
+        }
+        self.state = self.frame.apply_state(&self.programmed);
+        Ok((block, result.map(|_| self.state)))
+    }
+
+    /// 条件が真の範囲を実行中であるかの真偽値を返す
+    fn is_active(&self) -> bool {
+        self.scopes.last().is_none_or(|scope| scope.current)
+    }
+
+    /// 条件が真の間繰り返す範囲を開始する
+    /// Doで始まる範囲の終了の場合は、条件が真であれば先頭に戻る
+    fn while_update(&mut self, name: String, condition: &Expression) -> Result<(), Error> {
+        match self.scopes.last_mut() {
+            Some(scope) if scope.kind == ScopeKind::Do(name.clone()) => {
+                if scope.current && condition.is_true(&self.variables)? {
+                    self.transfer = Some(Transfer::Jump(Jump::new(&block_label(&name, "DO"), Direction::Backward)));
+                } else {
+                    self.scopes.pop();
+                }
+            },
+            Some(scope) if scope.kind == ScopeKind::While(name.clone()) => {
+                scope.current = scope.outer && condition.is_true(&self.variables)?;
+            },
+            _ => {
+                let outer = self.is_active();
+                let current = outer && condition.is_true(&self.variables)?;
+                self.scopes.push(Scope::new(ScopeKind::While(name), outer, current));
+            },
+        }
+        Ok(())
+    }
+
+    /// 指定した回数繰り返す範囲を開始する
+    /// 先頭に戻った場合は残りの回数を減らす
+    fn repeat_update(&mut self, name: String, count: &Expression) -> Result<(), Error> {
+        match self.scopes.last_mut() {
+            Some(Scope { kind: ScopeKind::Repeat(current_name, remaining), outer, current, .. }) if *current_name == name => {
+                *remaining = remaining.saturating_sub(1);
+                *current = *outer && *remaining > 0;
+            },
+            _ => {
+                let outer = self.is_active();
+                let count = match outer {
+                    true => count.evaluate(&self.variables)?.unwrap_or(0.0).max(0.0) as u32,
+                    false => 0,
+                };
+                self.scopes.push(Scope::new(ScopeKind::Repeat(name, count), outer, count > 0));
+            },
+        }
+        Ok(())
+    }
+
+    /// 名前を指定した繰り返しの範囲の位置を返す
+    fn loop_index(&self, name: &str) -> Result<usize, Error> {
+        self.scopes.iter().rposition(|scope| scope.loop_name() == Some(name))
+            .ok_or_else(|| Error::InvalidParser(name.to_string()))
+    }
+
+    /// サブプログラムから復帰し、ローカル変数を呼び出し前の値に戻す
+    fn return_update(&mut self, value: Option<&Expression>) -> Result<(), Error> {
+        let value = value.map(|value| value.evaluate(&self.variables)).transpose()?.flatten();
+        let index = self.scopes.iter().rposition(|scope| scope.kind == ScopeKind::Sub(true))
+            .ok_or_else(|| Error::InvalidParser("RETURN".to_string()))?;
+        self.scopes.truncate(index);
+        self.variables.retain(|name, _| !is_local(name));
+        self.variables.extend(self.locals.pop().unwrap_or_default());
+        if let Some(value) = value {
+            self.variables.insert(RETURN_VALUE.to_string(), value);
+        }
+        self.transfer = Some(Transfer::Return);
+        Ok(())
+    }
+
+    /// 座標変換を更新し、現在の位置をプログラムの座標系に変換する
+    /// 回転はXY平面のみとする
+    fn frame_update(&mut self, command: FrameCommand, words: &[(char, Expression)]) -> Result<(), Error> {
+        let mut values = [0.0; 3];
+        for (code, expression) in words {
+            let axis = match (command, code) {
+                (FrameCommand::Trans | FrameCommand::Atrans, 'X') => 0,
+                (FrameCommand::Trans | FrameCommand::Atrans, 'Y') => 1,
+                (_, 'Z') => 2,
+                _ => return Err(Error::InvalidCode(*code)),
+            };
+            values[axis] = expression.evaluate(&self.variables)?.unwrap_or(0.0);
+        }
+        match command {
+            FrameCommand::Trans => self.frame = Frame::new(values, 0.0),
+            FrameCommand::Atrans => self.frame.translate(values),
+            FrameCommand::Rot => self.frame = Frame::new([0.0; 3], values[2]),
+            FrameCommand::Arot => self.frame.rotate(values[2]),
+        }
+        self.programmed = self.frame.invert_state(&self.state);
+        Ok(())
+    }
+
+    /// 円弧の中心を更新する
+    /// 増分値は現在の位置からの増分とする
+    fn center_update(&mut self, axes: &[(char, bool, Expression)]) -> Result<(), Error> {
+        let mut center = [self.programmed.get_x(), self.programmed.get_y()];
+        for (code, incremental, expression) in axes {
+            let axis = match code {
+                'X' => 0,
+                'Y' => 1,
+                _ => return Err(Error::InvalidCode(*code)),
+            };
+            let value = expression.evaluate(&self.variables)?.unwrap_or(0.0);
+            center[axis] = if *incremental { center[axis] + value } else { value };
+        }
+        self.center = Some((center[0], center[1]));
+        Ok(())
+    }
+
+    /// 絶対値または増分値を個別に指定した座標を、ブロックの位置決めモードのワードに変換する
+    /// 円弧の中心の絶対値は始点からの増分に変換する
+    fn coordinate_words(&self, words: &[Word], coordinates: &[(char, bool, f32)]) -> Vec<Word> {
+        let incremental = words.iter().rev()
+            .filter(|word| word.get_code() == 'G')
+            .find_map(|word| match g_code(word.get_value()).as_str() {
+                "G90" => Some(false),
+                "G91" => Some(true),
+                _ => None,
+            })
+            .unwrap_or(self.programmed.get_incremental());
+        let state = self.programmed;
+        coordinates.iter().map(|&(code, explicit, value)| {
+            let current = match code {
+                'X' | 'I' => state.get_x(),
+                'Y' | 'J' => state.get_y(),
+                'Z' => state.get_z(),
+                _ => 0.0,
+            };
+            let value = match code {
+                'I' | 'J' if !explicit => value - current,
+                'X' | 'Y' | 'Z' if explicit && !incremental => current + value,
+                'X' | 'Y' | 'Z' if !explicit && incremental => value - current,
+                _ => value,
+            };
+            Word::new(code, &decimal(value))
+        }).collect()
+    }
+}
+
+/// 実行しない範囲でも範囲の入れ子を追跡するための文であるかの真偽値を返す
+fn is_structure(statement: &Statement) -> bool {
+    matches!(
+        statement,
+        Statement::If(_) | Statement::ElseIf(_) | Statement::Else | Statement::EndIf
+            | Statement::While(..) | Statement::Do(_) | Statement::Repeat(..) | Statement::EndLoop(_)
+            | Statement::Sub(_) | Statement::EndSub(..)
+    )
+}
+
+/// サブプログラムの呼び出しごとに退避するローカル変数であるかの真偽値を返す
+/// "#"で始まる変数のうち、"#1"から"#30"と、"_"で始まらない名前付きの変数をローカル変数とする
+/// Sinumerikの"R1"などのパラメータは呼び出しの前後で共通とする
+fn is_local(name: &str) -> bool {
+    let Some(name) = name.strip_prefix('#') else {
+        return false;
+    };
+    match name.strip_prefix('<') {
+        Some(name) => !name.starts_with('_'),
+        None => name.parse::<u32>().is_ok_and(|number| (1..=30).contains(&number)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::structs::dialect::DialectKind;
+    use crate::structs::machine_profile::DecimalInput;
+    use crate::structs::work_offset::WorkOffset;
+
+    #[test]
+    fn interpreter_execute() {
+        let mut interpreter = Interpreter::new(MachineProfile::default());
+        let datas = [
+            ("G90G00X-1610.0Y137.0", Some((-1610.0, 137.0, 0.0))),
+            ("M01", None),
+            ("G01Z5000F750.", Some((-1610.0, 137.0, 5.0))),
+            ("X-1515.", Some((-1515.0, 137.0, 5.0))),
+        ];
+        datas.iter().for_each(|&(line, expected)| {
+            let (_, result) = interpreter.execute(&Line::new(line.to_string())).unwrap();
+            let result = result.map(|state| (state.get_x(), state.get_y(), state.get_z()));
+            assert_eq!(result, expected);
+        })
+    }
+
+    #[test]
+    fn interpreter_execute_calculator() {
+        let mut profile = MachineProfile::default();
+        profile.decimal_input_set(DecimalInput::Calculator);
+        let mut interpreter = Interpreter::with_state(profile, State::default());
+        let (block, result) = interpreter.execute(&Line::new("G01X100(A)".to_string())).unwrap();
+        assert_eq!(block.get_comments(), ["A".to_string()]);
+        assert_eq!(result.unwrap().get_x(), 100.0);
+        assert_eq!(interpreter.get_state().get_x(), 100.0);
+    }
+
+    #[test]
+    fn interpreter_execute_variables() {
+        let mut interpreter = Interpreter::new(MachineProfile::default());
+        let datas = [
+            ("#1=100 #2=[#1+20]/2", (0.0, 0.0)),
+            ("G00X#1Y-#2", (100.0, -60.0)),
+            ("X#3Y[#2*2]", (100.0, 120.0)),
+            ("#1=#3", (100.0, 120.0)),
+            ("X[#1+1]", (1.0, 120.0)),
+        ];
+        datas.iter().for_each(|&(line, expected)| {
+            interpreter.execute(&Line::new(line.to_string())).unwrap();
+            let state = interpreter.get_state();
+            assert_eq!((state.get_x(), state.get_y()), expected, "{}", line);
+        });
+        assert_eq!(interpreter.get_variables().get("#1"), None);
+        assert_eq!(interpreter.get_variables().get("#2"), Some(&60.0));
+    }
+
+    #[test]
+    fn interpreter_execute_okuma() {
+        let mut profile = MachineProfile::default();
+        profile.dialect_set(DialectKind::Okuma);
+        let mut interpreter = Interpreter::new(profile);
+        let datas = [
+            ("VC1=50", (0.0, WorkOffset::G54, 0)),
+            ("G15H3", (0.0, WorkOffset::Extended(3), 0)),
+            ("G56H1Z=VC1+10", (60.0, WorkOffset::Extended(3), 1)),
+        ];
+        datas.iter().for_each(|&(line, expected)| {
+            interpreter.execute(&Line::new(line.to_string())).unwrap();
+            let state = interpreter.get_state();
+            assert_eq!((state.get_z(), state.get_work_offset(), state.get_tool_length_offset()), expected);
+        });
+    }
+
+    fn sinumerik() -> Interpreter {
+        let mut profile = MachineProfile::default();
+        profile.dialect_set(DialectKind::Sinumerik);
+        Interpreter::new(profile)
+    }
+
+    fn position(interpreter: &Interpreter) -> (f32, f32, f32) {
+        let state = interpreter.get_state();
+        let round = |value: f32| (value * 1000.0).round() / 1000.0;
+        (round(state.get_x()), round(state.get_y()), round(state.get_z()))
+    }
+
+    #[test]
+    fn interpreter_execute_sinumerik() {
+        let mut interpreter = sinumerik();
+        let datas = [
+            ("R1=10 DEF REAL DEPTH=-5", (0.0, 0.0, 0.0)),
+            ("G0 X=R1 Y20 Z=DEPTH+10", (10.0, 20.0, 5.0)),
+            ("G91 X=AC(50) Y5", (50.0, 25.0, 5.0)),
+            ("G90 Z=IC(-2)", (50.0, 25.0, 3.0)),
+            ("CYCLE81(30, 0, 2, -5,)", (50.0, 25.0, 30.0)),
+            ("G2 X70 Y25 I=AC(60) J=AC(25)", (70.0, 25.0, 30.0)),
+        ];
+        datas.iter().for_each(|&(line, expected)| {
+            interpreter.execute(&Line::new(line.to_string())).unwrap();
+            assert_eq!(position(&interpreter), expected, "{}", line);
+        });
+        assert_eq!((interpreter.get_state().get_i(), interpreter.get_state().get_j()), (10.0, 0.0));
+    }
+
+    #[test]
+    fn interpreter_execute_frame() {
+        let mut interpreter = sinumerik();
+        let datas = [
+            ("G0 X10 Y0", (10.0, 0.0, 0.0)),
+            ("TRANS X100 Y50", (10.0, 0.0, 0.0)),
+            ("X10 Y0", (110.0, 50.0, 0.0)),
+            ("AROT RPL=90", (110.0, 50.0, 0.0)),
+            ("X10 Y0", (100.0, 60.0, 0.0)),
+            ("ATRANS X5", (100.0, 60.0, 0.0)),
+            ("X0 Y0", (100.0, 55.0, 0.0)),
+            ("TRANS", (100.0, 55.0, 0.0)),
+            ("X0", (0.0, 55.0, 0.0)),
+        ];
+        datas.iter().for_each(|&(line, expected)| {
+            interpreter.execute(&Line::new(line.to_string())).unwrap();
+            assert_eq!(position(&interpreter), expected, "{}", line);
+        });
+        let result = interpreter.execute(&Line::new("ROT X30".to_string()));
+        assert_eq!(result, Err(Error::InvalidCode('X')));
+    }
+
+    #[test]
+    fn interpreter_execute_modal_cycle_and_condition() {
+        let mut interpreter = sinumerik();
+        let datas = [
+            ("G0 Z50 R1=1", (0.0, 0.0, 50.0)),
+            ("MCALL CYCLE81(5, 0, 2, -10)", (0.0, 0.0, 50.0)),
+            ("X10 Y10", (10.0, 10.0, 5.0)),
+            ("IF R1==2", (10.0, 10.0, 5.0)),
+            ("X20", (10.0, 10.0, 5.0)),
+            ("ELSE", (10.0, 10.0, 5.0)),
+            ("X30 Z40", (30.0, 10.0, 5.0)),
+            ("ENDIF", (30.0, 10.0, 5.0)),
+            ("MCALL", (30.0, 10.0, 5.0)),
+            ("X40 Z40", (40.0, 10.0, 40.0)),
+        ];
+        datas.iter().for_each(|&(line, expected)| {
+            interpreter.execute(&Line::new(line.to_string())).unwrap();
+            assert_eq!(position(&interpreter), expected, "{}", line);
+        });
+        let result = interpreter.execute(&Line::new("ENDIF".to_string()));
+        assert_eq!(result, Err(Error::InvalidParser("ENDIF".to_string())));
+    }
+
+    #[test]
+    fn interpreter_execute_heidenhain() {
+        let mut profile = MachineProfile::default();
+        profile.dialect_set(DialectKind::Heidenhain);
+        let mut interpreter = Interpreter::new(profile);
+        let datas = [
+            ("1 TOOL CALL 3 Z S2000", (0.0, 0.0, 0.0)),
+            ("2 L Z+100 R0 FMAX M3", (0.0, 0.0, 100.0)),
+            ("3 L X+10 Y+0 FMAX", (10.0, 0.0, 100.0)),
+            ("4 CC X+0 Y+0", (10.0, 0.0, 100.0)),
+            ("5 C X+0 Y+10 DR+ F300", (0.0, 10.0, 100.0)),
+            ("6 CR X-10 Y+0 R+10 DR+", (-10.0, 0.0, 100.0)),
+            ("7 CYCL DEF 200 DRILLING ~", (-10.0, 0.0, 100.0)),
+            ("  Q200=2 ;SET-UP CLEARANCE ~", (-10.0, 0.0, 100.0)),
+            ("  Q203=+0 ;SURFACE COORDINATE ~", (-10.0, 0.0, 100.0)),
+            ("  Q204=50 ;2ND SET-UP CLEARANCE", (-10.0, 0.0, 100.0)),
+            ("8 L X+20 Y+20 FMAX M99", (20.0, 20.0, 50.0)),
+            ("9 L IX+5", (25.0, 20.0, 50.0)),
+        ];
+        datas.iter().for_each(|&(line, expected)| {
+            interpreter.execute(&Line::new(line.to_string())).unwrap();
+            assert_eq!(position(&interpreter), expected, "{}", line);
+        });
+        let state = interpreter.get_state();
+        assert_eq!((state.get_tool(), state.get_tool_length_offset(), state.get_feed()), (3, 3, 300.0));
+    }
+
+    #[test]
+    fn interpreter_execute_abnormality() {
+        let mut interpreter = Interpreter::new(MachineProfile::default());
+        let result = interpreter.execute(&Line::new("G02X10.0".to_string()));
+        assert_eq!(result, Err(Error::InvalidArc));
+    }
+
+    #[test]
+    fn interpreter_is_local() {
+        let datas = [
+            ("#1", true),
+            ("#30", true),
+            ("#31", false),
+            ("#100", false),
+            ("#<depth>", true),
+            ("#<_global>", false),
+            ("R1", false),
+            ("R30", false),
+            ("Q1", false),
+        ];
+        datas.iter().for_each(|&(name, expected)| {
+            assert_eq!(is_local(name), expected, "{}", name);
+        });
+    }
+}
@@ -1,9 +1,15 @@
 use nom::branch::alt;
 use nom::bytes::complete::{tag, take_until};
 use nom::IResult;
-use nom::combinator::{opt, recognize};
-use nom::sequence::{tuple, preceded};
-use nom::character::complete::{char, digit0, digit1};
+use nom::bytes::complete::take_while1;
+use nom::combinator::{map, not, opt, recognize, value};
+use nom::multi::many0;
+use nom::sequence::{tuple, preceded, delimited};
+use nom::character::complete::{anychar, char, digit0, digit1, line_ending, multispace1, one_of, satisfy};
+use std::fmt;
+
+/// 位置決めモード、X、Yの解析結果
+pub type ParsedForState<'a> = (Option<&'a str>, Option<(char, &'a str)>, Option<(char, &'a str)>);
 
 #[derive(Debug, PartialEq)]
 pub struct Line {
@@ -15,13 +21,317 @@ impl Line {
         Self { line }
     }
 
-    pub fn parsed_for_state(&self) -> IResult<&str, (Option<&str>, Option<(char, &str)>, Option<(char, &str)>)> {
+    /// 行の文字列を返す
+    pub fn get_line(&self) -> &str {
+        &self.line
+    }
+
+    pub fn parsed_for_state(&self) -> IResult<&str, ParsedForState<'_>> {
         let (_, g_value) = opt(positioning_mode)(&self.line)?;
         let (_, x_value) = opt(x)(&self.line)?;
         let (_, y_value) = opt(y)(&self.line)?;
 
         Ok(("", (g_value, x_value, y_value)))
     }
+
+    /// 行に含まれるワードを先頭から順に返す
+    /// 解析できなかった残りの文字列も返す
+    pub fn parsed_words(&self) -> IResult<&str, Vec<(char, &str)>> {
+        words(&self.line)
+    }
+
+    /// 行に含まれるコメントを先頭から順に返す
+    pub fn parsed_comments(&self) -> IResult<&str, Vec<&str>> {
+        comments(&self.line)
+    }
+
+    /// 行の全ての文字を保持した構文木を返す
+    pub fn parsed_cst(&self) -> CstLine {
+        let (_, nodes) = cst_nodes(&self.line).unwrap_or_default();
+        CstLine { nodes, ending: String::new() }
+    }
+}
+
+/// 空白、コメント、"%"を含めて全ての文字を保持した構文木
+/// 出力すると元のプログラムと1バイトも違わない文字列に戻る
+#[derive(Debug, Default, PartialEq, Eq, Clone)]
+pub struct Cst {
+    lines: Vec<CstLine>,
+}
+
+impl Cst {
+    /// プログラムの文字列から構文木を作成
+    /// ワードとして解析できない文字は記述のまま保持する
+    pub fn new(source: &str) -> Self {
+        let mut lines = Vec::new();
+        let mut rest = source;
+        while !rest.is_empty() {
+            let (remaining, nodes) = cst_nodes(rest).unwrap_or_default();
+            let (remaining, ending) = opt(line_ending::<_, nom::error::Error<_>>)(remaining).unwrap_or((remaining, None));
+            lines.push(CstLine { nodes, ending: ending.unwrap_or_default().to_string() });
+            rest = remaining;
+        }
+        Self { lines }
+    }
+
+    /// 行を返す
+    pub fn get_lines(&self) -> &[CstLine] {
+        &self.lines
+    }
+
+    /// 編集する行を返す、例: 0で1行目
+    pub fn get_line_mut(&mut self, index: usize) -> Option<&mut CstLine> {
+        self.lines.get_mut(index)
+    }
+}
+
+impl fmt::Display for Cst {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.lines.iter().try_for_each(|line| write!(f, "{}", line))
+    }
+}
+
+/// 構文木の1行
+#[derive(Debug, Default, PartialEq, Eq, Clone)]
+pub struct CstLine {
+    nodes: Vec<CstNode>,
+    /// 改行の文字列、最後の行で改行がない場合は空
+    ending: String,
+}
+
+impl CstLine {
+    /// 行の要素を返す
+    pub fn get_nodes(&self) -> &[CstNode] {
+        &self.nodes
+    }
+
+    /// 改行の文字列を返す
+    pub fn get_ending(&self) -> &str {
+        &self.ending
+    }
+
+    /// 行に含まれるワードを先頭から順に返す
+    pub fn words(&self) -> impl Iterator<Item = &CstWord> {
+        self.nodes.iter().filter_map(|node| match node {
+            CstNode::Word(word) => Some(word),
+            _ => None,
+        })
+    }
+
+    /// 改行を除いた行の文字列を返す
+    pub fn text(&self) -> String {
+        self.nodes.iter().map(|node| node.to_string()).collect()
+    }
+
+    /// 行に含まれるワードを編集用に先頭から順に返す
+    pub fn words_mut(&mut self) -> impl Iterator<Item = &mut CstWord> {
+        self.nodes.iter_mut().filter_map(|node| match node {
+            CstNode::Word(word) => Some(word),
+            _ => None,
+        })
+    }
+
+    /// ワードを行の先頭に挿入する、シーケンス番号がある場合はその後ろに挿入する
+    pub fn word_insert(&mut self, code: char, value: &str) {
+        let index = match self.nodes.first() {
+            Some(CstNode::Word(word)) if word.code == 'N' => 1,
+            _ => 0,
+        };
+        self.nodes.insert(index, CstNode::Word(CstWord { code, value: value.to_string() }));
+    }
+
+    /// アドレスが一致する最初のワードの後ろにワードを挿入する
+    /// 一致するワードがない場合は最後のワードの後ろに挿入する
+    pub fn word_insert_after(&mut self, after: char, code: char, value: &str) {
+        let index = self.word_index(after)
+            .or_else(|| self.nodes.iter().rposition(|node| matches!(node, CstNode::Word(_))))
+            .map_or(0, |index| index + 1);
+        self.nodes.insert(index, CstNode::Word(CstWord { code, value: value.to_string() }));
+    }
+
+    /// アドレスが一致する最初のワードの前にワードを挿入する
+    /// 一致するワードがない場合は先頭に挿入する
+    pub fn word_insert_before(&mut self, before: char, code: char, value: &str) {
+        match self.word_index(before) {
+            Some(index) => self.nodes.insert(index, CstNode::Word(CstWord { code, value: value.to_string() })),
+            None => self.word_insert(code, value),
+        }
+    }
+
+    /// アドレスが一致する最初のワードの要素の位置を返す
+    fn word_index(&self, code: char) -> Option<usize> {
+        self.nodes.iter().position(|node| matches!(node, CstNode::Word(word) if word.code == code))
+    }
+
+    /// アドレスが一致する最初のワードを編集用に返す
+    pub fn word_mut(&mut self, code: char) -> Option<&mut CstWord> {
+        self.nodes.iter_mut().find_map(|node| match node {
+            CstNode::Word(word) if word.code == code => Some(word),
+            _ => None,
+        })
+    }
+}
+
+impl fmt::Display for CstLine {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.nodes.iter().try_for_each(|node| write!(f, "{}", node))?;
+        write!(f, "{}", self.ending)
+    }
+}
+
+/// 構文木の行の要素
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum CstNode {
+    /// アドレスと数値、例: "X-1515."
+    Word(CstWord),
+    /// 括弧を含むコメント、例: "(START)"
+    Comment(String),
+    /// 空白とタブ
+    Whitespace(String),
+    /// テープの始まりと終わりを表す"%"
+    Percent,
+    /// 式、";"などワードとして解析しない文字列
+    Text(String),
+}
+
+impl fmt::Display for CstNode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CstNode::Word(word) => write!(f, "{}", word),
+            CstNode::Comment(text) | CstNode::Whitespace(text) | CstNode::Text(text) => write!(f, "{}", text),
+            CstNode::Percent => write!(f, "%"),
+        }
+    }
+}
+
+/// 構文木のワード
+/// 値は記述のまま保持し、編集しない限り"G0"を"G00"のように揃えない
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct CstWord {
+    code: char,
+    value: String,
+}
+
+impl CstWord {
+    /// アドレスを返す
+    pub fn get_code(&self) -> char {
+        self.code
+    }
+
+    /// 値の文字列を返す
+    pub fn get_value(&self) -> &str {
+        &self.value
+    }
+
+    /// 値を設定
+    pub fn value_set(&mut self, value: &str) {
+        self.value = value.to_string();
+    }
+}
+
+impl fmt::Display for CstWord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}", self.code, self.value)
+    }
+}
+
+/// 構文木の要素を1つ返す
+/// どの要素にも当てはまらない文字は1文字ずつ文字列として返す
+fn cst_node(input: &str) -> IResult<&str, CstNode> {
+    alt((
+        map(word, |(code, value)| CstNode::Word(CstWord { code, value: value.to_string() })),
+        map(recognize(comment), |text: &str| CstNode::Comment(text.to_string())),
+        map(take_while1(|c| c == ' ' || c == '\t'), |text: &str| CstNode::Whitespace(text.to_string())),
+        value(CstNode::Percent, char('%')),
+        map(recognize(anychar), |text: &str| CstNode::Text(text.to_string())),
+    ))(input)
+}
+
+/// 改行に到達するまで構文木の要素を順に返す
+/// 連続する文字列は1つの要素にまとめる
+fn cst_nodes(input: &str) -> IResult<&str, Vec<CstNode>> {
+    let (rest, nodes) = many0(preceded(not(line_ending), cst_node))(input)?;
+    let nodes = nodes.into_iter().fold(Vec::new(), |mut nodes: Vec<CstNode>, node| {
+        match (nodes.last_mut(), node) {
+            (Some(CstNode::Text(text)), CstNode::Text(next)) => text.push_str(&next),
+            (_, node) => nodes.push(node),
+        }
+        nodes
+    });
+    Ok((rest, nodes))
+}
+
+/// Gコードの値を2桁の整数部に揃えたコードを返す
+/// 例: "0" -> "G00", "5.1" -> "G05.1", "90" -> "G90"
+pub fn g_code(value: &str) -> String {
+    code('G', value)
+}
+
+/// Mコードの値を2桁の整数部に揃えたコードを返す
+/// 例: "3" -> "M03", "102" -> "M102"
+pub fn m_code(value: &str) -> String {
+    code('M', value)
+}
+
+/// アドレスと値から整数部を2桁に揃えたコードを返す
+fn code(address: char, value: &str) -> String {
+    let value = value.trim_start_matches('+');
+    let (integer, decimal) = match value.split_once('.') {
+        Some((integer, decimal)) => (integer, decimal.trim_end_matches('0')),
+        None => (value, ""),
+    };
+    let integer = integer.trim_start_matches('0');
+    if decimal.is_empty() {
+        format!("{}{:0>2}", address, integer)
+    } else {
+        format!("{}{:0>2}.{}", address, integer, decimal)
+    }
+}
+
+/// 符号付きの数値を認識して返す
+/// "100", "-1515.", "+.5" の形式に対応
+pub fn number(input: &str) -> IResult<&str, &str> {
+    recognize(
+        tuple((
+            opt(one_of("+-")),
+            alt((
+                recognize(tuple((digit1, opt(char('.')), digit0))),
+                recognize(tuple((char('.'), digit1))),
+            ))
+        ))
+    )(input)
+}
+
+/// 括弧で囲まれたコメントを返す
+pub fn comment(input: &str) -> IResult<&str, &str> {
+    delimited(char('('), take_until(")"), char(')'))(input)
+}
+
+/// コメントに到達するまでスキップし、コメントを順に返す
+pub fn comments(input: &str) -> IResult<&str, Vec<&str>> {
+    many0(preceded(take_until("("), comment))(input)
+}
+
+/// ワードとワードの間にある空白、コメント、"%"、";"を読み飛ばす
+pub fn separator(input: &str) -> IResult<&str, ()> {
+    value(
+        (),
+        many0(alt((
+            value((), multispace1),
+            value((), comment),
+            value((), one_of("%;")),
+        )))
+    )(input)
+}
+
+/// アドレス（英大文字）と数値の組を返す
+pub fn word(input: &str) -> IResult<&str, (char, &str)> {
+    tuple((satisfy(|c| c.is_ascii_uppercase()), number))(input)
+}
+
+/// 空白とコメントを読み飛ばしながらワードを順に返す
+pub fn words(input: &str) -> IResult<&str, Vec<(char, &str)>> {
+    delimited(separator, many0(delimited(separator, word, separator)), separator)(input)
 }
 
 /// Gコードに到達するまでスキップし、Gコードと値、残りの文字列を返す
@@ -323,4 +633,150 @@ mod tests {
             assert_eq!(result, *expected);
         })
     }
+
+    #[test]
+    fn line_parsed_words() {
+        let datas = [
+            (
+                "G90G00X-1610.0Y137.0V0.0W0.0S1000 ",
+                ("", vec![('G', "90"), ('G', "00"), ('X', "-1610.0"), ('Y', "137.0"), ('V', "0.0"), ('W', "0.0"), ('S', "1000")])
+            ),
+            (
+                "G01Z5.0F750.0( --- 5.0MM NOKOSHI )",
+                ("", vec![('G', "01"), ('Z', "5.0"), ('F', "750.0")])
+            ),
+            (
+                "%",
+                ("", vec![])
+            ),
+            (
+                "G02 X10. Y-.5 R5",
+                ("", vec![('G', "02"), ('X', "10."), ('Y', "-.5"), ('R', "5")])
+            ),
+            (
+                "G90x100.0",
+                ("x100.0", vec![('G', "90")])
+            ),
+        ];
+
+        datas.iter().for_each(|(input, expected)| {
+            let line = Line::new(input.to_string());
+            let result = line.parsed_words();
+
+            assert_eq!(result.unwrap(), expected.clone());
+        })
+    }
+
+    #[test]
+    fn line_g_code() {
+        let datas = [
+            ("0", "G00"),
+            ("00", "G00"),
+            ("1", "G01"),
+            ("90", "G90"),
+            ("5.1", "G05.1"),
+            ("05.1", "G05.1"),
+            ("43", "G43"),
+            ("17.0", "G17"),
+        ];
+
+        datas.iter().for_each(|&(input, expected)| {
+            assert_eq!(g_code(input), expected);
+        })
+    }
+
+    #[test]
+    fn line_parsed_comments() {
+        let datas = [
+            ("G01Z5.0F750.0( --- 5.0MM NOKOSHI )", vec![" --- 5.0MM NOKOSHI "]),
+            ("(A)X1.(B)", vec!["A", "B"]),
+            ("X1.", vec![]),
+        ];
+
+        datas.iter().for_each(|(input, expected)| {
+            let line = Line::new(input.to_string());
+            let (_, result) = line.parsed_comments().unwrap();
+
+            assert_eq!(result, *expected);
+        })
+    }
+
+    #[test]
+    fn line_m_code() {
+        let datas = [("3", "M03"), ("03", "M03"), ("102", "M102"), ("1", "M01")];
+
+        datas.iter().for_each(|&(input, expected)| {
+            assert_eq!(m_code(input), expected);
+        })
+    }
+
+    #[test]
+    fn line_cst_round_trip() {
+        let datas = [
+            "",
+            "%\nO1000 (MAIN)\nG90 G00  X-1515. Y137.\t;\n%",
+            "%\r\nN10G01Z-#1F100.(CUT (A)\r\n\r\nM30\r\n",
+            "X1.(UNCLOSED\n\nR1=R2+1 ; SINUMERIK\n",
+            "g01 x1.5.3 \rY2",
+        ];
+
+        datas.iter().for_each(|input| {
+            assert_eq!(Cst::new(input).to_string(), *input);
+        })
+    }
+
+    #[test]
+    fn line_cst_nodes() {
+        let cst = Cst::new("%\nG01  X-1.(A)#1;\r\n");
+        let word = |code, value: &str| CstNode::Word(CstWord { code, value: value.to_string() });
+        let expected = [
+            (vec![CstNode::Percent], "\n"),
+            (
+                vec![
+                    word('G', "01"),
+                    CstNode::Whitespace("  ".to_string()),
+                    word('X', "-1."),
+                    CstNode::Comment("(A)".to_string()),
+                    CstNode::Text("#1;".to_string()),
+                ],
+                "\r\n",
+            ),
+        ];
+
+        assert_eq!(cst.get_lines().len(), expected.len());
+        cst.get_lines().iter().zip(expected.iter()).for_each(|(line, (nodes, ending))| {
+            assert_eq!(line.get_nodes(), nodes.as_slice());
+            assert_eq!(line.get_ending(), *ending);
+        });
+        assert_eq!(Line::new("G01  X-1.(A)#1;".to_string()).parsed_cst(), CstLine { ending: String::new(), ..cst.get_lines()[1].clone() });
+    }
+
+    #[test]
+    fn line_cst_edit() {
+        let source = "%\nG90 G00  X-1515.  Y137. (START)\r\nG01\tX0 Y0\n";
+        let mut cst = Cst::new(source);
+        cst.get_line_mut(1).unwrap().word_mut('Y').unwrap().value_set("-20.");
+
+        assert_eq!(cst.to_string(), source.replacen("Y137.", "Y-20.", 1));
+        assert_eq!(cst.get_lines()[2].words().map(|word| word.get_code()).collect::<String>(), "GXY");
+        assert!(cst.get_line_mut(3).is_none());
+
+        let line = cst.get_line_mut(2).unwrap();
+        line.words_mut().filter(|word| word.get_code() != 'G').for_each(|word| word.value_set("5."));
+        line.word_insert('G', "91");
+        assert_eq!(line.text(), "G91G01\tX5. Y5.");
+        let mut line = Line::new("N10 X1.".to_string()).parsed_cst();
+        line.word_insert('G', "90");
+        assert_eq!(line.text(), "N10G90 X1.");
+        line.word_insert_after('X', 'Y', "2.");
+        line.word_insert_before('X', 'Z', "3.");
+        line.word_insert_after('J', 'F', "10.");
+        assert_eq!(line.text(), "N10G90 Z3.X1.Y2.F10.");
+    }
+
+    #[test]
+    fn line_comment() {
+        let result = comment("( *** KANSYOU CYUUI *** )X1.");
+        assert_eq!(result.unwrap(), ("X1.", " *** KANSYOU CYUUI *** "));
+    }
 }
@@ -0,0 +1,414 @@
+use std::collections::BTreeMap;
+
+use crate::error::Error;
+use crate::structs::block::{Block, Word};
+use crate::structs::dialect::DialectKind;
+use crate::structs::state::State;
+use serde::{Deserialize, Serialize};
+
+/// 小数点のない数値の解釈
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DecimalInput {
+    /// 最小設定単位の倍数とする（X100は0.1mm）
+    #[default]
+    Standard,
+    /// 電卓型、mm単位とする（X100は100mm）
+    Calculator,
+}
+
+/// 軸ごとの設定
+#[derive(Debug, PartialEq, Clone, Copy, Serialize, Deserialize)]
+pub struct AxisProfile {
+    /// プログラムで使用するアドレス
+    letter: char,
+    /// 機械座標の移動範囲
+    min: Option<f32>,
+    max: Option<f32>,
+    /// 早送り速度（mm/min）
+    rapid_rate: f32,
+}
+
+impl AxisProfile {
+    /// インスタンス化
+    pub fn new(letter: char, min: Option<f32>, max: Option<f32>, rapid_rate: f32) -> Self {
+        Self { letter, min, max, rapid_rate }
+    }
+
+    /// アドレスを返す
+    pub fn get_letter(&self) -> char {
+        self.letter
+    }
+
+    /// 機械座標の最小値を返す
+    pub fn get_min(&self) -> Option<f32> {
+        self.min
+    }
+
+    /// 機械座標の最大値を返す
+    pub fn get_max(&self) -> Option<f32> {
+        self.max
+    }
+
+    /// 早送り速度を返す
+    pub fn get_rapid_rate(&self) -> f32 {
+        self.rapid_rate
+    }
+}
+
+/// X、Y、Zの各軸の設定
+/// 記述しない軸は初期値とする
+#[derive(Debug, PartialEq, Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Axes {
+    x: AxisProfile,
+    y: AxisProfile,
+    z: AxisProfile,
+}
+
+impl Default for Axes {
+    fn default() -> Self {
+        Self {
+            x: AxisProfile::new('X', None, None, 30000.0),
+            y: AxisProfile::new('Y', None, None, 30000.0),
+            z: AxisProfile::new('Z', None, None, 24000.0),
+        }
+    }
+}
+
+impl Axes {
+    /// X、Y、Zの順に設定を返す
+    pub fn to_array(&self) -> [AxisProfile; 3] {
+        [self.x, self.y, self.z]
+    }
+}
+
+/// ワーク座標系と工具長補正の補正量
+#[derive(Debug, Default, PartialEq, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OffsetTable {
+    /// ワーク座標系のコードごとのワーク原点の機械座標
+    work_offsets: BTreeMap<String, [f32; 3]>,
+    /// 工具長補正の番号ごとの補正量
+    tool_lengths: BTreeMap<u32, f32>,
+}
+
+impl OffsetTable {
+    /// ワーク原点の機械座標を設定
+    pub fn work_offset_set(&mut self, code: &str, offset: [f32; 3]) {
+        self.work_offsets.insert(code.to_string(), offset);
+    }
+
+    /// 工具長補正量を設定
+    pub fn tool_length_set(&mut self, number: u32, length: f32) {
+        self.tool_lengths.insert(number, length);
+    }
+
+    /// ワーク原点の機械座標を返す、未設定の場合は原点とする
+    pub fn work_offset(&self, code: &str) -> [f32; 3] {
+        self.work_offsets.get(code).copied().unwrap_or_default()
+    }
+
+    /// 工具長補正量を返す、未設定の場合は0とする
+    pub fn tool_length(&self, number: u32) -> f32 {
+        self.tool_lengths.get(&number).copied().unwrap_or_default()
+    }
+
+    /// 状態のワーク座標から機械座標への変換量を返す
+    pub fn offset(&self, state: &State) -> [f32; 3] {
+        let mut offset = self.work_offset(&state.get_work_offset().code());
+        offset[2] += self.tool_length(state.get_tool_length_offset());
+        offset
+    }
+}
+
+/// 機械ごとの設定
+/// 指定しない項目はファナック系の立形マシニングセンタを想定した値とする
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MachineProfile {
+    name: String,
+    /// 制御装置の方言
+    dialect: DialectKind,
+    axes: Axes,
+    decimal_input: DecimalInput,
+    /// 最小設定単位（mm）
+    least_increment: f32,
+    /// 工具交換の時間（秒）
+    tool_change_time: f32,
+    /// 主軸の最高回転数
+    max_spindle_speed: Option<f32>,
+    /// Mコードの意味
+    m_codes: BTreeMap<String, String>,
+    offsets: OffsetTable,
+    /// 基準点の番号ごとの機械座標、未設定の場合は機械原点とする
+    reference_points: BTreeMap<u32, [f32; 3]>,
+}
+
+impl Default for MachineProfile {
+    fn default() -> Self {
+        let m_codes = [
+            ("M00", "program stop"),
+            ("M01", "optional stop"),
+            ("M02", "end of program"),
+            ("M03", "spindle on clockwise"),
+            ("M04", "spindle on counterclockwise"),
+            ("M05", "spindle stop"),
+            ("M06", "tool change"),
+            ("M07", "mist coolant on"),
+            ("M08", "flood coolant on"),
+            ("M09", "coolant off"),
+            ("M19", "spindle orientation"),
+            ("M30", "end of program and rewind"),
+            ("M98", "subprogram call"),
+            ("M99", "end of subprogram"),
+        ];
+        Self {
+            name: "Fanuc vertical machining center".to_string(),
+            dialect: DialectKind::Fanuc,
+            axes: Axes::default(),
+            decimal_input: DecimalInput::Standard,
+            least_increment: 0.001,
+            tool_change_time: 3.0,
+            max_spindle_speed: Some(12000.0),
+            m_codes: m_codes.iter().map(|(code, meaning)| (code.to_string(), meaning.to_string())).collect(),
+            offsets: OffsetTable::default(),
+            reference_points: BTreeMap::new(),
+        }
+    }
+}
+
+impl MachineProfile {
+    /// TOMLの文字列からインスタンス化
+    pub fn from_toml(source: &str) -> Result<Self, Error> {
+        toml::from_str(source).map_err(|e| Error::InvalidConfig(e.to_string()))
+    }
+
+    /// 名前を返す
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    /// 制御装置の方言を返す
+    pub fn get_dialect(&self) -> DialectKind {
+        self.dialect
+    }
+
+    /// 各軸の設定を返す
+    pub fn get_axes(&self) -> &Axes {
+        &self.axes
+    }
+
+    /// 小数点のない数値の解釈を返す
+    pub fn get_decimal_input(&self) -> DecimalInput {
+        self.decimal_input
+    }
+
+    /// 工具交換の時間（秒）を返す
+    pub fn get_tool_change_time(&self) -> f32 {
+        self.tool_change_time
+    }
+
+    /// 主軸の最高回転数を返す
+    pub fn get_max_spindle_speed(&self) -> Option<f32> {
+        self.max_spindle_speed
+    }
+
+    /// 補正量を返す
+    pub fn get_offsets(&self) -> &OffsetTable {
+        &self.offsets
+    }
+
+    /// 補正量を設定
+    pub fn offsets_set(&mut self, offsets: OffsetTable) {
+        self.offsets = offsets;
+    }
+
+    /// 基準点の機械座標を返す、未設定の場合は機械原点とする
+    pub fn reference_point(&self, number: u32) -> [f32; 3] {
+        self.reference_points.get(&number).copied().unwrap_or_default()
+    }
+
+    /// 基準点の機械座標を設定
+    pub fn reference_point_set(&mut self, number: u32, point: [f32; 3]) {
+        self.reference_points.insert(number, point);
+    }
+
+    /// 状態のワーク座標系と工具長補正での基準点の位置を返す
+    pub fn reference_position(&self, number: u32, state: &State) -> [f32; 3] {
+        let point = self.reference_point(number);
+        let offset = self.offsets.offset(state);
+        [0, 1, 2].map(|axis| point[axis] - offset[axis])
+    }
+
+    /// 制御装置の方言を設定
+    pub fn dialect_set(&mut self, dialect: DialectKind) {
+        self.dialect = dialect;
+    }
+
+    /// 小数点のない数値の解釈を設定
+    pub fn decimal_input_set(&mut self, decimal_input: DecimalInput) {
+        self.decimal_input = decimal_input;
+    }
+
+    /// 定義されたMコードを返す
+    pub fn get_m_codes(&self) -> Vec<String> {
+        self.m_codes.keys().cloned().collect()
+    }
+
+    /// Mコードの意味を返す
+    pub fn m_code_meaning(&self, code: &str) -> Option<&str> {
+        self.m_codes.get(code).map(|meaning| meaning.as_str())
+    }
+
+    /// 機械のアドレスと数値の形式を、X、Y、Zのアドレスとmm単位の数値に揃えたブロックを返す
+    pub fn canonical(&self, block: &Block) -> Result<Block, Error> {
+        let letters = self.axes.to_array().map(|axis| axis.letter);
+        let words = block.get_words().iter().map(|word| {
+            let code = match letters.iter().position(|&letter| letter == word.get_code()) {
+                Some(axis) => ['X', 'Y', 'Z'][axis],
+                // 別のアドレスに割り当てた軸の標準のアドレスは使用しない
+                None if ['X', 'Y', 'Z'].contains(&word.get_code()) => '\0',
+                None => word.get_code(),
+            };
+            let value = word.get_value();
+            if matches!(code, 'X' | 'Y' | 'Z' | 'I' | 'J' | 'K' | 'R')
+                && self.decimal_input == DecimalInput::Standard
+                && !value.contains('.')
+            {
+                let value = value.parse::<f32>()? / (1.0 / self.least_increment).round();
+                Ok(Word::new(code, &value.to_string()))
+            } else {
+                Ok(Word::new(code, value))
+            }
+        }).collect::<Result<Vec<_>, Error>>()?;
+
+        Ok(Block::from_words(
+            words.into_iter().filter(|word| word.get_code() != '\0').collect(),
+            block.get_comments().to_vec(),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::structs::line_parser::Line;
+
+    fn block(line: &str) -> Block {
+        Block::new(&Line::new(line.to_string())).unwrap()
+    }
+
+    #[test]
+    fn machine_profile_default() {
+        let result = MachineProfile::default();
+        assert_eq!(result.get_dialect(), DialectKind::Fanuc);
+        assert_eq!(result.get_decimal_input(), DecimalInput::Standard);
+        assert_eq!(result.get_axes().to_array()[2].get_rapid_rate(), 24000.0);
+        assert_eq!(result.m_code_meaning("M01"), Some("optional stop"));
+        assert_eq!(result.m_code_meaning("M102"), None);
+        assert_eq!(result.get_m_codes().len(), 14);
+    }
+
+    #[test]
+    fn machine_profile_from_toml() {
+        let source = r#"
+            name = "Large VMC"
+            decimal_input = "calculator"
+            tool_change_time = 8.5
+
+            [axes]
+            x = { letter = "X", min = -1800.0, max = 0.0, rapid_rate = 24000.0 }
+            y = { letter = "Y", min = -800.0, max = 0.0, rapid_rate = 24000.0 }
+            z = { letter = "W", min = -600.0, max = 0.0, rapid_rate = 15000.0 }
+
+            [m_codes]
+            M102 = "chip conveyor on"
+
+            [offsets.work_offsets]
+            G54 = [-1000.0, -400.0, -500.0]
+
+            [offsets.tool_lengths]
+            267 = 150.0
+        "#;
+        let result = MachineProfile::from_toml(source).unwrap();
+        assert_eq!(result.get_name(), "Large VMC");
+        assert_eq!(result.get_decimal_input(), DecimalInput::Calculator);
+        assert_eq!(result.get_tool_change_time(), 8.5);
+        assert_eq!(result.get_axes().to_array()[0].get_min(), Some(-1800.0));
+        assert_eq!(result.m_code_meaning("M102"), Some("chip conveyor on"));
+        assert_eq!(result.get_offsets().work_offset("G54"), [-1000.0, -400.0, -500.0]);
+        assert_eq!(result.get_offsets().tool_length(267), 150.0);
+        // 指定しない項目は初期値とする
+        assert_eq!(result.get_dialect(), DialectKind::Fanuc);
+    }
+
+    #[test]
+    fn machine_profile_reference_position() {
+        let source = r#"
+            [offsets.work_offsets]
+            G54 = [-400.0, -250.0, -350.0]
+
+            [offsets.tool_lengths]
+            1 = 120.0
+
+            [reference_points]
+            2 = [0.0, 0.0, -50.0]
+        "#;
+        let profile = MachineProfile::from_toml(source).unwrap();
+        assert_eq!(profile.reference_point(1), [0.0, 0.0, 0.0]);
+        assert_eq!(profile.reference_point(2), [0.0, 0.0, -50.0]);
+
+        let mut state = State::default();
+        state.state_update(Line::new("G54G43H1Z100.".to_string())).unwrap();
+        assert_eq!(profile.reference_position(1, &State::default()), [400.0, 250.0, 350.0]);
+        assert_eq!(profile.reference_position(2, &state), [400.0, 250.0, 180.0]);
+    }
+
+    #[test]
+    fn machine_profile_from_toml_abnormality() {
+        let datas = ["least_increment = \"fine\"", "dialect = \"unknown\""];
+        datas.iter().for_each(|source| {
+            let result = MachineProfile::from_toml(source);
+            assert!(matches!(result, Err(Error::InvalidConfig(_))));
+        })
+    }
+
+    #[test]
+    fn machine_profile_canonical_decimal_input() {
+        let profile = MachineProfile::default();
+        let result = profile.canonical(&block("G01X100Y-1515.Z25F100S1000")).unwrap();
+        assert_eq!(result, block("G01X0.1Y-1515.Z0.025F100S1000"));
+
+        let mut profile = MachineProfile::default();
+        profile.decimal_input_set(DecimalInput::Calculator);
+        let result = profile.canonical(&block("G01X100Y-1515.")).unwrap();
+        assert_eq!(result, block("G01X100Y-1515."));
+    }
+
+    #[test]
+    fn machine_profile_canonical_letters() {
+        let source = r#"
+            decimal_input = "calculator"
+            [axes]
+            x = { letter = "X", rapid_rate = 24000.0 }
+            y = { letter = "Y", rapid_rate = 24000.0 }
+            z = { letter = "W", rapid_rate = 15000.0 }
+        "#;
+        let profile = MachineProfile::from_toml(source).unwrap();
+        let result = profile.canonical(&block("G00X1.W-5.Z3.")).unwrap();
+        assert_eq!(result, block("G00X1.Z-5."));
+    }
+
+    #[test]
+    fn offset_table_offset() {
+        let mut table = OffsetTable::default();
+        table.work_offset_set("G55", [-100.0, -200.0, -300.0]);
+        table.tool_length_set(2, 120.0);
+        let mut state = State::default();
+        state.state_update(Line::new("G55G43H2Z10.0".to_string())).unwrap();
+        assert_eq!(table.offset(&state), [-100.0, -200.0, -180.0]);
+        state.state_update(Line::new("G54G49".to_string())).unwrap();
+        assert_eq!(table.offset(&state), [0.0, 0.0, 0.0]);
+    }
+}
@@ -0,0 +1,122 @@
+use serde::{Deserialize, Serialize};
+
+/// 移動の種類
+/// 早送り、直線補間、円弧補間（時計回り、反時計回り）
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy, Hash, Serialize, Deserialize)]
+pub enum Motion {
+    #[default]
+    Rapid,
+    Linear,
+    ClockwiseArc,
+    CounterClockwiseArc,
+}
+
+impl Motion {
+    /// 対応するGコードを返す
+    pub fn code(self) -> &'static str {
+        match self {
+            Motion::Rapid => "G00",
+            Motion::Linear => "G01",
+            Motion::ClockwiseArc => "G02",
+            Motion::CounterClockwiseArc => "G03",
+        }
+    }
+
+    /// 円弧補間であるかの真偽値を返す
+    pub fn is_arc(self) -> bool {
+        matches!(self, Motion::ClockwiseArc | Motion::CounterClockwiseArc)
+    }
+
+    /// 円弧の向きを反転した移動の種類を返す、円弧以外はそのまま返す
+    pub fn reversed(self) -> Motion {
+        match self {
+            Motion::ClockwiseArc => Motion::CounterClockwiseArc,
+            Motion::CounterClockwiseArc => Motion::ClockwiseArc,
+            motion => motion,
+        }
+    }
+}
+
+/// 移動モードの構造体
+/// G00, G01, G02, G03
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct MotionMode {
+    code: String,
+    motion: Motion,
+}
+
+impl MotionMode {
+    /// G00からG03のコードを検証し、インスタンス化
+    pub fn new(code: &str) -> Option<Self> {
+        let motion = match code {
+            "G00" => Motion::Rapid,
+            "G01" => Motion::Linear,
+            "G02" => Motion::ClockwiseArc,
+            "G03" => Motion::CounterClockwiseArc,
+            _ => return None,
+        };
+
+        Some(Self { code: code.to_string(), motion })
+    }
+
+    /// 移動の種類を返す
+    pub fn get_motion(&self) -> Motion {
+        self.motion
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn motion_code() {
+        let data = [
+            (Motion::Rapid, "G00"),
+            (Motion::Linear, "G01"),
+            (Motion::ClockwiseArc, "G02"),
+            (Motion::CounterClockwiseArc, "G03"),
+        ];
+        data.iter().for_each(|&(motion, expected)| {
+            assert_eq!(motion.code(), expected);
+        });
+    }
+
+    #[test]
+    fn motion_is_arc() {
+        assert!(!Motion::Rapid.is_arc());
+        assert!(!Motion::Linear.is_arc());
+        assert!(Motion::ClockwiseArc.is_arc());
+        assert!(Motion::CounterClockwiseArc.is_arc());
+    }
+
+    #[test]
+    fn motion_mode_new_normal() {
+        let data = [
+            ("G00", Motion::Rapid),
+            ("G01", Motion::Linear),
+            ("G02", Motion::ClockwiseArc),
+            ("G03", Motion::CounterClockwiseArc),
+        ];
+        data.iter().for_each(|&(code, motion)| {
+            let expected = MotionMode { code: code.to_string(), motion };
+            let result = MotionMode::new(code);
+            assert_eq!(result.unwrap(), expected);
+        });
+    }
+
+    #[test]
+    fn motion_mode_new_abnormality() {
+        let data = ["G0", "G04", "G90", "X00", "g01"];
+        data.iter().for_each(|&code| {
+            let result = MotionMode::new(code);
+            assert_eq!(result, None);
+        });
+    }
+
+    #[test]
+    fn motion_mode_get_motion() {
+        let result = MotionMode::new("G02").unwrap().get_motion();
+        assert_eq!(result, Motion::ClockwiseArc);
+    }
+}
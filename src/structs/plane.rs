@@ -0,0 +1,65 @@
+use serde::{Deserialize, Serialize};
+
+/// 円弧補間と極座標の平面
+/// G17（XY平面）、G18（ZX平面）、G19（YZ平面）
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy, Serialize, Deserialize)]
+pub enum Plane {
+    #[default]
+    XY,
+    ZX,
+    YZ,
+}
+
+impl Plane {
+    /// G17からG19のコードを検証し、インスタンス化
+    pub fn new(code: &str) -> Option<Self> {
+        match code {
+            "G17" => Some(Plane::XY),
+            "G18" => Some(Plane::ZX),
+            "G19" => Some(Plane::YZ),
+            _ => None,
+        }
+    }
+
+    /// 対応するGコードを返す
+    pub fn code(self) -> &'static str {
+        match self {
+            Plane::XY => "G17",
+            Plane::ZX => "G18",
+            Plane::YZ => "G19",
+        }
+    }
+
+    /// 平面の第1軸と第2軸のアドレスを返す
+    /// 極座標では第1軸が半径、第2軸が角度となる
+    pub fn axes(self) -> (char, char) {
+        match self {
+            Plane::XY => ('X', 'Y'),
+            Plane::ZX => ('Z', 'X'),
+            Plane::YZ => ('Y', 'Z'),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn plane_new() {
+        let data = [
+            ("G17", Some(Plane::XY), ('X', 'Y')),
+            ("G18", Some(Plane::ZX), ('Z', 'X')),
+            ("G19", Some(Plane::YZ), ('Y', 'Z')),
+            ("G16", None, ('X', 'Y')),
+        ];
+        data.iter().for_each(|&(code, expected, axes)| {
+            let result = Plane::new(code);
+            assert_eq!(result, expected);
+            assert_eq!(result.unwrap_or_default().axes(), axes);
+            if let Some(plane) = result {
+                assert_eq!(plane.code(), code);
+            }
+        });
+    }
+}
@@ -1,6 +1,8 @@
+use serde::{Deserialize, Serialize};
+
 /// 位置決め方式の構造体
 /// アブソリュートまたはインクレメンタル
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
 pub struct PositioningMode {
     code: String,
     incremental: bool,
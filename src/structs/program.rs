@@ -0,0 +1,408 @@
+use crate::error::Error;
+use crate::structs::block::Block;
+use crate::structs::dialect::{Direction, Jump};
+use crate::structs::interpreter::{Interpreter, Transfer};
+use crate::structs::line_parser::Line;
+use crate::structs::machine_profile::MachineProfile;
+use crate::structs::state::State;
+use serde::{Deserialize, Serialize};
+
+/// ジャンプで繰り返し実行できる行数の上限
+/// 固定サイクルと原点復帰で同じ行から記録する経由点のブロックは数えない
+const MAX_REPEATED_LINES: usize = 100_000;
+
+/// 実行したブロックの記録
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct ExecutedBlock {
+    /// 1から始まる行番号
+    line_number: usize,
+    source: String,
+    block: Block,
+    /// ブロック実行後の状態
+    state: State,
+    /// 軸の移動があったか
+    moved: bool,
+    /// サブプログラムの呼び出しの深さ、メインプログラムは0
+    #[serde(default)]
+    depth: usize,
+}
+
+impl ExecutedBlock {
+    /// 行番号を返す
+    pub fn get_line_number(&self) -> usize {
+        self.line_number
+    }
+
+    /// 元の文字列を返す
+    pub fn get_source(&self) -> &str {
+        &self.source
+    }
+
+    /// 解析したブロックを返す
+    pub fn get_block(&self) -> &Block {
+        &self.block
+    }
+
+    /// ブロック実行後の状態を返す
+    pub fn get_state(&self) -> State {
+        self.state
+    }
+
+    /// 軸の移動があったかの真偽値を返す
+    pub fn get_moved(&self) -> bool {
+        self.moved
+    }
+
+    /// サブプログラムの呼び出しの深さを返す、メインプログラムは0
+    pub fn get_depth(&self) -> usize {
+        self.depth
+    }
+}
+
+/// NCプログラム全体を実行した結果
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct Program {
+    start: State,
+    blocks: Vec<ExecutedBlock>,
+}
+
+impl Program {
+    /// 初期状態から標準の機械の設定でプログラムを実行し、インスタンス化
+    pub fn new(source: &str) -> Result<Self, Error> {
+        Self::with_interpreter(source, Interpreter::default())
+    }
+
+    /// 指定した状態から標準の機械の設定でプログラムを実行し、インスタンス化
+    pub fn with_state(source: &str, start: State) -> Result<Self, Error> {
+        Self::with_interpreter(source, Interpreter::with_state(MachineProfile::default(), start))
+    }
+
+    /// 機械の設定と開始時の状態を持つインタプリタでプログラムを実行し、インスタンス化
+    /// ジャンプした場合はラベルの行から実行を続け、サブプログラムから復帰した場合は呼び出しの次の行に戻る
+    /// エラーの場合は行番号を付けて返す
+    pub fn with_interpreter(source: &str, interpreter: Interpreter) -> Result<Self, Error> {
+        let start = interpreter.get_state();
+        let (blocks, _) = execute(source, interpreter, None)?;
+        Ok(Self { start, blocks })
+    }
+
+    /// 指定した行（1から始まる行番号）を最初に実行する直前までプログラムを実行し、
+    /// それまでに実行したブロックと、その時点のインタプリタを返す
+    /// 指定した行を実行しない場合はNoneを返す
+    pub fn until_line(
+        source: &str,
+        interpreter: Interpreter,
+        line_number: usize,
+    ) -> Result<Option<(Self, Interpreter)>, Error> {
+        let start = interpreter.get_state();
+        let (blocks, interpreter) = execute(source, interpreter, Some(line_number))?;
+        Ok(interpreter.map(|interpreter| (Self { start, blocks }, interpreter)))
+    }
+
+    /// 開始時の状態を返す
+    pub fn get_start(&self) -> State {
+        self.start
+    }
+
+    /// 実行したブロックを返す
+    pub fn get_blocks(&self) -> &[ExecutedBlock] {
+        &self.blocks
+    }
+
+    /// 開始時の状態と、移動があったブロックの状態を順に返す
+    pub fn states(&self) -> Vec<State> {
+        std::iter::once(self.start)
+            .chain(self.moves().map(|(_, block)| block.state))
+            .collect()
+    }
+
+    /// 工具交換（M06）を行ったブロックを、交換前に装着していた工具番号と組にして順に返す
+    pub fn tool_changes(&self) -> impl Iterator<Item = (u32, &ExecutedBlock)> {
+        let mut previous = self.start;
+        self.blocks.iter().filter_map(move |block| {
+            let tool = previous.get_active_tool();
+            previous = block.state;
+            block.state.get_tool_change().then_some((tool, block))
+        })
+    }
+
+    /// 移動があったブロックを、移動前の状態と組にして順に返す
+    pub fn moves(&self) -> impl Iterator<Item = (State, &ExecutedBlock)> {
+        let mut previous = self.start;
+        self.blocks.iter().filter_map(move |block| {
+            let start = previous;
+            previous = block.state;
+            block.moved.then_some((start, block))
+        })
+    }
+}
+
+/// プログラムを実行し、実行したブロックを返す
+/// 停止する行を指定した場合は、その行を最初に実行する直前で止めてインタプリタも返す
+fn execute(
+    source: &str,
+    mut interpreter: Interpreter,
+    stop: Option<usize>,
+) -> Result<(Vec<ExecutedBlock>, Option<Interpreter>), Error> {
+    let lines: Vec<&str> = source.lines().collect();
+    let dialect = interpreter.get_profile().get_dialect().dialect();
+    let labels: Vec<Option<String>> = lines.iter().map(|line| dialect.label(line)).collect();
+    let limit = lines.len() + MAX_REPEATED_LINES;
+
+    let mut blocks = Vec::new();
+    let mut returns = Vec::new();
+    let mut executed = 0;
+    let mut index = 0;
+    while let Some(source) = lines.get(index) {
+        let line_number = index + 1;
+        if stop == Some(line_number) {
+            return Ok((blocks, Some(interpreter)));
+        }
+        let error = |e| Error::Line(line_number, Box::new(e));
+        if executed >= limit {
+            return Err(error(Error::ExecutionLimit(limit)));
+        }
+        executed += 1;
+        let line = Line::new(source.to_string());
+        let mut previous = interpreter.get_state();
+        let (block, result) = interpreter.execute(&line).map_err(error)?;
+        // 原点復帰と固定サイクルは経由した位置までの移動を同じ行の別のブロックとして記録し、
+        // 固定サイクルの位置が変わらない移動は記録しない
+        let position = |state: State| (state.get_x(), state.get_y(), state.get_z());
+        for via in result.map(|state| state.via_states()).unwrap_or_default() {
+            if via.get_cycle().is_some() && position(via) == position(previous) {
+                continue;
+            }
+            blocks.push(ExecutedBlock {
+                line_number,
+                source: source.to_string(),
+                block: block.clone(),
+                state: via,
+                moved: true,
+                depth: returns.len(),
+            });
+            previous = via;
+        }
+        blocks.push(ExecutedBlock {
+            line_number,
+            source: source.to_string(),
+            block,
+            state: interpreter.get_state(),
+            moved: result.is_some(),
+            depth: returns.len(),
+        });
+
+        let jump = |jump: &Jump| destination(&labels, index, jump)
+            .ok_or_else(|| error(Error::UndefinedLabel(jump.get_label().to_string())));
+        index = match interpreter.take_transfer() {
+            Some(Transfer::Jump(target)) => jump(&target)?,
+            Some(Transfer::Call(target)) => {
+                returns.push(index + 1);
+                jump(&target)?
+            },
+            Some(Transfer::Return) => returns.pop()
+                .ok_or_else(|| error(Error::InvalidParser("RETURN".to_string())))?,
+            None => index + 1,
+        };
+    }
+
+    Ok((blocks, None))
+}
+
+/// ジャンプ先の行の位置を返す
+/// 後方の探索はジャンプした行も含める
+fn destination(labels: &[Option<String>], index: usize, jump: &Jump) -> Option<usize> {
+    let matches = |&i: &usize| labels[i].as_deref() == Some(jump.get_label());
+    let forward = || (index + 1..labels.len()).find(matches);
+    let backward = || (0..=index).rev().find(matches);
+    match jump.get_direction() {
+        Direction::Forward => forward(),
+        Direction::Backward => backward(),
+        Direction::Both => forward().or_else(backward),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::structs::motion_mode::Motion;
+    use crate::structs::dialect::DialectKind;
+
+    const SOURCE: &str = "G90G00X-1610.0Y137.0\nM01\nG01Z5.0F750.0( --- 5.0MM NOKOSHI )\nX-1515.";
+
+    #[test]
+    fn program_new_normal() {
+        let program = Program::new(SOURCE).unwrap();
+        let blocks = program.get_blocks();
+        assert_eq!(blocks.len(), 4);
+        assert_eq!(blocks[1].get_line_number(), 2);
+        assert_eq!(blocks[1].get_source(), "M01");
+        assert!(!blocks[1].get_moved());
+        assert_eq!(blocks[1].get_state().get_x(), -1610.0);
+        assert_eq!(blocks[2].get_block().get_comments(), [" --- 5.0MM NOKOSHI ".to_string()]);
+        assert_eq!(blocks[3].get_state().get_feed(), 750.0);
+    }
+
+    #[test]
+    fn program_new_abnormality() {
+        let result = Program::new("G00X1.\nG90x1.");
+        assert_eq!(
+            result,
+            Err(Error::Line(2, Box::new(Error::InvalidParser("x1.".to_string()))))
+        );
+    }
+
+    fn sinumerik(source: &str) -> Result<Program, Error> {
+        let mut profile = MachineProfile::default();
+        profile.dialect_set(DialectKind::Sinumerik);
+        Program::with_interpreter(source, Interpreter::new(profile))
+    }
+
+    #[test]
+    fn program_with_interpreter_jump() {
+        let source = "R1=0\nLOOP: R1=R1+1 G0 X=R1*10\nIF R1<3 GOTOB LOOP\nGOTOF END\nX100\nEND: Y5";
+        let program = sinumerik(source).unwrap();
+        let result: Vec<(usize, f32, f32)> = program.moves()
+            .map(|(_, block)| (block.get_line_number(), block.get_state().get_x(), block.get_state().get_y()))
+            .collect();
+        assert_eq!(result, [(2, 10.0, 0.0), (2, 20.0, 0.0), (2, 30.0, 0.0), (6, 30.0, 5.0)]);
+    }
+
+    #[test]
+    fn program_with_interpreter_jump_abnormality() {
+        let result = sinumerik("G0 X1\nGOTOF END");
+        assert_eq!(result, Err(Error::Line(2, Box::new(Error::UndefinedLabel("END".to_string())))));
+
+        let result = sinumerik("LOOP: G0 X1\nGOTOB LOOP");
+        let limit = 2 + MAX_REPEATED_LINES;
+        assert_eq!(result, Err(Error::Line(1, Box::new(Error::ExecutionLimit(limit)))));
+    }
+
+    #[test]
+    fn program_new_many_holes() {
+        // 固定サイクルの経由点のブロックが上限を超えても、繰り返しがなければ実行する
+        let holes = MAX_REPEATED_LINES / 2;
+        let source = std::iter::once("G90G00Z10.\nG81X0Y0Z-5.R2.F100.".to_string())
+            .chain((1..holes).map(|n| format!("X{}.", n)))
+            .collect::<Vec<_>>()
+            .join("\n");
+        let program = Program::new(&source).unwrap();
+        assert!(program.get_blocks().len() > holes + MAX_REPEATED_LINES);
+    }
+
+    #[test]
+    fn program_with_interpreter_heidenhain() {
+        let mut profile = MachineProfile::default();
+        profile.dialect_set(DialectKind::Heidenhain);
+        let source = "0 BEGIN PGM LOOP MM\n1 FN 0: Q1 = +0\n2 LBL 1\n3 FN 1: Q1 = +Q1 + +10\n\
+            4 L X+Q1 FMAX\n5 FN 12: IF +Q1 LT +30 GOTO LBL 1\n6 END PGM LOOP MM";
+        let program = Program::with_interpreter(source, Interpreter::new(profile)).unwrap();
+        let result: Vec<(usize, f32)> = program.moves()
+            .map(|(_, block)| (block.get_line_number(), block.get_state().get_x()))
+            .collect();
+        assert_eq!(result, [(5, 10.0), (5, 20.0), (5, 30.0)]);
+    }
+
+    fn linuxcnc(source: &str) -> Result<Program, Error> {
+        let mut profile = MachineProfile::default();
+        profile.dialect_set(DialectKind::Linuxcnc);
+        Program::with_interpreter(source, Interpreter::new(profile))
+    }
+
+    fn x_moves(program: &Program) -> Vec<f32> {
+        program.moves().map(|(_, block)| block.get_state().get_x()).collect()
+    }
+
+    #[test]
+    fn program_with_interpreter_linuxcnc_sub() {
+        let source = "o<hole> sub\n#<x> = [#1 + 1]\ng0 x#<x>\no<hole> return [#<x> * 2]\no<hole> endsub\n\
+            #<x> = 5\no<hole> call [10]\ng0 x#<_value>\no<hole> call [20]\ng0 y#<x>\nm2";
+        let program = linuxcnc(source).unwrap();
+        assert_eq!(x_moves(&program), [11.0, 22.0, 21.0, 21.0]);
+        assert_eq!(program.get_blocks().last().unwrap().get_state().get_y(), 5.0);
+        let depths: Vec<usize> = program.moves().map(|(_, block)| block.get_depth()).collect();
+        assert_eq!(depths, [1, 0, 1, 0]);
+    }
+
+    #[test]
+    fn program_with_interpreter_linuxcnc_loop() {
+        let datas = [
+            ("#<i> = 0\no1 while [#<i> LT 3]\n#<i> = [#<i> + 1]\ng0 x#<i>\no1 endwhile", vec![1.0, 2.0, 3.0]),
+            ("#<i> = 0\no2 do\n#<i> = [#<i> + 1]\ng0 x#<i>\no2 while [#<i> LT 0]", vec![1.0]),
+            ("#<i> = 0\no3 repeat [3]\n#<i> = [#<i> + 1]\ng0 x#<i>\no3 endrepeat", vec![1.0, 2.0, 3.0]),
+            (
+                "#<i> = 0\no4 while [1]\n#<i> = [#<i> + 1]\no5 if [#<i> EQ 2]\no4 continue\n\
+                    o5 elseif [#<i> GT 3]\no4 break\no5 else\ng0 x#<i>\no5 endif\no4 endwhile\ng0 x9",
+                vec![1.0, 3.0, 9.0],
+            ),
+        ];
+        datas.iter().for_each(|(source, expected)| {
+            let program = linuxcnc(source).unwrap();
+            assert_eq!(x_moves(&program), *expected, "{}", source);
+        })
+    }
+
+    #[test]
+    fn program_with_interpreter_reference() {
+        let mut profile = MachineProfile::default();
+        let mut offsets = crate::structs::machine_profile::OffsetTable::default();
+        offsets.work_offset_set("G54", [-400.0, -250.0, -350.0]);
+        profile.offsets_set(offsets);
+        profile.reference_point_set(2, [-10.0, -20.0, 0.0]);
+        let source = "G90G54G01X10.Y10.Z50.F100.\nG91G28Z0.\nG30X0Y0\nG90G29X20.Y5.\nX0";
+        let program = Program::with_interpreter(source, Interpreter::new(profile)).unwrap();
+        let result: Vec<(usize, [f32; 3], Motion)> = program.moves()
+            .map(|(_, block)| {
+                let state = block.get_state();
+                (block.get_line_number(), [state.get_x(), state.get_y(), state.get_z()], state.get_motion())
+            })
+            .collect();
+        assert_eq!(result, [
+            (1, [10.0, 10.0, 50.0], Motion::Linear),
+            (2, [10.0, 10.0, 50.0], Motion::Rapid),
+            (2, [10.0, 10.0, 350.0], Motion::Rapid),
+            (3, [10.0, 10.0, 350.0], Motion::Rapid),
+            (3, [390.0, 230.0, 350.0], Motion::Rapid),
+            (4, [10.0, 10.0, 350.0], Motion::Rapid),
+            (4, [20.0, 5.0, 350.0], Motion::Rapid),
+            (5, [0.0, 5.0, 350.0], Motion::Linear),
+        ]);
+    }
+
+    #[test]
+    fn program_until_line() {
+        let source = "G90G00X10.\nG01Y5.F100.\nX20.\nY0";
+        let (program, interpreter) = Program::until_line(source, Interpreter::default(), 3).unwrap().unwrap();
+        assert_eq!(program.get_blocks().len(), 2);
+        let state = interpreter.get_state();
+        assert_eq!((state.get_x(), state.get_y(), state.get_feed()), (10.0, 5.0, 100.0));
+        assert_eq!(Program::until_line(source, Interpreter::default(), 5).unwrap(), None);
+    }
+
+    #[test]
+    fn program_tool_changes() {
+        let program = Program::new("T1M06\nG00X10.\nT2\nM06(DRILL)\nT3\nM06").unwrap();
+        let result: Vec<(u32, usize, u32)> = program.tool_changes()
+            .map(|(previous, block)| (previous, block.get_line_number(), block.get_state().get_active_tool()))
+            .collect();
+        assert_eq!(result, [(0, 1, 1), (1, 4, 2), (2, 6, 3)]);
+    }
+
+    #[test]
+    fn program_states() {
+        let program = Program::new(SOURCE).unwrap();
+        let result: Vec<(f32, f32, f32)> = program.states().iter()
+            .map(|state| (state.get_x(), state.get_y(), state.get_z()))
+            .collect();
+        assert_eq!(result, [(0.0, 0.0, 0.0), (-1610.0, 137.0, 0.0), (-1610.0, 137.0, 5.0), (-1515.0, 137.0, 5.0)]);
+    }
+
+    #[test]
+    fn program_moves() {
+        let program = Program::new(SOURCE).unwrap();
+        let result: Vec<(f32, usize)> = program.moves()
+            .map(|(start, block)| (start.get_z(), block.get_line_number()))
+            .collect();
+        assert_eq!(result, [(0.0, 1), (0.0, 3), (5.0, 4)]);
+    }
+}
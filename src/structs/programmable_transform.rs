@@ -0,0 +1,172 @@
+use serde::{Deserialize, Serialize};
+
+/// プログラムで指令するモーダルな座標変換
+/// 鏡像（G51.1）、拡大縮小（G51）、回転（G68）の順にプログラムの座標へ適用する
+#[derive(Debug, Default, PartialEq, Clone, Copy, Serialize, Deserialize)]
+pub struct ProgrammableTransform {
+    /// 軸ごとの鏡像の位置
+    mirror: [Option<f32>; 3],
+    /// 拡大縮小の中心と軸ごとの倍率、負の倍率は鏡像となる
+    scaling: Option<([f32; 3], [f32; 3])>,
+    /// XY平面の回転の中心と角度（度、反時計回りを正）
+    rotation: Option<([f32; 2], f32)>,
+}
+
+impl ProgrammableTransform {
+    /// 軸の鏡像の位置を設定、Noneの場合は取り消す
+    /// 軸は0がX、1がY、2がZ
+    pub fn mirror_set(&mut self, axis: usize, position: Option<f32>) {
+        self.mirror[axis] = position;
+    }
+
+    /// 拡大縮小の中心と軸ごとの倍率を設定、Noneの場合は取り消す
+    pub fn scaling_set(&mut self, scaling: Option<([f32; 3], [f32; 3])>) {
+        self.scaling = scaling;
+    }
+
+    /// 回転の中心と角度を設定、Noneの場合は取り消す
+    pub fn rotation_set(&mut self, rotation: Option<([f32; 2], f32)>) {
+        self.rotation = rotation;
+    }
+
+    /// 軸ごとの鏡像の位置を返す
+    pub fn get_mirror(&self) -> [Option<f32>; 3] {
+        self.mirror
+    }
+
+    /// 拡大縮小の中心と倍率を返す
+    pub fn get_scaling(&self) -> Option<([f32; 3], [f32; 3])> {
+        self.scaling
+    }
+
+    /// 回転の中心と角度を返す
+    pub fn get_rotation(&self) -> Option<([f32; 2], f32)> {
+        self.rotation
+    }
+
+    /// 座標変換がないかの真偽値を返す
+    pub fn is_identity(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// XY平面が裏返り、円弧の向きが反転するかの真偽値を返す
+    pub fn mirrored(&self) -> bool {
+        let factors = self.scaling.map_or([1.0; 3], |(_, factors)| factors);
+        let flips = (0..2)
+            .filter(|&axis| self.mirror[axis].is_some() != (factors[axis] < 0.0))
+            .count();
+        flips % 2 == 1
+    }
+
+    /// プログラムの座標をワーク座標系の座標に変換する
+    pub fn apply(&self, position: [f32; 3]) -> [f32; 3] {
+        let mut position = position;
+        (0..3).for_each(|axis| {
+            if let Some(center) = self.mirror[axis] {
+                position[axis] = center * 2.0 - position[axis];
+            }
+        });
+        if let Some((center, factors)) = self.scaling {
+            (0..3).for_each(|axis| position[axis] = center[axis] + factors[axis] * (position[axis] - center[axis]));
+        }
+        if let Some(([x, y], angle)) = self.rotation {
+            let (dx, dy) = rotate((position[0] - x, position[1] - y), angle);
+            (position[0], position[1]) = (x + dx, y + dy);
+        }
+        position
+    }
+
+    /// ワーク座標系の座標をプログラムの座標に変換する
+    pub fn invert(&self, position: [f32; 3]) -> [f32; 3] {
+        let mut position = position;
+        if let Some(([x, y], angle)) = self.rotation {
+            let (dx, dy) = rotate((position[0] - x, position[1] - y), -angle);
+            (position[0], position[1]) = (x + dx, y + dy);
+        }
+        if let Some((center, factors)) = self.scaling {
+            (0..3).for_each(|axis| position[axis] = center[axis] + (position[axis] - center[axis]) / factors[axis]);
+        }
+        (0..3).for_each(|axis| {
+            if let Some(center) = self.mirror[axis] {
+                position[axis] = center * 2.0 - position[axis];
+            }
+        });
+        position
+    }
+
+    /// 円弧の中心までの増分のようなXY平面の移動量を変換する
+    pub fn vector(&self, (x, y): (f32, f32)) -> (f32, f32) {
+        let mut vector = [x, y];
+        (0..2).for_each(|axis| {
+            if self.mirror[axis].is_some() {
+                vector[axis] = -vector[axis];
+            }
+        });
+        if let Some((_, factors)) = self.scaling {
+            (0..2).for_each(|axis| vector[axis] *= factors[axis]);
+        }
+        match self.rotation {
+            Some((_, angle)) => rotate((vector[0], vector[1]), angle),
+            None => (vector[0], vector[1]),
+        }
+    }
+}
+
+/// 原点を中心に回転した座標を返す
+fn rotate((x, y): (f32, f32), degrees: f32) -> (f32, f32) {
+    let (sin, cos) = degrees.to_radians().sin_cos();
+    (x * cos - y * sin, x * sin + y * cos)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round(position: [f32; 3]) -> [f32; 3] {
+        position.map(|value| (value * 1000.0).round() / 1000.0 + 0.0)
+    }
+
+    #[test]
+    fn programmable_transform_apply() {
+        let mut mirror = ProgrammableTransform::default();
+        mirror.mirror_set(0, Some(10.0));
+        let mut scaling = ProgrammableTransform::default();
+        scaling.scaling_set(Some(([10.0, 0.0, 0.0], [2.0, 2.0, 1.0])));
+        let mut rotation = ProgrammableTransform::default();
+        rotation.rotation_set(Some(([10.0, 0.0], 90.0)));
+        let mut combined = mirror;
+        combined.scaling_set(scaling.get_scaling());
+        combined.rotation_set(rotation.get_rotation());
+
+        let datas = [
+            (ProgrammableTransform::default(), [20.0, 5.0, -1.0], [20.0, 5.0, -1.0], (1.0, 0.0)),
+            (mirror, [20.0, 5.0, -1.0], [0.0, 5.0, -1.0], (-1.0, 0.0)),
+            (scaling, [20.0, 5.0, -1.0], [30.0, 10.0, -1.0], (2.0, 0.0)),
+            (rotation, [20.0, 5.0, -1.0], [5.0, 10.0, -1.0], (0.0, 1.0)),
+            // 鏡像で(0, 5)、拡大で(-10, 10)、回転で(0, -20)
+            (combined, [20.0, 5.0, -1.0], [0.0, -20.0, -1.0], (0.0, -2.0)),
+        ];
+
+        datas.iter().for_each(|&(transform, position, expected, (i, j))| {
+            assert_eq!(round(transform.apply(position)), expected);
+            assert_eq!(round(transform.invert(expected)), position);
+            let (x, y) = transform.vector((1.0, 0.0));
+            assert_eq!(round([x, y, 0.0]), [i, j, 0.0]);
+        })
+    }
+
+    #[test]
+    fn programmable_transform_mirrored() {
+        let mut transform = ProgrammableTransform::default();
+        assert!(!transform.mirrored());
+        transform.mirror_set(1, Some(0.0));
+        assert!(transform.mirrored());
+        transform.mirror_set(2, Some(0.0));
+        assert!(transform.mirrored());
+        transform.scaling_set(Some(([0.0; 3], [-1.0, 1.0, 1.0])));
+        assert!(!transform.mirrored());
+        transform.mirror_set(1, None);
+        assert!(transform.mirrored());
+        assert!(!transform.is_identity());
+    }
+}
@@ -1,14 +1,93 @@
-use crate::structs::coordinate::{XCoordinate, YCoordinate};
+use crate::structs::coordinate::{XCoordinate, YCoordinate, ZCoordinate};
 use crate::structs::positioning_mode::PositioningMode;
+use crate::structs::motion_mode::{Motion, MotionMode};
+use crate::structs::auxiliary::{Coolant, Spindle};
 use crate::error::Error;
-use crate::structs::line_parser::Line;
+use crate::structs::line_parser::{g_code, m_code, Line};
+use crate::structs::block::Block;
+use crate::structs::work_offset::WorkOffset;
+use crate::structs::programmable_transform::ProgrammableTransform;
+use crate::structs::plane::Plane;
+use crate::structs::canned_cycle::{CannedCycle, ReturnLevel};
+use crate::structs::compensation::{CutterCompensation, Units};
+use serde::{Deserialize, Serialize};
+
+/// プログラムで座標変換を指令、取り消すGコード
+const TRANSFORM_CODES: [&str; 6] = ["G68", "G69", "G51", "G50", "G51.1", "G50.1"];
+
+/// 座標変換の指令で、移動ではなく中心、角度、倍率を表すアドレス
+const TRANSFORM_ADDRESSES: [char; 8] = ['X', 'Y', 'Z', 'I', 'J', 'K', 'R', 'P'];
 
 /// 読み込んだ座標の状態
-#[derive(Debug, Default, PartialEq, Clone, Copy)]
+/// 位置、円弧の中心までの増分、移動の種類は、プログラムで指令した座標変換を適用したワーク座標系の値とする
+#[derive(Debug, Default, PartialEq, Clone, Copy, Serialize, Deserialize)]
 pub struct State {
     incremental: bool,
+    motion: Motion,
     x: f32,
     y: f32,
+    z: f32,
+    /// 回転軸A、B、Cの位置
+    #[serde(default)]
+    rotary: [f32; 3],
+    /// 円弧の始点から中心までのXの増分
+    i: f32,
+    /// 円弧の始点から中心までのYの増分
+    j: f32,
+    /// 円弧の始点から中心までのZの増分
+    #[serde(default)]
+    k: f32,
+    /// 指令した工具番号（T）、工具交換の前は次に使う工具の選択となる
+    tool: u32,
+    /// 工具交換（M06）で主軸に装着した工具番号、工具交換の指令がない場合はNone
+    #[serde(default)]
+    active_tool: Option<u32>,
+    /// このブロックで工具交換（M06）を行ったか
+    #[serde(default)]
+    tool_change: bool,
+    feed: f32,
+    spindle_speed: f32,
+    spindle: Spindle,
+    coolant: Coolant,
+    work_offset: WorkOffset,
+    /// 工具長補正の番号（G43のH）、補正なしの場合は0
+    tool_length_offset: u32,
+    /// 工具径補正（G40、G41、G42）
+    #[serde(default)]
+    cutter_compensation: CutterCompensation,
+    /// 入力単位（G20、G21）
+    #[serde(default)]
+    units: Units,
+    /// 円弧補間と極座標の平面
+    #[serde(default)]
+    plane: Plane,
+    /// 極座標指令（G16）中の最後の半径と角度（度）、極座標指令でない場合はNone
+    #[serde(default)]
+    polar: Option<[f32; 2]>,
+    /// 原点復帰（G28、G30）で最後に経由した中間点、軸ごとに指令した場合のみ記録する
+    #[serde(default)]
+    intermediate: [Option<f32>; 3],
+    /// このブロックの原点復帰（G28、G30、G29）で経由した位置
+    #[serde(default)]
+    via: Option<[f32; 3]>,
+    /// 実行中の固定サイクル、固定サイクルでない場合はNone
+    #[serde(default)]
+    cycle: Option<CannedCycle>,
+    /// 固定サイクルの復帰点（G98、G99）
+    #[serde(default)]
+    return_level: ReturnLevel,
+    /// このブロックの固定サイクルで経由した位置（位置決め、R点、穴底）
+    #[serde(default)]
+    drill: Option<[[f32; 3]; 3]>,
+    /// 中間点の後に移動する基準点の番号と軸、基準点の位置は機械の設定から求める
+    #[serde(skip)]
+    reference: Option<(u32, [bool; 3])>,
+    /// プログラムで指令した座標変換（G68、G51、G51.1）
+    #[serde(default)]
+    transform: ProgrammableTransform,
+    /// 座標変換前のプログラムの座標、座標変換がない場合は原点のままとする
+    #[serde(default)]
+    programmed: [f32; 3],
 }
 
 impl State {
@@ -22,7 +101,7 @@ impl State {
         let x = x_coordinate.get_value();
         let y = y_coordinate.get_value();
 
-        Self { incremental, x, y }
+        Self { incremental, x, y, ..Default::default() }
     }
 
     /// incrementalの値を設定
@@ -52,6 +131,37 @@ impl State {
         }
     }
 
+    /// Zの値を設定
+    /// アブソリュートの時はそのまま代入
+    /// インクレメンタルの時は加算
+    pub fn z_set(&mut self, value: ZCoordinate) {
+        if !self.incremental {
+            self.z = value.get_value();
+        } else {
+            self.z += value.get_value();
+        }
+    }
+
+    /// 移動モードを設定
+    pub fn motion_set(&mut self, motion: MotionMode) {
+        self.motion = motion.get_motion();
+    }
+
+    /// X、Y、Zの値を直接設定
+    /// 座標変換した位置の設定に使う
+    pub fn position_set(&mut self, [x, y, z]: [f32; 3]) {
+        (self.x, self.y, self.z) = (x, y, z);
+        self.programmed = match self.transform.is_identity() {
+            true => [0.0; 3],
+            false => self.transform.invert([x, y, z]),
+        };
+    }
+
+    /// 円弧の始点から中心までの増分を直接設定
+    pub fn center_offset_set(&mut self, i: f32, j: f32) {
+        (self.i, self.j) = (i, j);
+    }
+
     /// incrementalの値を返す
     pub fn get_incremental(self) -> bool {
         self.incremental
@@ -67,99 +177,572 @@ impl State {
         self.y
     }
 
-    pub fn state_update(&mut self, line: Line) -> Result<Option<State>, Error> {
-        let (_, (g, x, y)) = line.parsed_for_state()
-            .map_err(|e| Error::InvalidParser(format!("{:?}", e)))?;
-        match (g, x, y) {
-            (
-                Some(g_code), 
-                Some((x_code, x_value)), 
-                Some((y_code, y_value))
-            ) => {
-                if let Some(mode) = PositioningMode::new(g_code) {
-                    self.incremental_set(mode);
-                };
-                self.x_set(XCoordinate::new(x_code, x_value.parse::<f32>()?)?);
-                self.y_set(YCoordinate::new(y_code, y_value.parse::<f32>()?)?);
+    /// zの値を返す
+    pub fn get_z(self) -> f32 {
+        self.z
+    }
+
+    /// 回転軸A、B、Cの位置を返す
+    pub fn get_rotary(self) -> [f32; 3] {
+        self.rotary
+    }
+
+    /// 移動の種類を返す
+    /// 原点復帰の移動は、モーダルな移動モードによらず早送りとする
+    /// 固定サイクルの復帰は、タップとボーリングのサイクルは切削送り、その他は早送りとする
+    pub fn get_motion(self) -> Motion {
+        match (self.via, self.drill, self.cycle) {
+            (Some(_), _, _) => Motion::Rapid,
+            (None, Some(_), Some(cycle)) if cycle.is_feed_retract() => Motion::Linear,
+            (None, Some(_), _) => Motion::Rapid,
+            _ => self.motion,
+        }
+    }
 
-                Ok(Some(self.clone()))
+    /// 円弧の始点から中心までのXの増分を返す
+    pub fn get_i(self) -> f32 {
+        self.i
+    }
+
+    /// 円弧の始点から中心までのYの増分を返す
+    pub fn get_j(self) -> f32 {
+        self.j
+    }
+
+    /// 円弧の始点から中心までのZの増分を返す
+    pub fn get_k(self) -> f32 {
+        self.k
+    }
+
+    /// 指令した工具番号を返す
+    pub fn get_tool(self) -> u32 {
+        self.tool
+    }
+
+    /// 主軸に装着した工具番号を返す
+    /// 工具交換（M06）の指令がないプログラムでは、指令した工具番号を装着した工具とする
+    pub fn get_active_tool(self) -> u32 {
+        self.active_tool.unwrap_or(self.tool)
+    }
+
+    /// このブロックで工具交換（M06）を行ったかの真偽値を返す
+    pub fn get_tool_change(self) -> bool {
+        self.tool_change
+    }
+
+    /// 送り速度を返す
+    pub fn get_feed(self) -> f32 {
+        self.feed
+    }
+
+    /// 主軸回転数を返す
+    pub fn get_spindle_speed(self) -> f32 {
+        self.spindle_speed
+    }
+
+    /// 主軸の回転状態を返す
+    pub fn get_spindle(self) -> Spindle {
+        self.spindle
+    }
+
+    /// クーラントの状態を返す
+    pub fn get_coolant(self) -> Coolant {
+        self.coolant
+    }
+
+    /// ワーク座標系を返す
+    pub fn get_work_offset(self) -> WorkOffset {
+        self.work_offset
+    }
+
+    /// 工具長補正の番号を返す、補正なしの場合は0
+    pub fn get_tool_length_offset(self) -> u32 {
+        self.tool_length_offset
+    }
+
+    /// 工具径補正を返す
+    pub fn get_cutter_compensation(self) -> CutterCompensation {
+        self.cutter_compensation
+    }
+
+    /// 入力単位を返す
+    pub fn get_units(self) -> Units {
+        self.units
+    }
+
+    /// 原点復帰と固定サイクルで経由した位置の状態を移動の順に返す
+    /// 固定サイクルは位置決めとR点までを早送り、穴底までを切削送りとする
+    pub fn via_states(self) -> Vec<State> {
+        let mut state = self;
+        (state.via, state.drill, state.reference, state.tool_change) = (None, None, None, false);
+        let at = |(position, motion): ([f32; 3], Motion)| {
+            let mut result = state;
+            result.position_set(position);
+            result.motion = motion;
+            result
+        };
+        match (self.via, self.drill) {
+            (Some(via), _) => vec![at((via, Motion::Rapid))],
+            (None, Some([position, r, bottom])) => {
+                [(position, Motion::Rapid), (r, Motion::Rapid), (bottom, Motion::Linear)].map(at).to_vec()
             },
-            (
-                Some(g_code), 
-                Some((x_code, x_value)), 
-                None
-            ) => {
-                if let Some(mode) = PositioningMode::new(g_code) {
-                    self.incremental_set(mode);
-                };
-                self.x_set(XCoordinate::new(x_code, x_value.parse::<f32>()?)?);
+            (None, None) => Vec::new(),
+        }
+    }
+
+    /// 原点復帰と固定サイクルで経由した位置を変換する
+    pub fn via_map<F>(&mut self, f: F)
+    where
+        F: Fn([f32; 3]) -> [f32; 3],
+    {
+        self.via = self.via.map(&f);
+        self.drill = self.drill.map(|points| points.map(&f));
+    }
+
+    /// 中間点の後に移動する基準点の番号と軸を取り出す
+    pub fn take_reference(&mut self) -> Option<(u32, [bool; 3])> {
+        self.reference.take()
+    }
+
+    /// 指令した軸を基準点のワーク座標の位置に移動する
+    pub fn reference_return(&mut self, position: [f32; 3], axes: [bool; 3]) {
+        let mut current = [self.x, self.y, self.z];
+        (0..3).filter(|&axis| axes[axis]).for_each(|axis| current[axis] = position[axis]);
+        self.position_set(current);
+    }
+
+    /// 円弧補間と極座標の平面を返す
+    pub fn get_plane(self) -> Plane {
+        self.plane
+    }
+
+    /// 実行中の固定サイクルを返す、固定サイクルでない場合はNone
+    pub fn get_cycle(self) -> Option<CannedCycle> {
+        self.cycle
+    }
+
+    /// 固定サイクルの復帰点を返す
+    pub fn get_return_level(self) -> ReturnLevel {
+        self.return_level
+    }
+
+    /// 極座標指令中であるかの真偽値を返す
+    pub fn get_polar(self) -> bool {
+        self.polar.is_some()
+    }
+
+    /// プログラムで指令した座標変換を返す
+    pub fn get_transform(self) -> ProgrammableTransform {
+        self.transform
+    }
+
+    /// 有効なモーダルのGコードとMコードを返す
+    /// 移動、平面、アブソリュートとインクレメンタル、単位、工具径補正、工具長補正、固定サイクル、ワーク座標系、
+    /// 主軸、クーラントの順とし、移動は原点復帰と固定サイクルの移動を含めたこのブロックの移動の種類とする
+    pub fn modal_codes(self) -> Vec<String> {
+        let positioning = if self.incremental { "G91" } else { "G90" };
+        let length_compensation = if self.tool_length_offset > 0 { "G43" } else { "G49" };
+        let cycle = self.cycle.map_or("G80".to_string(), |cycle| cycle.code());
+        vec![
+            self.get_motion().code().to_string(),
+            self.plane.code().to_string(),
+            positioning.to_string(),
+            self.units.code().to_string(),
+            self.cutter_compensation.code().to_string(),
+            length_compensation.to_string(),
+            cycle,
+            self.work_offset.code(),
+            self.spindle.code().to_string(),
+            self.coolant.code().to_string(),
+        ]
+    }
 
-                Ok(Some(self.clone()))
+    /// 行を解析し、状態を更新する
+    /// 軸の移動がある場合は更新後の状態を返す
+    pub fn state_update(&mut self, line: Line) -> Result<Option<State>, Error> {
+        self.block_update(&Block::new(&line)?)
+    }
+
+    /// 解析済みのブロックで状態を更新する
+    /// 軸の移動がある場合は更新後の状態を返す
+    /// 座標変換中の移動はプログラムの座標系で増分値を加算し、ワーク座標系に変換する
+    pub fn block_update(&mut self, block: &Block) -> Result<Option<State>, Error> {
+        let codes: Vec<String> = block.words_of('G').map(|word| g_code(word.get_value())).collect();
+        let mut programmed = self.programmed_state();
+        if codes.iter().any(|code| TRANSFORM_CODES.contains(&code.as_str())) {
+            // 変換の指令の軸のアドレスは移動としない
+            let position = [self.x, self.y, self.z];
+            codes.iter().try_for_each(|code| programmed.transform_update(block, code))?;
+            let words = block.get_words().iter()
+                .filter(|word| !TRANSFORM_ADDRESSES.contains(&word.get_code()))
+                .cloned()
+                .collect();
+            programmed.programmed_update(&Block::from_words(words, block.get_comments().to_vec()))?;
+            *self = programmed.actual_state();
+            self.position_set(position);
+            return Ok(None);
+        }
+        let result = programmed.programmed_update(block)?;
+        *self = programmed.actual_state();
+        Ok(result.map(|_| *self))
+    }
+
+    /// 位置と移動の種類をプログラムの座標系に戻した状態を返す
+    fn programmed_state(&self) -> State {
+        let mut state = *self;
+        if !self.transform.is_identity() {
+            (state.x, state.y, state.z) = (self.programmed[0], self.programmed[1], self.programmed[2]);
+            if self.transform.mirrored() {
+                state.motion = self.motion.reversed();
+            }
+        }
+        state
+    }
+
+    /// プログラムの座標系の状態に座標変換を適用した状態を返す
+    /// 鏡像で裏返る場合は円弧の向きを反転する
+    fn actual_state(mut self) -> State {
+        self.programmed = [0.0; 3];
+        if !self.transform.is_identity() {
+            self.programmed = [self.x, self.y, self.z];
+            [self.x, self.y, self.z] = self.transform.apply(self.programmed);
+            let center = self.transform.apply([
+                self.programmed[0] + self.i,
+                self.programmed[1] + self.j,
+                self.programmed[2] + self.k,
+            ]);
+            [self.i, self.j, self.k] = [center[0] - self.x, center[1] - self.y, center[2] - self.z];
+            let transform = self.transform;
+            self.via_map(|position| transform.apply(position));
+            if self.transform.mirrored() {
+                self.motion = self.motion.reversed();
+            }
+        }
+        self
+    }
+
+    /// 座標変換の指令で座標変換を更新する
+    /// 中心を省略した場合は現在の位置とし、小数点のない拡大縮小の倍率Pは0.001倍とする
+    fn transform_update(&mut self, block: &Block, code: &str) -> Result<(), Error> {
+        let value = |code| block.words_of(code).next().map(|word| word.to_f32()).transpose();
+        let position = [self.x, self.y, self.z];
+        let axes = ['X', 'Y', 'Z'];
+        match code {
+            "G68" => {
+                let center = [value('X')?.unwrap_or(position[0]), value('Y')?.unwrap_or(position[1])];
+                self.transform.rotation_set(Some((center, value('R')?.unwrap_or(0.0))));
             },
-            (
-                Some(g_code), 
-                None, 
-                Some((y_code, y_value))
-            ) => {
-                if let Some(mode) = PositioningMode::new(g_code) {
-                    self.incremental_set(mode);
+            "G69" => self.transform.rotation_set(None),
+            "G51" => {
+                let mut center = position;
+                for (axis, &code) in axes.iter().enumerate() {
+                    center[axis] = value(code)?.unwrap_or(position[axis]);
+                }
+                let factors = match block.words_of('P').next() {
+                    Some(word) if word.get_value().contains('.') => [word.to_f32()?; 3],
+                    Some(word) => [word.to_f32()? / 1000.0; 3],
+                    None => [value('I')?.unwrap_or(1.0), value('J')?.unwrap_or(1.0), value('K')?.unwrap_or(1.0)],
                 };
-                self.y_set(YCoordinate::new(y_code, y_value.parse::<f32>()?)?);
-
-                Ok(Some(self.clone()))
+                if factors.contains(&0.0) {
+                    return Err(Error::InvalidStateSetting);
+                }
+                self.transform.scaling_set(Some((center, factors)));
             },
-            (
-                None, 
-                Some((x_code, x_value)), 
-                Some((y_code, y_value))
-            ) => {
-                self.x_set(XCoordinate::new(x_code, x_value.parse::<f32>()?)?);
-                self.y_set(YCoordinate::new(y_code, y_value.parse::<f32>()?)?);
-
-                Ok(Some(self.clone()))
+            "G50" => self.transform.scaling_set(None),
+            "G51.1" => for (axis, &code) in axes.iter().enumerate() {
+                if let Some(position) = value(code)? {
+                    self.transform.mirror_set(axis, Some(position));
+                }
             },
-            (
-                None, 
-                Some((x_code, x_value)), 
-                None
-            ) => {
-                self.x_set(XCoordinate::new(x_code, x_value.parse::<f32>()?)?);
-
-                Ok(Some(self.clone()))
+            "G50.1" => {
+                let all = axes.iter().all(|&code| block.words_of(code).next().is_none());
+                (0..axes.len())
+                    .filter(|&axis| all || block.words_of(axes[axis]).next().is_some())
+                    .for_each(|axis| self.transform.mirror_set(axis, None));
             },
-            (
-                None, 
-                None, 
-                Some((y_code, y_value))
-            ) => {
-                self.y_set(YCoordinate::new(y_code, y_value.parse::<f32>()?)?);
+            _ => {},
+        }
+        Ok(())
+    }
 
-                Ok(Some(self.clone()))
-            },
-            (
-                None, 
-                None, 
-                None
-            ) => {
-                // 値がないのでStateを返さない
-                Ok(None)
-            },
-            (
-                Some(g_code), 
-                None, 
-                None
-            ) => {
-                // インクレメンタルの値の更新のみなのでStateを返さない
-                if let Some(mode) = PositioningMode::new(g_code) {
-                    self.incremental_set(mode);
+    /// プログラムの座標系でブロックを実行し、状態を更新する
+    fn programmed_update(&mut self, block: &Block) -> Result<Option<State>, Error> {
+        // モーダルなGコードは同じ行の座標より先に反映する
+        let mut length_compensation = None;
+        let mut extended_work_offset = false;
+        let mut reference = None;
+        let mut from_reference = false;
+        let mut dwell = false;
+        (self.via, self.drill, self.reference, self.tool_change) = (None, None, None, false);
+        block.words_of('G').for_each(|word| {
+            let g_code = g_code(word.get_value());
+            if let Some(mode) = PositioningMode::new(&g_code) {
+                self.incremental_set(mode);
+            };
+            if let Some(mode) = MotionMode::new(&g_code) {
+                // 移動モードの指令は固定サイクルを取り消す
+                self.motion_set(mode);
+                self.cycle = None;
+            };
+            if let Some(cycle) = CannedCycle::new(&g_code, self.z) {
+                // 固定サイクル中のサイクルの変更はイニシャル点、R点、穴底を引き継ぐ
+                self.cycle = Some(self.cycle.map_or(cycle, |active| cycle.continued(active)));
+            };
+            if let Some(level) = ReturnLevel::new(&g_code) {
+                self.return_level = level;
+            };
+            if let Some(work_offset) = WorkOffset::new(&g_code) {
+                self.work_offset = work_offset;
+            };
+            if let Some(plane) = Plane::new(&g_code) {
+                self.plane = plane;
+            };
+            if let Some(compensation) = CutterCompensation::new(&g_code) {
+                self.cutter_compensation = compensation;
+            };
+            if let Some(units) = Units::new(&g_code) {
+                self.units = units;
+            };
+            match g_code.as_str() {
+                // 極座標の開始時は現在の位置を最後の半径と角度とする
+                "G16" if self.polar.is_none() => {
+                    let (first, second) = self.plane.axes();
+                    let (first, second) = (self.axis(first), self.axis(second));
+                    self.polar = Some([first.hypot(second), second.atan2(first).to_degrees()]);
+                },
+                "G15" => self.polar = None,
+                "G28" => reference = Some(1),
+                // Pの指定がない場合は第2基準点とする
+                "G30" => reference = Some(2),
+                "G29" => from_reference = true,
+                "G04" => dwell = true,
+                "G80" => self.cycle = None,
+                "G43" => length_compensation = Some(true),
+                "G49" => length_compensation = Some(false),
+                // Pの指定がない場合はP1とする
+                "G54.1" => {
+                    extended_work_offset = true;
+                    self.work_offset = WorkOffset::extended(1);
+                },
+                _ => {},
+            }
+        });
+
+        let axes = ['X', 'Y', 'Z'].map(|code| block.words_of(code).next().is_some());
+        if from_reference {
+            // 中間点を経由し、インクレメンタルの値は中間点からの増分とする
+            (0..3).filter(|&axis| axes[axis]).for_each(|axis| {
+                if let Some(position) = self.intermediate[axis] {
+                    self.axis_set(['X', 'Y', 'Z'][axis], position);
+                }
+            });
+            self.via = Some([self.x, self.y, self.z]);
+        }
+        let start = *self;
+        let mut moved = false;
+        let mut offset = [None; 3];
+        let mut radius = None;
+        let mut polar = (None, None);
+        let mut levels = (None, None);
+        for word in block.get_words() {
+            let (code, value) = (word.get_code(), word.get_value());
+            match code {
+                // ドウェルのX、P、Uは時間の指定で移動ではない
+                'X' | 'P' | 'U' if dwell => {},
+                // 固定サイクル中のRとZはR点と穴底の指令とする
+                'R' if self.cycle.is_some() => levels.0 = Some(value.parse::<f32>()?),
+                'Z' if self.cycle.is_some() => levels.1 = Some(value.parse::<f32>()?),
+                code if self.polar.is_some() && code == self.plane.axes().0 => polar.0 = Some(value.parse::<f32>()?),
+                code if self.polar.is_some() && code == self.plane.axes().1 => polar.1 = Some(value.parse::<f32>()?),
+                'X' => {
+                    self.x_set(XCoordinate::new(code, value.parse::<f32>()?)?);
+                    moved = true;
+                },
+                'Y' => {
+                    self.y_set(YCoordinate::new(code, value.parse::<f32>()?)?);
+                    moved = true;
+                },
+                'Z' => {
+                    self.z_set(ZCoordinate::new(code, value.parse::<f32>()?)?);
+                    moved = true;
+                },
+                'A' | 'B' | 'C' => {
+                    let axis = (code as u8 - b'A') as usize;
+                    let value = value.parse::<f32>()?;
+                    self.rotary[axis] = if self.incremental { self.rotary[axis] + value } else { value };
+                    moved = true;
+                },
+                'I' | 'J' | 'K' => offset[(code as u8 - b'I') as usize] = Some(value.parse::<f32>()?),
+                'R' => radius = Some(value.parse::<f32>()?),
+                'T' => self.tool = value.parse::<u32>()?,
+                'F' => self.feed = value.parse::<f32>()?,
+                'S' => self.spindle_speed = value.parse::<f32>()?,
+                'H' if length_compensation == Some(true) => self.tool_length_offset = value.parse::<u32>()?,
+                'P' if extended_work_offset => self.work_offset = WorkOffset::extended(value.parse::<u32>()?),
+                'P' if reference == Some(2) => reference = Some(value.parse::<u32>()?),
+                'M' => {
+                    let m_code = m_code(value);
+                    if let Some(spindle) = Spindle::new(&m_code) {
+                        self.spindle = spindle;
+                    }
+                    if let Some(coolant) = Coolant::new(&m_code) {
+                        self.coolant = coolant;
+                    }
+                    self.tool_change |= m_code == "M06";
+                },
+                _ => {},
+            }
+        }
+
+        if polar != (None, None) {
+            self.polar_set(&start, polar);
+            moved = true;
+        }
+
+        if let Some(mut cycle) = self.cycle {
+            // インクレメンタルのR点はイニシャル点から、穴底はR点からの増分とする
+            if let Some(r) = levels.0 {
+                cycle.r_set(if self.incremental { cycle.get_initial() + r } else { r });
+            }
+            if let Some(bottom) = levels.1 {
+                cycle.bottom_set(if self.incremental { cycle.get_r() + bottom } else { bottom });
+            }
+            self.cycle = Some(cycle);
+            if moved || levels != (None, None) {
+                // 位置決めの後に穴あけを行い、復帰点に戻る
+                let (x, y) = (self.x, self.y);
+                self.drill = Some([[x, y, start.z], [x, y, cycle.get_r()], [x, y, cycle.get_bottom()]]);
+                self.z = match self.return_level {
+                    ReturnLevel::Initial => cycle.get_initial(),
+                    ReturnLevel::RPoint => cycle.get_r(),
                 };
+                moved = true;
+            }
+        }
 
-                Ok(None)
-            },
+        // 円弧補間で平面の中心の増分のみの指定は全周の円弧とする
+        let (first, second) = self.plane.axes();
+        let offset = (offset[axis_index(first)], offset[axis_index(second)]);
+        moved |= self.cycle.is_none() && self.motion.is_arc() && offset != (None, None);
+        if length_compensation == Some(false) {
+            self.tool_length_offset = 0;
+        }
+        // 同じ行のTは工具交換より先に反映する
+        if self.tool_change {
+            self.active_tool = Some(self.tool);
+        }
+        if !moved {
+            // 値がないのでStateを返さない
+            self.via = None;
+            return Ok(None);
+        }
+
+        (self.i, self.j, self.k) = (0.0, 0.0, 0.0);
+        if let Some(number) = reference {
+            // 中間点まで移動し、基準点への移動は機械の設定で行う
+            let position = [self.x, self.y, self.z];
+            (0..3).filter(|&axis| axes[axis]).for_each(|axis| self.intermediate[axis] = Some(position[axis]));
+            self.via = Some(position);
+            self.reference = Some((number, axes));
+            return Ok(Some(*self));
+        }
+        if self.via.is_some() || self.drill.is_some() {
+            return Ok(Some(*self));
+        }
+        if self.motion.is_arc() {
+            let planar = |state: &State| (state.axis(first), state.axis(second));
+            match (offset, radius) {
+                ((None, None), Some(radius)) => self.center_set_by_radius(&start, radius)?,
+                ((None, None), None) if planar(&start) != planar(self) => {
+                    return Err(Error::InvalidArc);
+                },
+                ((first_offset, second_offset), _) => {
+                    self.center_offset_axis_set(first, first_offset.unwrap_or(0.0));
+                    self.center_offset_axis_set(second, second_offset.unwrap_or(0.0));
+                },
+            }
+        }
+
+        Ok(Some(*self))
+    }
+
+    /// 極座標の半径と角度から平面の2軸の位置を設定
+    /// 極座標の中心は、インクレメンタルで半径を指令した場合は現在の位置、それ以外はワーク座標系の原点とする
+    /// インクレメンタルの角度は最後の角度に加算し、省略した半径と角度は最後の値とする
+    fn polar_set(&mut self, start: &State, (radius, angle): (Option<f32>, Option<f32>)) {
+        let [last_radius, last_angle] = self.polar.unwrap_or_default();
+        let (first, second) = self.plane.axes();
+        let center = match self.incremental && radius.is_some() {
+            true => (start.axis(first), start.axis(second)),
+            false => (0.0, 0.0),
+        };
+        let radius = radius.unwrap_or(last_radius);
+        let angle = match (self.incremental, angle) {
+            (true, Some(angle)) => last_angle + angle,
+            (false, Some(angle)) => angle,
+            (_, None) => last_angle,
+        };
+        let (sin, cos) = angle.to_radians().sin_cos();
+        self.axis_set(first, center.0 + radius * cos);
+        self.axis_set(second, center.1 + radius * sin);
+        self.polar = Some([radius, angle]);
+    }
+
+    /// アドレスの軸の位置を返す
+    fn axis(&self, code: char) -> f32 {
+        match code {
+            'X' => self.x,
+            'Y' => self.y,
+            _ => self.z,
+        }
+    }
 
+    /// アドレスの軸の位置を直接設定
+    fn axis_set(&mut self, code: char, value: f32) {
+        match code {
+            'X' => self.x = value,
+            'Y' => self.y = value,
+            _ => self.z = value,
         }
     }
 
+    /// アドレスの軸の円弧の始点から中心までの増分を設定
+    fn center_offset_axis_set(&mut self, code: char, value: f32) {
+        match code {
+            'X' => self.i = value,
+            'Y' => self.j = value,
+            _ => self.k = value,
+        }
+    }
+
+    /// 半径指定の円弧から平面の中心を求め、始点からの増分として設定
+    /// 半径が負の場合は180度を超える円弧とする
+    fn center_set_by_radius(&mut self, start: &State, radius: f32) -> Result<(), Error> {
+        let (first, second) = self.plane.axes();
+        let (dx, dy) = (self.axis(first) - start.axis(first), self.axis(second) - start.axis(second));
+        let chord = dx.hypot(dy);
+        if chord == 0.0 || chord > radius.abs() * 2.0 + 1.0e-3 {
+            return Err(Error::InvalidArc);
+        }
+        let height = (radius * radius - chord * chord / 4.0).max(0.0).sqrt();
+        // 進行方向の左側を正とする
+        let left = (self.motion == Motion::CounterClockwiseArc) == (radius > 0.0);
+        let height = if left { height } else { -height };
+        self.center_offset_axis_set(first, dx / 2.0 - dy / chord * height);
+        self.center_offset_axis_set(second, dy / 2.0 + dx / chord * height);
+
+        Ok(())
+    }
+}
+
+
+/// 軸のアドレスのX、Y、Zの順の番号を返す
+fn axis_index(code: char) -> usize {
+    match code {
+        'X' => 0,
+        'Y' => 1,
+        _ => 2,
+    }
 }
 
 
@@ -170,18 +753,18 @@ mod tests {
     #[test]
     fn state_default() {
         let result = State::default();
-        assert_eq!(result, State {incremental: false, x: 0.0, y: 0.0});
+        assert_eq!(result, State {incremental: false, x: 0.0, y: 0.0, ..Default::default()});
     }
 
     #[test]
     fn state_new_normal() {
         let datas = [
             (
-                State { incremental: false, x: 100.0, y: -100.0 }, 
+                State {incremental: false, x: 100.0, y: -100.0, ..Default::default()}, 
                 ("G90", ('X', 100.0), ('Y', -100.0)),
             ),
             (
-                State { incremental: true, x: 100.0, y: -100.0 }, 
+                State {incremental: true, x: 100.0, y: -100.0, ..Default::default()}, 
                 ("G91", ('X', 100.0), ('Y', -100.0)),
             ),
         ];
@@ -202,7 +785,7 @@ mod tests {
 
     #[test]
     fn state_incremental_set() {
-        let mut expected = State { incremental: true, x: 100.0, y: -100.0 };
+        let mut expected = State {incremental: true, x: 100.0, y: -100.0, ..Default::default()};
         let mut state = State::default();
         state.incremental_set(PositioningMode::new("G91").unwrap());
         assert_eq!(state.incremental, expected.incremental);
@@ -214,57 +797,57 @@ mod tests {
 
     #[test]
     fn state_absolute_x_set() {
-        let mut result = State {incremental: false, x: 0.0, y: 0.0};
+        let mut result = State {incremental: false, x: 0.0, y: 0.0, ..Default::default()};
         result.x_set(XCoordinate::new('X', 100.0).unwrap());
         result.x_set(XCoordinate::new('X', -100.0).unwrap());
         result.x_set(XCoordinate::new('X', 10.0).unwrap());
         result.x_set(XCoordinate::new('X', -125.0).unwrap());
 
-        assert_eq!(result, State {incremental: false, x: -125.0, y: 0.0});
+        assert_eq!(result, State {incremental: false, x: -125.0, y: 0.0, ..Default::default()});
     }
 
     #[test]
     fn state_incremental_x_set() {
-        let mut result = State {incremental: true, x: 0.0, y: 0.0};
+        let mut result = State {incremental: true, x: 0.0, y: 0.0, ..Default::default()};
         result.x_set(XCoordinate::new('X', 100.0).unwrap());
         result.x_set(XCoordinate::new('X', -100.0).unwrap());
         result.x_set(XCoordinate::new('X', 10.0).unwrap());
         result.x_set(XCoordinate::new('X', -125.0).unwrap());
 
-        assert_eq!(result, State {incremental: true, x: -115.0, y: 0.0});
+        assert_eq!(result, State {incremental: true, x: -115.0, y: 0.0, ..Default::default()});
     }
 
     #[test]
     fn state_absolute_y_set() {
-        let mut result = State {incremental: false, x: 0.0, y: 0.0};
+        let mut result = State {incremental: false, x: 0.0, y: 0.0, ..Default::default()};
         result.y_set(YCoordinate::new('Y', 100.0).unwrap());
         result.y_set(YCoordinate::new('Y', -100.0).unwrap());
         result.y_set(YCoordinate::new('Y', 10.0).unwrap());
         result.y_set(YCoordinate::new('Y', -125.0).unwrap());
 
-        assert_eq!(result, State {incremental: false, x: 0.0, y: -125.0});
+        assert_eq!(result, State {incremental: false, x: 0.0, y: -125.0, ..Default::default()});
     }
 
     #[test]
     fn state_incremental_y_set() {
-        let mut result = State {incremental: true, x: 0.0, y: 0.0};
+        let mut result = State {incremental: true, x: 0.0, y: 0.0, ..Default::default()};
         result.y_set(YCoordinate::new('Y', 100.0).unwrap());
         result.y_set(YCoordinate::new('Y', -100.0).unwrap());
         result.y_set(YCoordinate::new('Y', 10.0).unwrap());
         result.y_set(YCoordinate::new('Y', -125.0).unwrap());
 
-        assert_eq!(result, State {incremental: true, x: 0.0, y: -115.0});
+        assert_eq!(result, State {incremental: true, x: 0.0, y: -115.0, ..Default::default()});
     }
 
     #[test]
     fn state_get_incremental() {
         let datas = [
             (State 
-                {incremental: true, x: 0.0, y: 0.0},
+                {incremental: true, x: 0.0, y: 0.0, ..Default::default()},
                 true
             ),
             (State 
-                {incremental: false, x: 0.0, y: 0.0},
+                {incremental: false, x: 0.0, y: 0.0, ..Default::default()},
                 false
             )
         ];
@@ -281,11 +864,11 @@ mod tests {
     fn state_get_x() {
         let datas = [
             (State 
-                {incremental: true, x: 100.0, y: 0.0},
+                {incremental: true, x: 100.0, y: 0.0, ..Default::default()},
                 100.0
             ),
             (State 
-                {incremental: false, x: -125.0, y: 0.0},
+                {incremental: false, x: -125.0, y: 0.0, ..Default::default()},
                 -125.0
             )
         ];
@@ -302,11 +885,11 @@ mod tests {
     fn state_get_y() {
         let datas = [
             (State 
-                {incremental: true, x: 0.0, y: 100.0},
+                {incremental: true, x: 0.0, y: 100.0, ..Default::default()},
                 100.0
             ),
             (State 
-                {incremental: false, x: 0.0, y: -125.0},
+                {incremental: false, x: 0.0, y: -125.0, ..Default::default()},
                 -125.0
             )
         ];
@@ -324,34 +907,42 @@ mod tests {
         let datas = [
             (
                 ("Z-500.0G91X100.0Y-100.0W-700.0"),
-                Some(State {incremental: true, x: 200.0, y: -200.0})
+                Some(State {incremental: true, x: 200.0, y: -200.0, z: -500.0, ..Default::default()})
             ),
             (
                 ("Z-500.0G91X100.0W-700.0"),
-                Some(State {incremental: true, x: 200.0, y: -100.0})
+                Some(State {incremental: true, x: 200.0, y: -100.0, z: -500.0, ..Default::default()})
             ),
             (
                 ("Z-500.0G91Y-100.0W-700.0"),
-                Some(State {incremental: true, x: 100.0, y: -200.0})
+                Some(State {incremental: true, x: 100.0, y: -200.0, z: -500.0, ..Default::default()})
             ),
             (
                 ("Z-500.0X100.0Y-100.0W-700.0"),
-                Some(State {incremental: false, x: 100.0, y: -100.0})
+                Some(State {incremental: false, x: 100.0, y: -100.0, z: -500.0, ..Default::default()})
             ),
             (
                 ("Z-500.0X100.0W-700.0"),
-                Some(State {incremental: false, x: 100.0, y: -100.0})
+                Some(State {incremental: false, x: 100.0, y: -100.0, z: -500.0, ..Default::default()})
             ),
             (
                 ("Z-500.0Y-100.0W-700.0"),
-                Some(State {incremental: false, x: 100.0, y: -100.0})
+                Some(State {incremental: false, x: 100.0, y: -100.0, z: -500.0, ..Default::default()})
             ),
             (
                 ("Z-500.0W-700.0"),
-                None
+                Some(State {incremental: false, x: 100.0, y: -100.0, z: -500.0, ..Default::default()})
             ),
             (
                 ("Z-500.0G91W-700.0"),
+                Some(State {incremental: true, x: 100.0, y: -100.0, z: -500.0, ..Default::default()})
+            ),
+            (
+                ("V-500.0W-700.0"),
+                None
+            ),
+            (
+                ("G91W-700.0( X100.0 )"),
                 None
             )
         ];
@@ -368,4 +959,328 @@ mod tests {
         })
 
     }
+
+    #[test]
+    fn state_state_update_motion_and_tool() {
+        let mut state = State::default();
+        let datas = [
+            ("T12G00X10.0", Some(State {motion: Motion::Rapid, x: 10.0, tool: 12, ..Default::default()})),
+            ("G01Z-5.0F300.", Some(State {motion: Motion::Linear, x: 10.0, z: -5.0, tool: 12, feed: 300.0, ..Default::default()})),
+            ("G17S1200M03M08", None),
+            ("Y20.", Some(State {motion: Motion::Linear, x: 10.0, y: 20.0, z: -5.0, tool: 12, feed: 300.0, spindle_speed: 1200.0, spindle: Spindle::Clockwise, coolant: Coolant::Flood, ..Default::default()})),
+            ("M5M9", None),
+            ("G04X2.", None),
+            ("X0.", Some(State {motion: Motion::Linear, y: 20.0, z: -5.0, tool: 12, feed: 300.0, spindle_speed: 1200.0, ..Default::default()})),
+        ];
+        datas.iter().for_each(|&(line_str, expected)| {
+            let result = state.state_update(Line::new(line_str.to_string()));
+            assert_eq!(result.unwrap(), expected);
+        })
+    }
+
+    #[test]
+    fn state_state_update_tool_change() {
+        let mut state = State::default();
+        let datas = [
+            // 工具交換の指令がない場合は指令した工具を装着した工具とする
+            ("T1", (1, 1, false)),
+            ("M06T2", (2, 2, true)),
+            // 次の工具の選択
+            ("T3", (3, 2, false)),
+            ("G00X10.", (3, 2, false)),
+            ("M6", (3, 3, true)),
+            ("M06", (3, 3, true)),
+            ("G01X0F100.", (3, 3, false)),
+        ];
+        datas.iter().for_each(|&(line_str, expected)| {
+            state.state_update(Line::new(line_str.to_string())).unwrap();
+            assert_eq!((state.get_tool(), state.get_active_tool(), state.get_tool_change()), expected);
+        })
+    }
+
+    #[test]
+    fn state_state_update_canned_cycle() {
+        let mut state = State::default();
+        let position = |state: State| (state.get_x(), state.get_y(), state.get_z(), state.get_motion());
+        let datas = [
+            ("G90G00Z50.", Some((0.0, 0.0, 50.0, Motion::Rapid)), vec![]),
+            // G98はイニシャル点に復帰する
+            (
+                "G81X10.Y5.Z-5.R2.F100.",
+                Some((10.0, 5.0, 50.0, Motion::Rapid)),
+                vec![(10.0, 5.0, 50.0, Motion::Rapid), (10.0, 5.0, 2.0, Motion::Rapid), (10.0, 5.0, -5.0, Motion::Linear)],
+            ),
+            // G99はR点に復帰し、インクレメンタルのR点はイニシャル点から、穴底はR点からの増分とする
+            (
+                "G91G99X10.R-45.Z-12.",
+                Some((20.0, 5.0, 5.0, Motion::Rapid)),
+                vec![(20.0, 5.0, 50.0, Motion::Rapid), (20.0, 5.0, 5.0, Motion::Rapid), (20.0, 5.0, -7.0, Motion::Linear)],
+            ),
+            // ボーリングは切削送りで復帰する
+            (
+                "G90G85Y10.",
+                Some((20.0, 10.0, 5.0, Motion::Linear)),
+                vec![(20.0, 10.0, 5.0, Motion::Rapid), (20.0, 10.0, 5.0, Motion::Rapid), (20.0, 10.0, -7.0, Motion::Linear)],
+            ),
+            ("G80", None, vec![]),
+            ("Z10.", Some((20.0, 10.0, 10.0, Motion::Rapid)), vec![]),
+        ];
+        datas.iter().for_each(|(line_str, expected, via)| {
+            let result = state.state_update(Line::new(line_str.to_string())).unwrap();
+            assert_eq!(result.map(position), *expected, "{}", line_str);
+            let result: Vec<_> = result.map(|state| state.via_states()).unwrap_or_default().into_iter().map(position).collect();
+            assert_eq!(result, *via, "{}", line_str);
+        });
+        assert_eq!((state.get_cycle(), state.get_return_level()), (None, ReturnLevel::RPoint));
+    }
+
+    #[test]
+    fn state_state_update_arc() {
+        let datas = [
+            // I,J指定
+            ("G02X10.0Y0.I5.0", (5.0, 0.0, 0.0)),
+            ("G03X10.0Y0.I5.0J0.", (5.0, 0.0, 0.0)),
+            // R指定（180度以下）
+            ("G02X10.0Y10.0R10.0", (10.0, 0.0, 0.0)),
+            ("G03X10.0Y10.0R10.0", (0.0, 10.0, 0.0)),
+            // R指定（180度超）
+            ("G02X10.0Y10.0R-10.0", (0.0, 10.0, 0.0)),
+            ("G03X10.0Y10.0R-10.0", (10.0, 0.0, 0.0)),
+            // ZX平面はK,I、YZ平面はJ,Kを中心の増分とする
+            ("G18G02X10.0Z0.K5.0", (0.0, 0.0, 5.0)),
+            ("G18G03X10.0Z10.0I5.0J3.0", (5.0, 0.0, 0.0)),
+            ("G19G02Y10.0Z0.J5.0", (0.0, 5.0, 0.0)),
+            ("G19G03Y10.0Z10.0K5.0I3.0", (0.0, 0.0, 5.0)),
+            // ZX平面、YZ平面のR指定
+            ("G18G02X10.0Z10.0R10.0", (0.0, 0.0, 10.0)),
+            ("G18G03X10.0Z10.0R10.0", (10.0, 0.0, 0.0)),
+            ("G19G02Y10.0Z10.0R10.0", (0.0, 10.0, 0.0)),
+            ("G19G03Y10.0Z10.0R-10.0", (0.0, 10.0, 0.0)),
+        ];
+        datas.iter().for_each(|&(line_str, (i, j, k))| {
+            let mut state = State::default();
+            let result = state.state_update(Line::new(line_str.to_string())).unwrap().unwrap();
+            assert!((result.get_i() - i).abs() < 1.0e-4, "{}: i = {}", line_str, result.get_i());
+            assert!((result.get_j() - j).abs() < 1.0e-4, "{}: j = {}", line_str, result.get_j());
+            assert!((result.get_k() - k).abs() < 1.0e-4, "{}: k = {}", line_str, result.get_k());
+        });
+
+        // 平面の中心の増分のみの指定は全周の円弧とし、平面外の増分は移動としない
+        let mut state = State::default();
+        assert!(state.state_update(Line::new("G18G02K5.".to_string())).unwrap().is_some());
+        assert!(state.state_update(Line::new("G17G02K5.".to_string())).unwrap().is_none());
+    }
+
+    #[test]
+    fn state_state_update_abnormality() {
+        let datas = [
+            ("G02X10.0Y10.0", Error::InvalidArc),
+            ("G02X30.0R10.0", Error::InvalidArc),
+            ("G18G02X10.0", Error::InvalidArc),
+            ("G19G03Z30.0R10.0", Error::InvalidArc),
+            ("G90x100.0", Error::InvalidParser("x100.0".to_string())),
+        ];
+        datas.iter().for_each(|(line_str, expected)| {
+            let mut state = State::default();
+            let result = state.state_update(Line::new(line_str.to_string()));
+            assert_eq!(result, Err(expected.clone()));
+        })
+    }
+
+    #[test]
+    fn state_modal_codes() {
+        let datas = [
+            (State {..Default::default()}, vec!["G00", "G17", "G90", "G21", "G40", "G49", "G80", "G54", "M05", "M09"]),
+            (
+                State {incremental: true, motion: Motion::ClockwiseArc, work_offset: WorkOffset::G56, tool_length_offset: 2, spindle: Spindle::Clockwise, coolant: Coolant::Flood, ..Default::default()},
+                vec!["G02", "G17", "G91", "G21", "G40", "G43", "G80", "G56", "M03", "M08"]
+            ),
+        ];
+        datas.iter().for_each(|(state, expected)| {
+            assert_eq!(state.modal_codes(), *expected);
+        });
+
+        let mut state = State::default();
+        let datas = [
+            ("G20G18G42G01X10.F100.", vec!["G01", "G18", "G90", "G20", "G42", "G49", "G80", "G54", "M05", "M09"]),
+            // 原点復帰の移動は移動モードによらず早送りとする
+            ("G91G28Z0", vec!["G00", "G18", "G91", "G20", "G42", "G49", "G80", "G54", "M05", "M09"]),
+            ("G17G40G90G99G81X0Z-5.R1.M04M07", vec!["G00", "G17", "G90", "G20", "G40", "G49", "G81", "G54", "M04", "M07"]),
+            ("G80G21G55X5.M05M09", vec!["G01", "G17", "G90", "G21", "G40", "G49", "G80", "G55", "M05", "M09"]),
+        ];
+        datas.iter().for_each(|(line_str, expected)| {
+            state.state_update(Line::new(line_str.to_string())).unwrap();
+            assert_eq!(state.modal_codes(), *expected, "{}", line_str);
+        });
+    }
+
+    #[test]
+    fn state_state_update_rotary() {
+        let mut state = State::default();
+        let datas = [
+            ("G90A90.", Some([90.0, 0.0, 0.0])),
+            ("G91B-30.C15.", Some([90.0, -30.0, 15.0])),
+            ("A10.", Some([100.0, -30.0, 15.0])),
+            ("G90", None),
+        ];
+        datas.iter().for_each(|&(line_str, expected)| {
+            let result = state.state_update(Line::new(line_str.to_string())).unwrap();
+            assert_eq!(result.map(|state| state.get_rotary()), expected, "{}", line_str);
+        });
+    }
+
+    #[test]
+    fn state_serde() {
+        let state = State {motion: Motion::Linear, x: 1.5, feed: 100.0, tool: 2, ..Default::default()};
+        let json = serde_json::to_string(&state).unwrap();
+        let result: State = serde_json::from_str(&json).unwrap();
+        assert_eq!(result, state);
+    }
+
+    #[test]
+    fn state_state_update_transform() {
+        let datas = [
+            // 回転（G68、G69）
+            (vec![
+                ("G68X0Y0R90.", None),
+                ("G01X10.Y0", Some((0.0, 10.0, Motion::Linear, (0.0, 0.0)))),
+                ("G91X5.", Some((0.0, 15.0, Motion::Linear, (0.0, 0.0)))),
+                ("G69", None),
+                ("G90X20.", Some((20.0, 15.0, Motion::Linear, (0.0, 0.0)))),
+            ]),
+            // 鏡像（G51.1、G50.1）で円弧の向きが反転する
+            (vec![
+                ("G51.1X0", None),
+                ("G00X10.Y0", Some((-10.0, 0.0, Motion::Rapid, (0.0, 0.0)))),
+                ("G02X20.I5.", Some((-20.0, 0.0, Motion::CounterClockwiseArc, (-5.0, 0.0)))),
+                ("X30.I5.", Some((-30.0, 0.0, Motion::CounterClockwiseArc, (-5.0, 0.0)))),
+                ("G50.1X0", None),
+                ("G03X-40.I-5.", Some((-40.0, 0.0, Motion::CounterClockwiseArc, (-5.0, 0.0)))),
+            ]),
+            // 拡大縮小（G51、G50）、小数点のないPは0.001倍
+            (vec![
+                ("G51X10.Y0P2000", None),
+                ("G90G01X20.Y5.", Some((30.0, 10.0, Motion::Linear, (0.0, 0.0)))),
+                ("G91Y-5.", Some((30.0, 0.0, Motion::Linear, (0.0, 0.0)))),
+                ("G02X10.R5.", Some((50.0, 0.0, Motion::ClockwiseArc, (10.0, 0.0)))),
+                ("G50", None),
+                ("G90G01X0", Some((0.0, 0.0, Motion::Linear, (0.0, 0.0)))),
+            ]),
+        ];
+
+        datas.iter().for_each(|lines| {
+            let mut state = State::default();
+            lines.iter().for_each(|&(line_str, expected)| {
+                let result = state.state_update(Line::new(line_str.to_string())).unwrap();
+                let result = result.map(|state| (
+                    (state.get_x() * 1000.0).round() / 1000.0 + 0.0,
+                    (state.get_y() * 1000.0).round() / 1000.0 + 0.0,
+                    state.get_motion(),
+                    ((state.get_i() * 1000.0).round() / 1000.0 + 0.0, (state.get_j() * 1000.0).round() / 1000.0 + 0.0),
+                ));
+                assert_eq!(result, expected, "{}", line_str);
+            });
+            assert!(state.get_transform().is_identity());
+        });
+
+        let mut state = State::default();
+        let result = state.state_update(Line::new("G51X0Y0P0".to_string()));
+        assert_eq!(result, Err(Error::InvalidStateSetting));
+    }
+
+    #[test]
+    fn state_state_update_polar() {
+        let datas = [
+            // 絶対値の角度で等分したボルト穴
+            (vec![
+                ("G17G90G16", None),
+                // 穴あけ後はイニシャル点に復帰する
+                ("G81X100.Y30.Z-20.R-5.F200.", Some((86.603, 50.0, 0.0))),
+                ("Y150.", Some((-86.603, 50.0, 0.0))),
+                ("Y270.", Some((0.0, -100.0, 0.0))),
+                ("G15G80", None),
+                ("X10.Y10.", Some((10.0, 10.0, 0.0))),
+            ]),
+            // インクレメンタルの角度は最後の角度に加算する
+            (vec![
+                ("G90G16X50.Y0", Some((50.0, 0.0, 0.0))),
+                ("G91Y90.", Some((0.0, 50.0, 0.0))),
+                ("Y90.", Some((-50.0, 0.0, 0.0))),
+            ]),
+            // インクレメンタルで半径を指令した場合は現在の位置を中心とし、角度は開始時の45度に加算する
+            (vec![
+                ("G90X10.Y10.", Some((10.0, 10.0, 0.0))),
+                ("G16", None),
+                ("G91X20.Y45.", Some((10.0, 30.0, 0.0))),
+            ]),
+            // ZX平面では半径がZ、角度がX
+            (vec![
+                ("G18G90G16Z10.X90.", Some((10.0, 0.0, 0.0))),
+                ("X0", Some((0.0, 0.0, 10.0))),
+            ]),
+        ];
+
+        datas.iter().for_each(|lines| {
+            let mut state = State::default();
+            lines.iter().for_each(|&(line_str, expected)| {
+                let result = state.state_update(Line::new(line_str.to_string())).unwrap();
+                let round = |value: f32| (value * 1000.0).round() / 1000.0 + 0.0;
+                let result = result.map(|state| (round(state.get_x()), round(state.get_y()), round(state.get_z())));
+                assert_eq!(result, expected, "{}", line_str);
+            });
+        });
+
+        let mut state = State::default();
+        state.state_update(Line::new("G19G16".to_string())).unwrap();
+        assert_eq!((state.get_plane(), state.get_polar()), (Plane::YZ, true));
+    }
+
+    #[test]
+    fn state_state_update_reference() {
+        let mut state = State::default();
+        let position = |state: State| (state.get_x(), state.get_y(), state.get_z(), state.get_motion());
+        state.state_update(Line::new("G90G01X10.Y20.Z5.F100.".to_string())).unwrap();
+
+        // 中間点は現在の位置、基準点への移動は外から与える
+        let result = state.state_update(Line::new("G91G28Z0.".to_string())).unwrap().unwrap();
+        assert_eq!(position(result), (10.0, 20.0, 5.0, Motion::Rapid));
+        assert_eq!(state.take_reference(), Some((1, [false, false, true])));
+        assert_eq!(state.take_reference(), None);
+        state.reference_return([0.0, 0.0, 100.0], [false, false, true]);
+        assert_eq!(position(state), (10.0, 20.0, 100.0, Motion::Rapid));
+
+        // 第3基準点、アブソリュートの中間点
+        let result = state.state_update(Line::new("G90G30P3X0".to_string())).unwrap().unwrap();
+        assert_eq!(position(result), (0.0, 20.0, 100.0, Motion::Rapid));
+        assert_eq!(state.take_reference(), Some((3, [true, false, false])));
+        state.reference_return([-50.0, 0.0, 0.0], [true, false, false]);
+
+        // G29は中間点を経由し、インクレメンタルの値は中間点からの増分とする
+        let result = state.state_update(Line::new("G91G29X5.Z-3.".to_string())).unwrap().unwrap();
+        assert_eq!(position(result), (5.0, 20.0, 2.0, Motion::Rapid));
+        assert_eq!(position(result.via_states()[0]), (0.0, 20.0, 5.0, Motion::Rapid));
+
+        // 原点復帰の後はモーダルな移動モードに戻る
+        let result = state.state_update(Line::new("Y1.".to_string())).unwrap().unwrap();
+        assert_eq!(position(result), (5.0, 21.0, 2.0, Motion::Linear));
+        assert!(result.via_states().is_empty());
+    }
+
+    #[test]
+    fn state_state_update_offsets() {
+        let mut state = State::default();
+        let datas = [
+            ("G55G43H267Z100.0", (WorkOffset::G55, 267)),
+            ("G59H3", (WorkOffset::G59, 267)),
+            ("G49Z200.0", (WorkOffset::G59, 0)),
+            ("G54.1P12X10.0", (WorkOffset::Extended(12), 0)),
+            ("G54.1", (WorkOffset::Extended(1), 0)),
+            ("G04P1000", (WorkOffset::Extended(1), 0)),
+        ];
+        datas.iter().for_each(|&(line_str, (work_offset, tool_length_offset))| {
+            state.state_update(Line::new(line_str.to_string())).unwrap();
+            assert_eq!(state.get_work_offset(), work_offset);
+            assert_eq!(state.get_tool_length_offset(), tool_length_offset);
+        })
+    }
 }
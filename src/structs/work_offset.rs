@@ -0,0 +1,79 @@
+use serde::{Deserialize, Serialize};
+
+/// ワーク座標系
+/// G54からG59と、追加のワーク座標系（G54.1 P1など）
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy, Hash, Serialize, Deserialize)]
+pub enum WorkOffset {
+    #[default]
+    G54,
+    G55,
+    G56,
+    G57,
+    G58,
+    G59,
+    /// 追加のワーク座標系の番号
+    Extended(u32),
+}
+
+impl WorkOffset {
+    /// G54からG59のコードを検証し、インスタンス化
+    pub fn new(code: &str) -> Option<Self> {
+        match code {
+            "G54" => Some(WorkOffset::G54),
+            "G55" => Some(WorkOffset::G55),
+            "G56" => Some(WorkOffset::G56),
+            "G57" => Some(WorkOffset::G57),
+            "G58" => Some(WorkOffset::G58),
+            "G59" => Some(WorkOffset::G59),
+            _ => None,
+        }
+    }
+
+    /// 追加のワーク座標系の番号からインスタンス化
+    pub fn extended(number: u32) -> Self {
+        WorkOffset::Extended(number)
+    }
+
+    /// 対応するGコードを返す
+    /// 追加のワーク座標系は"G54.1P1"の形式とする
+    pub fn code(self) -> String {
+        match self {
+            WorkOffset::Extended(number) => format!("G54.1P{}", number),
+            _ => format!("{:?}", self),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn work_offset_new_normal() {
+        let data = [
+            ("G54", WorkOffset::G54),
+            ("G55", WorkOffset::G55),
+            ("G56", WorkOffset::G56),
+            ("G57", WorkOffset::G57),
+            ("G58", WorkOffset::G58),
+            ("G59", WorkOffset::G59),
+        ];
+        data.iter().for_each(|&(code, expected)| {
+            assert_eq!(WorkOffset::new(code), Some(expected));
+            assert_eq!(expected.code(), code);
+        });
+    }
+
+    #[test]
+    fn work_offset_new_abnormality() {
+        let data = ["G53", "G60", "G54.1", "g54"];
+        data.iter().for_each(|&code| {
+            assert_eq!(WorkOffset::new(code), None);
+        });
+    }
+
+    #[test]
+    fn work_offset_extended() {
+        assert_eq!(WorkOffset::extended(12).code(), "G54.1P12");
+    }
+}
@@ -0,0 +1,36 @@
+use crate::error::Error;
+use crate::structs::block::{Block, Word};
+use crate::structs::line_parser::CstWord;
+use crate::structs::machine_profile::MachineProfile;
+
+pub mod positioning;
+pub mod affine;
+
+/// 計算した座標を小数点以下4桁に丸めた文字列で返す
+/// 例: 0.19999981 -> "0.2"、-20.0 -> "-20."
+pub fn rounded(value: f32) -> String {
+    let value = format!("{:.4}", value);
+    let value = value.trim_end_matches('0');
+    match value {
+        "-0." => "0.".to_string(),
+        value => value.to_string(),
+    }
+}
+
+/// 記述したワードを機械の設定に従い、標準のアドレスとmm単位の数値に揃えて返す
+/// 別のアドレスに割り当てた軸の標準のアドレスの場合はNoneを返す
+fn canonical_word(profile: &MachineProfile, word: &CstWord) -> Result<Option<Word>, Error> {
+    let block = Block::from_words(vec![Word::new(word.get_code(), word.get_value())], Vec::new());
+    Ok(profile.canonical(&block)?.get_words().first().cloned())
+}
+
+/// 値が変わる場合のみ、ワードの値を小数点付きの数値で書き直す
+/// 小数点のない値は機械の設定により単位が異なるため、変わらない値は記述のまま残す
+fn value_update(profile: &MachineProfile, word: &mut CstWord, value: f32) -> Result<(), Error> {
+    if let Some(current) = canonical_word(profile, word)? {
+        if rounded(current.to_f32()?) != rounded(value) {
+            word.value_set(&rounded(value));
+        }
+    }
+    Ok(())
+}
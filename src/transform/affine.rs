@@ -0,0 +1,333 @@
+use crate::error::Error;
+use crate::structs::block::Block;
+use crate::structs::interpreter::Interpreter;
+use crate::structs::line_parser::{g_code, Cst, CstLine, Line};
+use crate::structs::machine_profile::MachineProfile;
+use crate::structs::state::State;
+use crate::transform::{canonical_word, rounded, value_update};
+
+/// 位置や移動量を変換できないGコード
+/// 座標系の設定、機械座標系、プログラム上の座標変換
+const UNCONVERTIBLE_CODES: [&str; 9] = ["G10", "G16", "G51", "G51.1", "G52", "G53", "G68", "G68.1", "G92"];
+
+/// XY平面の座標変換と、Zの拡大縮小、平行移動
+/// 拡大縮小は全ての軸で同じ倍率とする
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Transform {
+    /// XY平面の変換行列、[[xx, xy], [yx, yy]]
+    matrix: [[f32; 2]; 2],
+    /// 平行移動量
+    offset: [f32; 3],
+    /// 拡大縮小の倍率
+    factor: f32,
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Self { matrix: [[1.0, 0.0], [0.0, 1.0]], offset: [0.0; 3], factor: 1.0 }
+    }
+}
+
+impl Transform {
+    /// 平行移動
+    pub fn translation(x: f32, y: f32, z: f32) -> Self {
+        Self { offset: [x, y, z], ..Default::default() }
+    }
+
+    /// 中心の周りの回転、角度は反時計回りを正とする度数
+    pub fn rotation([x, y]: [f32; 2], degrees: f32) -> Self {
+        let (sin, cos) = degrees.to_radians().sin_cos();
+        Self {
+            matrix: [[cos, -sin], [sin, cos]],
+            offset: [x - cos * x + sin * y, y - sin * x - cos * y, 0.0],
+            factor: 1.0,
+        }
+    }
+
+    /// 軸の座標を反転する鏡像
+    /// 例: 'X'、10.0でX10.を境にXの座標を反転する
+    pub fn mirror(axis: char, position: f32) -> Result<Self, Error> {
+        match axis {
+            'X' => Ok(Self { matrix: [[-1.0, 0.0], [0.0, 1.0]], offset: [position * 2.0, 0.0, 0.0], factor: 1.0 }),
+            'Y' => Ok(Self { matrix: [[1.0, 0.0], [0.0, -1.0]], offset: [0.0, position * 2.0, 0.0], factor: 1.0 }),
+            _ => Err(Error::InvalidCode(axis)),
+        }
+    }
+
+    /// XY平面の中心とZ0を基準にした拡大縮小
+    /// 倍率が正でない場合はエラーを返す
+    pub fn scale([x, y]: [f32; 2], factor: f32) -> Result<Self, Error> {
+        if factor <= 0.0 || !factor.is_finite() {
+            return Err(Error::InvalidConfig(format!("scale factor must be positive : {}", factor)));
+        }
+        Ok(Self {
+            matrix: [[factor, 0.0], [0.0, factor]],
+            offset: [x - factor * x, y - factor * y, 0.0],
+            factor,
+        })
+    }
+
+    /// この変換の後に次の変換を行う変換を返す
+    pub fn then(&self, next: &Transform) -> Transform {
+        let matrix = [0, 1].map(|row| [0, 1].map(|column| {
+            next.matrix[row][0] * self.matrix[0][column] + next.matrix[row][1] * self.matrix[1][column]
+        }));
+        Transform { matrix, offset: next.point(self.offset), factor: self.factor * next.factor }
+    }
+
+    /// 位置を変換する
+    pub fn point(&self, position: [f32; 3]) -> [f32; 3] {
+        let [x, y, z] = self.vector(position);
+        [x + self.offset[0], y + self.offset[1], z + self.offset[2]]
+    }
+
+    /// 移動量を変換する、平行移動は含まない
+    pub fn vector(&self, [x, y, z]: [f32; 3]) -> [f32; 3] {
+        let [[a, b], [c, d]] = self.matrix;
+        [a * x + b * y, c * x + d * y, self.factor * z]
+    }
+
+    /// 鏡像を含み、円弧の向きと工具径補正の左右が反転するかの真偽値を返す
+    pub fn mirrored(&self) -> bool {
+        let [[a, b], [c, d]] = self.matrix;
+        a * d - b * c < 0.0
+    }
+
+    /// XとYが互いの値に影響するかの真偽値を返す
+    fn mixed(&self) -> bool {
+        self.matrix[0][1].abs() > f32::EPSILON || self.matrix[1][0].abs() > f32::EPSILON
+    }
+
+    /// プログラムの全ての位置と移動量を変換する
+    /// 円弧の中心の増分（I、J）と半径（R）も変換し、鏡像の場合はG02とG03、G41とG42を入れ替える
+    /// 各行は機械の設定に従って実行し、値が変わるワードのみ小数点付きの数値で書き直す
+    /// 移動以外のワード、空白、コメントは記述のまま残す
+    pub fn apply(&self, source: &str, mut interpreter: Interpreter) -> Result<String, Error> {
+        let mut cst = Cst::new(source);
+        let [x, y, _] = interpreter.get_profile().get_axes().to_array().map(|axis| axis.get_letter());
+        let mut plane = "G17".to_string();
+        let mut cycle = false;
+        let mut unknown = [false; 2];
+        let mut index = 0;
+        while let Some(line) = cst.get_line_mut(index) {
+            index += 1;
+            let error = |e| Error::Line(index, Box::new(e));
+            let block = Block::new(&Line::new(line.text())).map_err(error)?;
+            let codes: Vec<String> = block.words_of('G').map(|word| g_code(word.get_value())).collect();
+            if codes.iter().any(|code| UNCONVERTIBLE_CODES.contains(&code.as_str())) {
+                return Err(error(Error::InvalidCode('G')));
+            }
+            codes.iter().for_each(|code| match code.as_str() {
+                "G17" | "G18" | "G19" => plane = code.clone(),
+                "G00" | "G01" | "G02" | "G03" | "G80" => cycle = false,
+                code if code[1..].parse::<u32>().is_ok_and(|number| (73..=89).contains(&number)) => cycle = true,
+                _ => {},
+            });
+            if self.mirrored() {
+                swap_codes(line);
+            }
+            // ドウェルのXとPは時間のため変換しない
+            if codes.iter().any(|code| code == "G04") {
+                continue;
+            }
+
+            let start = interpreter.get_state();
+            let moved = interpreter.execute(&Line::new(line.text())).map_err(error)?.1.is_some();
+            if !interpreter.get_frame().is_identity() {
+                return Err(error(Error::InvalidCode('G')));
+            }
+            if !moved {
+                continue;
+            }
+            let state = interpreter.get_state();
+            let profile = interpreter.get_profile();
+            let block = profile.canonical(&block).map_err(error)?;
+            let arc = state.get_motion().is_arc() && !cycle;
+            if arc && plane != "G17" {
+                return Err(error(Error::InvalidCode('G')));
+            }
+            let reference = codes.iter().any(|code| code == "G28" || code == "G30");
+            let incremental = state.get_incremental();
+            let present = ['X', 'Y'].map(|code| block.words_of(code).next().is_some());
+            let position = |state: State| [state.get_x(), state.get_y(), state.get_z()];
+            let end = match incremental {
+                true => self.vector(difference(position(state), position(start))),
+                false => self.point(position(state)),
+            };
+            let written = match self.mixed() && present.contains(&true) {
+                true => [true, true],
+                false => present,
+            };
+            if !incremental {
+                if let Some(axis) = (0..2).find(|&axis| written[axis] && !present[axis] && unknown[axis]) {
+                    return Err(error(Error::InvalidCode(['X', 'Y'][axis])));
+                }
+            }
+            axis_words(line, profile, [(x, end[0], written[0]), (y, end[1], written[1])]).map_err(error)?;
+            for word in line.words_mut() {
+                let Some(canonical) = canonical_word(profile, word).map_err(error)? else {
+                    continue;
+                };
+                let value = canonical.to_f32().map_err(error)?;
+                let value = match canonical.get_code() {
+                    'Z' if !cycle => end[2],
+                    // 固定サイクルのR点と穴底は復帰後の位置によらず指令した値を変換する
+                    'R' | 'Z' if cycle => match incremental {
+                        true => self.factor * value,
+                        false => self.point([0.0, 0.0, value])[2],
+                    },
+                    'R' if arc => self.factor * value,
+                    _ => continue,
+                };
+                value_update(profile, word, value).map_err(error)?;
+            }
+            let center = ['I', 'J'].map(|code| block.words_of(code).next());
+            if arc && center.iter().any(Option::is_some) {
+                let [i, j] = center.map(|word| word.map_or(Ok(0.0), |word| word.to_f32()));
+                let [i, j, _] = self.vector([i.map_err(error)?, j.map_err(error)?, 0.0]);
+                let written = match self.mixed() {
+                    true => [true, true],
+                    false => center.map(|word| word.is_some()),
+                };
+                axis_words(line, profile, [('I', i, written[0]), ('J', j, written[1])]).map_err(error)?;
+            }
+            (0..2).filter(|&axis| present[axis]).for_each(|axis| {
+                unknown[axis] = reference || (unknown[axis] && incremental);
+            });
+        }
+
+        Ok(cst.to_string())
+    }
+}
+
+/// 位置の差を返す
+fn difference(end: [f32; 3], start: [f32; 3]) -> [f32; 3] {
+    [end[0] - start[0], end[1] - start[1], end[2] - start[2]]
+}
+
+/// 対になる2つの軸のワードに値を設定する
+/// ワードがない場合は、1つ目の軸は2つ目の軸の前、2つ目の軸は1つ目の軸の後ろに挿入する
+fn axis_words(
+    line: &mut CstLine,
+    profile: &MachineProfile,
+    [first, second]: [(char, f32, bool); 2],
+) -> Result<(), Error> {
+    let ((first, first_value, first_written), (second, second_value, second_written)) = (first, second);
+    if first_written {
+        match line.word_mut(first) {
+            Some(word) => value_update(profile, word, first_value)?,
+            None => line.word_insert_before(second, first, &rounded(first_value)),
+        }
+    }
+    if second_written {
+        match line.word_mut(second) {
+            Some(word) => value_update(profile, word, second_value)?,
+            None => line.word_insert_after(first, second, &rounded(second_value)),
+        }
+    }
+    Ok(())
+}
+
+/// 円弧の向き（G02、G03）と工具径補正の左右（G41、G42）を入れ替える
+/// 値の桁数は記述のまま残す、例: "G2" -> "G3"、"G02" -> "G03"
+fn swap_codes(line: &mut CstLine) {
+    line.words_mut().filter(|word| word.get_code() == 'G').for_each(|word| {
+        let (from, to) = match g_code(word.get_value()).as_str() {
+            "G02" => ("2", "3"),
+            "G03" => ("3", "2"),
+            "G41" => ("41", "42"),
+            "G42" => ("42", "41"),
+            _ => return,
+        };
+        let value = word.get_value().replacen(from, to, 1);
+        word.value_set(&value);
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn interpreter() -> Interpreter {
+        Interpreter::new(MachineProfile::default())
+    }
+
+    #[test]
+    fn affine_point() {
+        let datas = [
+            (Transform::translation(1.0, -2.0, 3.0), [1.0, 1.0, 1.0], [2.0, -1.0, 4.0]),
+            (Transform::rotation([10.0, 0.0], 90.0), [20.0, 0.0, 1.0], [10.0, 10.0, 1.0]),
+            (Transform::mirror('X', 5.0).unwrap(), [20.0, 3.0, 0.0], [-10.0, 3.0, 0.0]),
+            (Transform::mirror('Y', 0.0).unwrap(), [20.0, 3.0, 0.0], [20.0, -3.0, 0.0]),
+            (Transform::scale([10.0, 10.0], 2.0).unwrap(), [20.0, 0.0, -1.0], [30.0, -10.0, -2.0]),
+            (
+                Transform::mirror('X', 0.0).unwrap().then(&Transform::translation(100.0, 0.0, 0.0)),
+                [20.0, 3.0, 0.0],
+                [80.0, 3.0, 0.0],
+            ),
+            (
+                Transform::translation(100.0, 0.0, 0.0).then(&Transform::rotation([0.0, 0.0], 90.0)),
+                [20.0, 3.0, 0.0],
+                [-3.0, 120.0, 0.0],
+            ),
+        ];
+
+        datas.iter().for_each(|(transform, position, expected)| {
+            let result = transform.point(*position);
+            (0..3).for_each(|axis| assert!((result[axis] - expected[axis]).abs() < 1.0e-4, "{:?}", result));
+        });
+        assert!(Transform::mirror('Y', 1.0).unwrap().mirrored());
+        assert!(!Transform::mirror('X', 1.0).unwrap().then(&Transform::mirror('Y', 1.0).unwrap()).mirrored());
+    }
+
+    #[test]
+    fn affine_apply() {
+        let datas = [
+            (
+                Transform::mirror('X', 0.0).unwrap(),
+                "%\nG90G00X10.Y5.(START)\nG01Z-1.F100.\nG2X20.Y-5.I10.\nG01G41X5.D1\nG91X-5.\n%\n",
+                "%\nG90G00X-10.Y5.(START)\nG01Z-1.F100.\nG3X-20.Y-5.I-10.\nG01G42X-5.D1\nG91X5.\n%\n",
+            ),
+            (
+                Transform::rotation([10.0, 0.0], 90.0),
+                "G00X20.\nG01 Y5.\nG03X15.Y10.R5.\nG01G91X-5.",
+                "G00X10.Y10.\nG01 X5.Y10.\nG03X0.Y5.R5.\nG01G91X0.Y-5.",
+            ),
+            (
+                Transform::scale([0.0, 0.0], 2.0).unwrap().then(&Transform::translation(10.0, 0.0, 1.0)),
+                "G81X1.Y2.Z-3.R1.F50.\nX2.\nG80\nG04X1.",
+                "G81X12.Y4.Z-5.R3.F50.\nX14.\nG80\nG04X1.",
+            ),
+            (
+                Transform::translation(1.0, 0.0, 0.0),
+                "G90G00X10.Y5\nG91X100\nG90X100Z-2",
+                "G90G00X11.Y5\nG91X100\nG90X1.1Z-2",
+            ),
+        ];
+
+        datas.iter().for_each(|(transform, input, expected)| {
+            assert_eq!(transform.apply(input, interpreter()).unwrap(), *expected);
+        });
+
+        let mirror = Transform::mirror('Y', 2.5).unwrap();
+        let source = "G90G00X10.Y5.\nG02X20.Y-5.R10.\nG03X10.Y5.I-10.J0\n";
+        let mirrored = mirror.apply(source, interpreter()).unwrap();
+        assert_eq!(mirror.apply(&mirrored, interpreter()).unwrap(), source);
+    }
+
+    #[test]
+    fn affine_abnormality() {
+        assert_eq!(Transform::mirror('Z', 0.0), Err(Error::InvalidCode('Z')));
+        assert!(matches!(Transform::scale([0.0, 0.0], 0.0), Err(Error::InvalidConfig(_))));
+
+        let rotation = Transform::rotation([0.0, 0.0], 30.0);
+        let datas = [
+            ("G92X0.Y0.", Error::Line(1, Box::new(Error::InvalidCode('G')))),
+            ("G18G02X1.Z1.R1.", Error::Line(1, Box::new(Error::InvalidCode('G')))),
+            ("G91G28X0.Y0.\nG90G00X10.", Error::Line(2, Box::new(Error::InvalidCode('Y')))),
+        ];
+        datas.iter().for_each(|(input, expected)| {
+            assert_eq!(rotation.apply(input, interpreter()), Err(expected.clone()));
+        });
+    }
+}
@@ -0,0 +1,198 @@
+use crate::error::Error;
+use crate::structs::block::Block;
+use crate::structs::interpreter::Interpreter;
+use crate::structs::line_parser::{g_code, Cst, Line};
+use crate::structs::state::State;
+use crate::transform::{canonical_word, value_update};
+
+/// 値を変換する軸
+const AXES: [char; 6] = ['X', 'Y', 'Z', 'A', 'B', 'C'];
+
+/// 座標系の設定と機械座標系のGコード
+/// 軸の値を絶対値と増分値で変換できず、指令した軸のプログラムの位置は不明になる
+const COORDINATE_CODES: [&str; 4] = ["G10", "G52", "G53", "G92"];
+
+/// プログラムの全ての移動を絶対値（G90）で書き直す
+/// 移動以外のワード、空白、コメントは記述のまま残す
+pub fn absolute(source: &str, interpreter: Interpreter) -> Result<String, Error> {
+    convert(source, interpreter, false)
+}
+
+/// プログラムの全ての移動を増分値（G91）で書き直す
+/// 最初の移動までにG90、G91の指令がない場合は、最初の移動の行にG91を挿入する
+pub fn incremental(source: &str, interpreter: Interpreter) -> Result<String, Error> {
+    convert(source, interpreter, true)
+}
+
+/// 各行を機械の設定に従って実行して移動後の位置を求め、変換先の指令方法と異なる行の軸の値を書き直す
+/// 原点復帰（G28、G30）と座標系の設定（G10、G52、G53、G92）の後は位置が不明になるため、
+/// 変換が必要な移動がある場合はエラーを返す
+fn convert(source: &str, mut interpreter: Interpreter, incremental: bool) -> Result<String, Error> {
+    let mut cst = Cst::new(source);
+    let mut declared = false;
+    let mut unknown = [false; AXES.len()];
+    let mut index = 0;
+    while let Some(line) = cst.get_line_mut(index) {
+        index += 1;
+        let error = |e| Error::Line(index, Box::new(e));
+        let block = Block::new(&Line::new(line.text())).map_err(error)?;
+        let codes: Vec<String> = block.words_of('G').map(|word| g_code(word.get_value())).collect();
+        // 座標変換と極座標の位置はワーク座標系の値となり、プログラムの値に戻せない
+        if codes.iter().any(|code| matches!(code.as_str(), "G16" | "G51" | "G51.1" | "G68" | "G68.1")) {
+            return Err(error(Error::InvalidCode('G')));
+        }
+        let start = interpreter.get_state();
+        let moved = interpreter.execute(&Line::new(line.text())).map_err(error)?.1.is_some();
+        if !interpreter.get_frame().is_identity() {
+            return Err(error(Error::InvalidCode('G')));
+        }
+        let state = interpreter.get_state();
+        let block = interpreter.get_profile().canonical(&block).map_err(error)?;
+
+        line.words_mut()
+            .filter(|word| word.get_code() == 'G' && matches!(g_code(word.get_value()).as_str(), "G90" | "G91"))
+            .for_each(|word| {
+                declared = true;
+                word.value_set(if incremental { "91" } else { "90" });
+            });
+        let reference = codes.iter().any(|code| code == "G28" || code == "G30");
+        let coordinate = codes.iter().any(|code| COORDINATE_CODES.contains(&code.as_str()));
+        // ドウェルのXとPは時間のため変換しない
+        let dwell = codes.iter().any(|code| code == "G04");
+        let axes: Vec<usize> = (0..AXES.len())
+            .filter(|&axis| !dwell && block.words_of(AXES[axis]).next().is_some())
+            .collect();
+        if !moved || state.get_incremental() == incremental {
+            axes.iter().for_each(|&axis| unknown[axis] = reference || coordinate || (unknown[axis] && incremental));
+            continue;
+        }
+
+        // 固定サイクル中のR点と穴底は移動後の位置から求められない
+        let cycle = codes.iter()
+            .any(|code| code[1..].parse::<u32>().is_ok_and(|number| (73..=89).contains(&number) && number != 80))
+            || (state.get_cycle().is_some() && block.get_words().iter().any(|word| matches!(word.get_code(), 'R' | 'Z')));
+        if coordinate || cycle {
+            return Err(error(Error::InvalidCode('G')));
+        }
+        if let Some(&axis) = axes.iter().find(|&&axis| unknown[axis]) {
+            return Err(error(Error::InvalidCode(AXES[axis])));
+        }
+        let position = |state: State| {
+            let [a, b, c] = state.get_rotary();
+            [state.get_x(), state.get_y(), state.get_z(), a, b, c]
+        };
+        let (start, end) = (position(start), position(state));
+        for word in line.words_mut() {
+            let axis = canonical_word(interpreter.get_profile(), word).map_err(error)?
+                .and_then(|canonical| AXES.iter().position(|&axis| axis == canonical.get_code()));
+            if let Some(axis) = axis {
+                let value = if incremental { end[axis] - start[axis] } else { end[axis] };
+                value_update(interpreter.get_profile(), word, value).map_err(error)?;
+            }
+        }
+        if incremental && !declared {
+            line.word_insert('G', "91");
+            declared = true;
+        }
+        axes.iter().for_each(|&axis| unknown[axis] = reference);
+    }
+
+    Ok(cst.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::structs::machine_profile::MachineProfile;
+
+    fn interpreter() -> Interpreter {
+        Interpreter::new(MachineProfile::default())
+    }
+
+    #[test]
+    fn positioning_absolute() {
+        let datas = [
+            (
+                "%\nO1000 (SUB)\nG91 G01 X10. Y-5.5 F100.\nX10.1 (A)\r\nM98 P2\nG03 X-10. Y10. I-10.\n%\n",
+                "%\nO1000 (SUB)\nG90 G01 X10. Y-5.5 F100.\nX20.1 (A)\r\nM98 P2\nG03 X10.1 Y4.5 I-10.\n%\n",
+            ),
+            (
+                "G90G00X100.Y50.\nG91Z-10.\nX.1\nG90 X0.3Y0",
+                "G90G00X100.Y50.\nG90Z-10.\nX100.1\nG90 X0.3Y0",
+            ),
+            (
+                "G91G28Z0.\nG90G00X10.\nG43Z50.H1",
+                "G90G28Z0.\nG90G00X10.\nG43Z50.H1",
+            ),
+            (
+                "G90G00Z50.\nG81X10.Z-5.R2.F100.\nG91X5.\nG80\nZ-10.",
+                "G90G00Z50.\nG81X10.Z-5.R2.F100.\nG90X15.\nG80\nZ40.",
+            ),
+            (
+                "G91G00X10.A90.\nA-45.C-30.\nG90A0",
+                "G90G00X10.A90.\nA45.C-30.\nG90A0",
+            ),
+            (
+                "G90G00X10.\nG91G04P500\nX10.\nG04X1.\nY5.",
+                "G90G00X10.\nG90G04P500\nX20.\nG04X1.\nY5.",
+            ),
+            (
+                "G90G00X10.\nG91X100Y0\nG90Z5",
+                "G90G00X10.\nG90X10.1Y0\nG90Z5",
+            ),
+        ];
+
+        datas.iter().for_each(|(input, expected)| {
+            assert_eq!(absolute(input, interpreter()).unwrap(), *expected);
+        })
+    }
+
+    #[test]
+    fn positioning_incremental() {
+        let datas = [
+            (
+                "O2000\nG00 X10. Y10. S1000 M03\nG01 Z-1. F50.\nX10.3 Y0 (CUT)\nG02 X0.1 Y10. R10.\n",
+                "O2000\nG91G00 X10. Y10. S1000 M03\nG01 Z-1. F50.\nX0.3 Y-10. (CUT)\nG02 X-10.2 Y10. R10.\n",
+            ),
+            (
+                "N1G90G00X10.\nN2G91X5.\nN3G90X0",
+                "N1G91G00X10.\nN2G91X5.\nN3G91X-15.",
+            ),
+            (
+                "G90G00X10.Y5\nX10100",
+                "G91G00X10.Y5\nX0.1",
+            ),
+        ];
+
+        datas.iter().for_each(|(input, expected)| {
+            assert_eq!(incremental(input, interpreter()).unwrap(), *expected);
+        })
+    }
+
+    #[test]
+    fn positioning_round_trip() {
+        let source = "G90G00X-1515.Y137.\nG01Z-5.F300.\nX-1500.25\nG03X-1490.25Y147.R10.\nG01Y200.\n";
+        assert_eq!(absolute(&incremental(source, interpreter()).unwrap(), interpreter()).unwrap(), source);
+    }
+
+    #[test]
+    fn positioning_abnormality() {
+        let datas = [
+            ("G91G28Z0.\nZ10.", Error::Line(2, Box::new(Error::InvalidCode('Z'))), false),
+            ("G90G00X10.\nG28Z0.\nG00Z50.", Error::Line(3, Box::new(Error::InvalidCode('Z'))), true),
+            ("G91G81X10.R-2.Z-5.", Error::Line(1, Box::new(Error::InvalidCode('G'))), false),
+            ("G90G81X10.R2.Z-5.\nG91X5.Z-6.", Error::Line(2, Box::new(Error::InvalidCode('G'))), false),
+            ("G90X0Y0\nG68X0Y0R90.\nX10.", Error::Line(2, Box::new(Error::InvalidCode('G'))), true),
+            ("G90G00X10.\nG53X-100.\nG91X5.", Error::Line(3, Box::new(Error::InvalidCode('X'))), false),
+            ("G90G00X10.\nG92X0Y0\nG91X5.", Error::Line(3, Box::new(Error::InvalidCode('X'))), false),
+            ("G90G00X10.\nG92X0Y0\nX5.", Error::Line(2, Box::new(Error::InvalidCode('G'))), true),
+            ("G91G00X10.\nG52X5.", Error::Line(2, Box::new(Error::InvalidCode('G'))), false),
+            ("G04X1.\nX10.\nX#1", Error::Line(3, Box::new(Error::InvalidParser("X#1".to_string()))), true),
+        ];
+
+        datas.iter().for_each(|(input, expected, incremental_mode)| {
+            let result = if *incremental_mode { incremental(input, interpreter()) } else { absolute(input, interpreter()) };
+            assert_eq!(result, Err(expected.clone()));
+        })
+    }
+}
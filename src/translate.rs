@@ -0,0 +1,828 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::Error;
+use crate::export::nc::{NcFormat, NcWriter};
+use crate::structs::block::{Block, Word};
+use crate::structs::dialect::{Dialect, DialectKind, FrameCommand, Statement};
+use crate::structs::expression::{decimal, Expression, Operator};
+use crate::structs::interpreter::Interpreter;
+use crate::structs::line_parser::{g_code, m_code, Line};
+use crate::structs::machine_profile::MachineProfile;
+use crate::structs::work_offset::WorkOffset;
+
+/// 小数点のない数値を機械の設定に従って解釈するアドレス
+const AXIS_ADDRESSES: [char; 7] = ['X', 'Y', 'Z', 'I', 'J', 'K', 'R'];
+
+/// 移動のGコード
+const MOTION_CODES: [&str; 4] = ["G00", "G01", "G02", "G03"];
+
+/// 変換の対応表
+/// ワーク座標系とMコードのキーはファナック系の意味のコード（"G54.1P1"、"M08"）、値は変換先のファナック系の意味のワード
+/// 変数のキーは変換元の変数、値は変換先の変数とする
+#[derive(Debug, Default, PartialEq, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TranslationTable {
+    work_offsets: BTreeMap<String, String>,
+    m_codes: BTreeMap<String, String>,
+    variables: BTreeMap<String, String>,
+}
+
+impl TranslationTable {
+    /// ワーク座標系の対応を設定
+    pub fn work_offset_set(&mut self, source: &str, target: &str) {
+        self.work_offsets.insert(source.to_string(), target.to_string());
+    }
+
+    /// Mコードの対応を設定
+    /// "M8"と"M08"は同じコードとし、値が空の場合はMコードを削除する
+    pub fn m_code_set(&mut self, source: &str, target: &str) {
+        self.m_codes.insert(source.to_string(), target.to_string());
+    }
+
+    /// 変数の対応を設定
+    pub fn variable_set(&mut self, source: &str, target: &str) {
+        self.variables.insert(source.to_string(), target.to_string());
+    }
+
+    /// 変換先の変数を返す
+    pub fn variable(&self, name: &str) -> Option<&str> {
+        self.variables.get(name).map(|name| name.as_str())
+    }
+
+    /// 対応表に従ってワーク座標系とMコードを置き換えたブロックを返す
+    pub fn apply(&self, block: &Block) -> Result<Block, Error> {
+        let extended = match block.words_of('G').any(|word| g_code(word.get_value()) == "G54.1") {
+            true => block.words_of('P').next()
+                .map(|word| word.get_value().parse::<u32>())
+                .transpose()?
+                .and_then(|number| self.work_offsets.get(&WorkOffset::extended(number).code())),
+            false => None,
+        };
+        let mut words = Vec::new();
+        for word in block.get_words() {
+            let code = g_code(word.get_value());
+            let mapped = match word.get_code() {
+                'G' if code == "G54.1" => extended,
+                'G' => WorkOffset::new(&code).and_then(|offset| self.work_offsets.get(&offset.code())),
+                'P' if extended.is_some() => continue,
+                'M' => {
+                    let code = m_code(word.get_value());
+                    self.m_codes.iter()
+                        .find(|(key, _)| m_code(key.trim_start_matches('M')) == code)
+                        .map(|(_, value)| value)
+                },
+                _ => None,
+            };
+            match mapped {
+                Some(value) => words.extend_from_slice(Block::new(&Line::new(value.clone()))?.get_words()),
+                None => words.push(word.clone()),
+            }
+        }
+        Ok(Block::from_words(words, block.get_comments().to_vec()))
+    }
+}
+
+/// 変換できなかった行
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct Issue {
+    line_number: usize,
+    message: String,
+}
+
+impl Issue {
+    /// 変換元の行番号を返す
+    pub fn get_line_number(&self) -> usize {
+        self.line_number
+    }
+
+    /// 変換できなかった理由を返す
+    pub fn get_message(&self) -> &str {
+        &self.message
+    }
+}
+
+impl std::fmt::Display for Issue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Line {} : {}", self.line_number, self.message)
+    }
+}
+
+/// 変換したプログラム
+#[derive(Debug, Default, PartialEq, Clone)]
+pub struct Translation {
+    lines: Vec<String>,
+    issues: Vec<Issue>,
+}
+
+impl Translation {
+    /// 変換先の行を返す
+    pub fn get_lines(&self) -> &[String] {
+        &self.lines
+    }
+
+    /// 変換できなかった行を返す
+    pub fn get_issues(&self) -> &[Issue] {
+        &self.issues
+    }
+}
+
+/// 制御装置の間でプログラムを変換する
+/// 変換元の行をファナック系の意味のブロックに揃え、変換先の制御装置の書式で出力する
+/// 変換できない行は変換元の記述をコメントとして残し、理由を記録する
+#[derive(Debug, PartialEq, Clone)]
+pub struct Translator {
+    /// 変換元の機械の設定
+    profile: MachineProfile,
+    target: DialectKind,
+    table: TranslationTable,
+    format: NcFormat,
+}
+
+impl Translator {
+    /// 変換元の機械の設定、変換先の制御装置、対応表からインスタンス化
+    /// ハイデンハインへの変換には対応しない
+    pub fn new(mut profile: MachineProfile, target: DialectKind, table: TranslationTable) -> Result<Self, Error> {
+        if target == DialectKind::Heidenhain {
+            return Err(Error::InvalidConfig(format!(
+                "translation to {} is not supported",
+                target.dialect().name(),
+            )));
+        }
+        if let Some(decimal_input) = profile.get_dialect().dialect().decimal_input() {
+            profile.decimal_input_set(decimal_input);
+        }
+        Ok(Self { profile, target, table, format: NcFormat::default() })
+    }
+
+    /// 変換先のワードの書式を設定
+    /// シーケンス番号は変換元の記述のままとし、"X=R1"のように式を書ける制御装置は区切りがなければ空白で区切る
+    pub fn format_set(&mut self, format: NcFormat) {
+        self.format = format;
+    }
+
+    /// プログラムを変換する
+    /// 変数の値と位置は記述の順に追跡し、ジャンプと繰り返しは追わない
+    pub fn translate(&self, source: &str) -> Translation {
+        let mut session = Session::new(self);
+        let mut translation = Translation::default();
+        for (index, text) in source.lines().enumerate() {
+            let line = Line::new(text.to_string());
+            match session.line(&line) {
+                Ok(lines) => translation.lines.extend(lines),
+                Err(message) => {
+                    translation.lines.push(session.target.comment(&format!("UNTRANSLATED: {}", text.trim())));
+                    translation.issues.push(Issue { line_number: index + 1, message });
+                },
+            }
+            session.interpreter.execute(&line).ok();
+            session.interpreter.take_transfer();
+        }
+        translation
+    }
+}
+
+/// 穴あけサイクルの指令値
+#[derive(Debug, PartialEq, Clone)]
+struct Cycle {
+    /// G81またはG82
+    code: String,
+    /// 穴底のZ
+    bottom: f32,
+    /// R点のZ
+    reference: f32,
+    /// 復帰点のZ
+    retract: f32,
+    /// 穴底でのドウェル（秒）
+    dwell: Option<f32>,
+    /// 次の位置決めで開始する
+    pending: bool,
+}
+
+impl Cycle {
+    /// シーメンスのモーダルなサイクルの呼び出しを返す
+    /// 例: "MCALL CYCLE81(50,2,0,-10)"
+    fn sinumerik(&self) -> String {
+        let mut arguments = vec![
+            self.retract.to_string(),
+            self.reference.to_string(),
+            "0".to_string(),
+            self.bottom.to_string(),
+        ];
+        if let Some(dwell) = self.dwell {
+            arguments.extend([String::new(), dwell.to_string()]);
+        }
+        format!("MCALL CYCLE{}({})", &self.code[1..], arguments.join(","))
+    }
+
+    /// ファナック系の復帰点の選択とサイクルのGコードを返す
+    fn g_codes(&self) -> Vec<Word> {
+        let level = if self.retract == self.reference { "99" } else { "98" };
+        vec![Word::new('G', level), Word::new('G', &self.code[1..])]
+    }
+
+    /// ファナック系の穴底、R点、ドウェルのワードを返す
+    fn parameters(&self) -> Vec<Word> {
+        let mut words = vec![Word::new('Z', &decimal(self.bottom)), Word::new('R', &decimal(self.reference))];
+        words.extend(self.dwell.map(|dwell| Word::new('P', &((dwell * 1000.0).round() as i64).to_string())));
+        words
+    }
+}
+
+/// 1つのプログラムの変換中の状態
+struct Session<'a> {
+    translator: &'a Translator,
+    source: &'static dyn Dialect,
+    target: &'static dyn Dialect,
+    /// 変換元の変数の値と位置
+    interpreter: Interpreter,
+    /// 固定サイクルの復帰点をイニシャル点（G98）とするか
+    initial_level: bool,
+    /// 固定サイクルを開始したときのZ
+    initial_z: f32,
+    cycle: Option<Cycle>,
+    /// 変換元で最後に指令した移動のGコード
+    motion: Option<Word>,
+    /// モーダルなサイクルの実行中に省いた移動のGコード
+    suppressed: Option<Word>,
+    writer: NcWriter,
+}
+
+impl<'a> Session<'a> {
+    /// インスタンス化
+    fn new(translator: &'a Translator) -> Self {
+        let mut format = translator.format.clone();
+        format.numbering_set(None);
+        if translator.target.dialect().word_assignment() && format.get_separator().is_empty() {
+            format.separator_set(" ");
+        }
+        Self {
+            translator,
+            source: translator.profile.get_dialect().dialect(),
+            target: translator.target.dialect(),
+            interpreter: Interpreter::new(translator.profile.clone()),
+            initial_level: true,
+            initial_z: 0.0,
+            cycle: None,
+            motion: None,
+            suppressed: None,
+            writer: NcWriter::new(format),
+        }
+    }
+
+    /// 変換元と変換先が同じ制御装置であるかの真偽値を返す
+    fn is_identity(&self) -> bool {
+        self.translator.profile.get_dialect() == self.translator.target
+    }
+
+    /// 1行を変換し、変換先の行を返す
+    /// 変換できない場合は理由を返す
+    fn line(&mut self, line: &Line) -> Result<Vec<String>, String> {
+        let statements = self.source.tokenize(line).map_err(|e| e.to_string())?;
+        let comments = self.source.comments(line);
+        let text = line.get_line().trim();
+        if statements.is_empty() && comments.is_empty() {
+            // 空行と"%"などの区切りは変換先で解析できる場合のみ残す
+            let keeps = self.target.tokenize(&Line::new(text.to_string())).is_ok();
+            return Ok(keeps.then(|| text.to_string()).into_iter().collect());
+        }
+        let structured = statements.iter()
+            .any(|statement| !matches!(statement, Statement::Assignment(..) | Statement::Word(..)));
+        if structured && self.is_identity() {
+            return Ok(vec![line.get_line().to_string()]);
+        }
+
+        // 数値のワードはまとめてファナック系の意味に揃え、最初の数値のワードの位置に出力する
+        let mut parts: Vec<Option<String>> = Vec::new();
+        let mut literals = Vec::new();
+        let mut before = Vec::new();
+        let mut after = Vec::new();
+        let mut call = None;
+        let mut cancel = false;
+        for statement in statements {
+            match statement {
+                Statement::Assignment(name, expression) => {
+                    if self.target.expressions() {
+                        parts.push(Some(format!("{}={}", self.variable(&name)?, self.expression(&expression)?)));
+                    }
+                },
+                Statement::Word(code, expression) => self.word(code, &expression, &mut parts, &mut literals)?,
+                Statement::Coordinate(code, incremental, expression) => {
+                    if incremental != self.interpreter.get_state().get_incremental() {
+                        let mode = if incremental { "IC" } else { "AC" };
+                        return Err(format!("{}={}() has no equivalent for {}", code, mode, self.target.name()));
+                    }
+                    self.word(code, &expression, &mut parts, &mut literals)?;
+                },
+                Statement::Frame(FrameCommand::Trans, words) => before.push(self.local_offset(&words)?),
+                Statement::Frame(..) => {
+                    return Err(format!("ATRANS, ROT and AROT have no equivalent for {}", self.target.name()));
+                },
+                Statement::Call { name, arguments, modal } => call = Some(self.call(&name, &arguments, modal, &mut before)?),
+                Statement::CancelModalCall => cancel = true,
+                Statement::SetCenter(_) | Statement::UseCenter => {
+                    return Err(format!("arc center points have no equivalent for {}", self.target.name()));
+                },
+                Statement::Command(command) => {
+                    return Err(format!("{} has no equivalent for {}", command, self.target.name()));
+                },
+                _ => return Err(format!("control flow cannot be translated to {}", self.target.name())),
+            }
+        }
+
+        let block = self.canonical(literals)?;
+        if let Some(word) = block.words_of('G').filter(|word| MOTION_CODES.contains(&g_code(word.get_value()).as_str())).last() {
+            self.motion = Some(word.clone());
+        }
+        let block = if self.translator.target == DialectKind::Sinumerik {
+            self.sinumerik_cycle(block, &mut before)?
+        } else {
+            self.canned_cycle(block, call, cancel, &mut after)?
+        };
+        let words = self.render(&block)?;
+        if !parts.contains(&None) {
+            parts.insert(0, None);
+        }
+        let mut items: Vec<String> = parts.into_iter()
+            .map(|part| part.unwrap_or_else(|| words.clone()))
+            .filter(|item| !item.is_empty())
+            .collect();
+        if !comments.is_empty() {
+            items.push(self.target.comment(&comments.join(" ")));
+        }
+        let current = Some(items.join(self.writer.get_format().get_separator())).filter(|line| !line.is_empty());
+        Ok(before.into_iter().chain(current).chain(after).collect())
+    }
+
+    /// ワードを数値のワード、または変換先の書式の式のワードとして追加する
+    /// 変換先で式を記述できない場合は評価した値とし、空の変数を値とするワードは指令しなかったものとする
+    fn word(
+        &self,
+        code: char,
+        expression: &Expression,
+        parts: &mut Vec<Option<String>>,
+        literals: &mut Vec<Word>,
+    ) -> Result<(), String> {
+        let value = match expression {
+            Expression::Number(value) => value.clone(),
+            _ if self.target.expressions() => {
+                parts.push(Some(self.expression_word(code, expression)?));
+                return Ok(());
+            },
+            _ => match expression.evaluate(self.interpreter.get_variables()).map_err(|e| e.to_string())? {
+                Some(value) => decimal(value),
+                None => return Ok(()),
+            },
+        };
+        if literals.is_empty() {
+            parts.push(None);
+        }
+        literals.push(Word::new(code, &value));
+        Ok(())
+    }
+
+    /// 数値のワードを、変換元の制御装置と機械の設定に従ってファナック系の意味とmm単位の数値に揃え、対応表を適用したブロックを返す
+    /// 変換先で小数点のない数値の解釈が決まっていない場合は、座標に小数点を付ける
+    fn canonical(&self, words: Vec<Word>) -> Result<Block, String> {
+        let block = Block::from_words(words, Vec::new());
+        let canonical = self.source.canonical(&block)
+            .and_then(|block| self.translator.profile.canonical(&block))
+            .map_err(|e| e.to_string())?;
+        let homing = |block: &Block| block.words_of('G').any(|word| g_code(word.get_value()) == "G28");
+        if homing(&block) && !homing(&canonical) {
+            return Err(format!("{} homing has no equivalent for {}", self.source.name(), self.target.name()));
+        }
+        let words = canonical.get_words().iter().map(|word| {
+            let value = word.get_value();
+            match AXIS_ADDRESSES.contains(&word.get_code()) && !value.contains('.') && self.target.decimal_input().is_none() {
+                true => Word::new(word.get_code(), &format!("{}.", value)),
+                false => word.clone(),
+            }
+        }).collect();
+        self.translator.table.apply(&Block::from_words(words, Vec::new())).map_err(|e| e.to_string())
+    }
+
+    /// ファナック系の固定サイクルG81、G82をシーメンスのMCALL CYCLE81、CYCLE82に置き換え、位置決めのブロックを返す
+    /// G80と移動のGコードでサイクルを取り消し、サイクル中のZ、R、Pの指令はサイクルを再定義する
+    fn sinumerik_cycle(&mut self, block: Block, before: &mut Vec<String>) -> Result<Block, String> {
+        let mut words = Vec::new();
+        let mut code = None;
+        let mut cancel = false;
+        for word in block.get_words() {
+            let g_code = g_code(word.get_value());
+            match (word.get_code(), g_code.as_str()) {
+                ('G', "G98") => self.initial_level = true,
+                ('G', "G99") => self.initial_level = false,
+                ('G', "G80") => cancel = true,
+                ('G', "G81" | "G82") => code = Some(g_code),
+                ('G', code) if MOTION_CODES.contains(&code) => {
+                    cancel = true;
+                    words.push(word.clone());
+                },
+                _ => words.push(word.clone()),
+            }
+        }
+        if cancel && code.is_none() && self.cycle.take().is_some() {
+            before.push("MCALL".to_string());
+            let restores = !words.iter().any(|word| word.get_code() == 'G' && MOTION_CODES.contains(&g_code(word.get_value()).as_str()));
+            if let Some(motion) = self.motion.clone().filter(|motion| restores && g_code(motion.get_value()) != "G00") {
+                words.insert(0, motion);
+            }
+        }
+        let redefines = words.iter().any(|word| matches!(word.get_code(), 'Z' | 'R' | 'P'));
+        if code.is_none() && !(self.cycle.is_some() && redefines) {
+            return Ok(Block::from_words(words, Vec::new()));
+        }
+
+        let incremental = words.iter().any(|word| word.get_code() == 'G' && g_code(word.get_value()) == "G91");
+        if incremental || self.interpreter.get_state().get_incremental() {
+            return Err("incremental canned cycles have no equivalent for Sinumerik".to_string());
+        }
+        if let Some(word) = words.iter().find(|word| matches!(word.get_code(), 'K' | 'L')) {
+            return Err(format!("repeat count {} has no equivalent for Sinumerik", word.get_code()));
+        }
+        let value = |address: char| {
+            words.iter().find(|word| word.get_code() == address).map(|word| word.to_f32()).transpose()
+        };
+        let (bottom, reference, dwell) = (
+            value('Z').map_err(|e| e.to_string())?,
+            value('R').map_err(|e| e.to_string())?,
+            value('P').map_err(|e| e.to_string())?,
+        );
+        let previous = self.cycle.take();
+        if previous.is_none() {
+            self.initial_z = self.interpreter.get_state().get_z();
+        }
+        let code = code.or(previous.as_ref().map(|cycle| cycle.code.clone())).unwrap_or_default();
+        let bottom = bottom.or(previous.as_ref().map(|cycle| cycle.bottom));
+        let reference = reference.or(previous.as_ref().map(|cycle| cycle.reference));
+        let (Some(bottom), Some(reference)) = (bottom, reference) else {
+            return Err(format!("{} without Z or R cannot be translated", code));
+        };
+        let dwell = match code.as_str() {
+            "G82" => dwell.map(|dwell| dwell / 1000.0).or(previous.as_ref().and_then(|cycle| cycle.dwell)),
+            _ => None,
+        };
+        let retract = if self.initial_level { self.initial_z } else { reference };
+        let cycle = Cycle { code, bottom, reference, retract, dwell, pending: false };
+        before.push(cycle.sinumerik());
+        self.cycle = Some(cycle);
+
+        // 位置決めの後にサイクルを実行するため、早送りで現在または指令の位置に移動する
+        words.retain(|word| !matches!(word.get_code(), 'Z' | 'R' | 'P'));
+        if !words.iter().any(|word| matches!(word.get_code(), 'X' | 'Y')) {
+            let state = self.interpreter.get_state();
+            words.extend([Word::new('X', &decimal(state.get_x())), Word::new('Y', &decimal(state.get_y()))]);
+        }
+        if !words.iter().any(|word| word.get_code() == 'G' && g_code(word.get_value()) == "G00") {
+            words.insert(0, Word::new('G', "0"));
+        }
+        Ok(Block::from_words(words, Vec::new()))
+    }
+
+    /// シーメンスのCYCLE81、CYCLE82をファナック系の固定サイクルG81、G82に置き換えたブロックを返す
+    /// モーダルな呼び出しは次の位置決めのブロックでサイクルを開始し、サイクル中の位置決めの移動のGコードは省く
+    fn canned_cycle(
+        &mut self,
+        block: Block,
+        call: Option<Cycle>,
+        cancel: bool,
+        after: &mut Vec<String>,
+    ) -> Result<Block, String> {
+        let mut words = block.get_words().to_vec();
+        if cancel && self.cycle.take().is_some() {
+            words.insert(0, Word::new('G', "80"));
+            words.extend(self.suppressed.take());
+        }
+        if let Some(cycle) = self.cycle.as_mut() {
+            if words.iter().any(|word| word.get_code() == 'G' && matches!(g_code(word.get_value()).as_str(), "G02" | "G03")) {
+                return Err("circular motion during a modal cycle cannot be translated".to_string());
+            }
+            if words.iter().any(|word| word.get_code() == 'Z') {
+                return Err("Z motion during a modal cycle cannot be translated".to_string());
+            }
+            if let Some(index) = words.iter().rposition(|word| word.get_code() == 'G' && MOTION_CODES.contains(&g_code(word.get_value()).as_str())) {
+                self.suppressed = Some(words[index].clone());
+            }
+            words.retain(|word| !(word.get_code() == 'G' && MOTION_CODES.contains(&g_code(word.get_value()).as_str())));
+            if cycle.pending && words.iter().any(|word| matches!(word.get_code(), 'X' | 'Y')) {
+                words = cycle.g_codes().into_iter().chain(words).chain(cycle.parameters()).collect();
+                cycle.pending = false;
+            }
+        }
+        match call {
+            Some(cycle) if cycle.pending => self.cycle = Some(cycle),
+            Some(cycle) => {
+                let words = cycle.g_codes().into_iter().chain(cycle.parameters()).collect();
+                after.push(self.render(&Block::from_words(words, Vec::new()))?);
+                after.push(self.render(&Block::from_words(vec![Word::new('G', "80")], Vec::new()))?);
+            },
+            None => {},
+        }
+        Ok(Block::from_words(words, Vec::new()))
+    }
+
+    /// シーメンスの穴あけサイクルの呼び出しを、ファナック系の固定サイクルの指令値として返す
+    /// 復帰点がR点と異なる場合はイニシャル点（G98）に復帰するものとし、現在のZと異なる場合は復帰点に早送りする
+    fn call(
+        &self,
+        name: &str,
+        arguments: &[Option<Expression>],
+        modal: bool,
+        before: &mut Vec<String>,
+    ) -> Result<Cycle, String> {
+        let code = match name {
+            "CYCLE81" => "G81",
+            "CYCLE82" => "G82",
+            _ => return Err(format!("{} has no equivalent for {}", name, self.target.name())),
+        };
+        let argument = |index: usize| -> Result<Option<f32>, String> {
+            match arguments.get(index) {
+                Some(Some(expression)) => expression.evaluate(self.interpreter.get_variables()).map_err(|e| e.to_string()),
+                _ => Ok(None),
+            }
+        };
+        let (Some(retract), Some(surface)) = (argument(0)?, argument(1)?) else {
+            return Err(format!("{} without RTP or RFP cannot be translated", name));
+        };
+        let reference = surface + argument(2)?.unwrap_or(0.0);
+        let Some(bottom) = argument(3)?.or(argument(4)?.map(|depth| surface - depth)) else {
+            return Err(format!("{} without DP or DPR cannot be translated", name));
+        };
+        let dwell = if code == "G82" { argument(5)? } else { None };
+        let cycle = Cycle { code: code.to_string(), bottom, reference, retract, dwell, pending: modal };
+        let words = cycle.g_codes().into_iter().chain(cycle.parameters()).collect();
+        self.render(&Block::from_words(words, Vec::new()))?;
+        if retract != reference && retract != self.interpreter.get_state().get_z() {
+            before.push(self.render(&Block::from_words(
+                vec![Word::new('G', "00"), Word::new('Z', &decimal(retract))],
+                Vec::new(),
+            ))?);
+        }
+        Ok(cycle)
+    }
+
+    /// TRANSの平行移動をローカル座標系の設定G52に置き換えた行を返す
+    /// 値を指定しない場合は平行移動を取り消す
+    fn local_offset(&self, words: &[(char, Expression)]) -> Result<String, String> {
+        let mut block = vec![Word::new('G', "52")];
+        if words.is_empty() {
+            block.extend(['X', 'Y', 'Z'].map(|code| Word::new(code, "0.")));
+        }
+        for (code, expression) in words {
+            let value = expression.evaluate(self.interpreter.get_variables())
+                .map_err(|e| e.to_string())?
+                .unwrap_or(0.0);
+            block.push(Word::new(*code, &decimal(value)));
+        }
+        self.render(&Block::from_words(block, Vec::new()))
+    }
+
+    /// ファナック系の意味のブロックを変換先の制御装置のワードの並びとして返す
+    fn render(&self, block: &Block) -> Result<String, String> {
+        let localized = self.target.localize(block).map_err(|e| {
+            let words = NcWriter::new(NcFormat::default()).words(block.get_words());
+            format!("{} cannot be written for {} : {}", words, self.target.name(), e)
+        })?;
+        Ok(self.writer.words(localized.get_words()))
+    }
+
+    /// 変換先の変数を返す
+    /// 対応表になければ、ファナック系の番号を介して対応させる
+    fn variable(&self, name: &str) -> Result<String, String> {
+        if let Some(variable) = self.translator.table.variable(name) {
+            return Ok(variable.to_string());
+        }
+        if self.is_identity() {
+            return Ok(name.to_string());
+        }
+        self.source.variable_number(name)
+            .and_then(|number| self.target.variable_name(number))
+            .ok_or_else(|| format!("variable {} has no equivalent for {}", name, self.target.name()))
+    }
+
+    /// 式を含むワードを変換先の書式で返す
+    /// 例: ファナック系の"X#1"、"X[#1+2]"、シーメンスの"X=R1+2"
+    fn expression_word(&self, code: char, expression: &Expression) -> Result<String, String> {
+        let value = self.expression(expression)?;
+        if self.target.word_assignment() {
+            return Ok(format!("{}={}", code, value));
+        }
+        let variable = match expression {
+            Expression::Variable(_) => true,
+            Expression::Negative(expression) => matches!(**expression, Expression::Variable(_)),
+            _ => false,
+        };
+        let (open, close) = self.target.syntax().brackets();
+        match variable {
+            true => Ok(format!("{}{}", code, value)),
+            false => Ok(format!("{}{}{}{}", code, open, value, close)),
+        }
+    }
+
+    /// 式を変換先の書式で返す
+    fn expression(&self, expression: &Expression) -> Result<String, String> {
+        match expression {
+            Expression::Number(value) => Ok(value.clone()),
+            Expression::Variable(name) => self.variable(name),
+            Expression::Negative(expression) => Ok(format!("-{}", self.operand(expression, u8::MAX)?)),
+            Expression::Binary(operator, left, right) => {
+                let precedence = precedence(*operator);
+                Ok(format!(
+                    "{}{}{}",
+                    self.operand(left, precedence)?,
+                    self.operator(*operator),
+                    self.operand(right, precedence + 1)?,
+                ))
+            },
+            Expression::Call(function, argument) => Ok(self.target.function(*function, &self.expression(argument)?)),
+        }
+    }
+
+    /// 優先順位が指定より低い演算の式を括弧で囲んで返す
+    fn operand(&self, expression: &Expression, minimum: u8) -> Result<String, String> {
+        let value = self.expression(expression)?;
+        match expression {
+            Expression::Binary(operator, ..) if precedence(*operator) < minimum => {
+                let (open, close) = self.target.syntax().brackets();
+                Ok(format!("{}{}{}", open, value, close))
+            },
+            _ => Ok(value),
+        }
+    }
+
+    /// 演算子を変換先の書式で返す
+    /// 丸括弧で式を囲む制御装置は比較に記号、それ以外は"EQ"などの略語を使う
+    fn operator(&self, operator: Operator) -> &'static str {
+        let symbols = self.target.syntax().brackets().0 == '(';
+        match (operator, symbols) {
+            (Operator::Add, _) => "+",
+            (Operator::Subtract, _) => "-",
+            (Operator::Multiply, _) => "*",
+            (Operator::Divide, _) => "/",
+            (Operator::Equal, true) => "==",
+            (Operator::Equal, false) => " EQ ",
+            (Operator::NotEqual, true) => "<>",
+            (Operator::NotEqual, false) => " NE ",
+            (Operator::Less, true) => "<",
+            (Operator::Less, false) => " LT ",
+            (Operator::LessEqual, true) => "<=",
+            (Operator::LessEqual, false) => " LE ",
+            (Operator::Greater, true) => ">",
+            (Operator::Greater, false) => " GT ",
+            (Operator::GreaterEqual, true) => ">=",
+            (Operator::GreaterEqual, false) => " GE ",
+            (Operator::And, _) => " AND ",
+            (Operator::Or, _) => " OR ",
+        }
+    }
+}
+
+/// 演算子の優先順位を返す
+fn precedence(operator: Operator) -> u8 {
+    match operator {
+        Operator::And | Operator::Or => 0,
+        Operator::Equal
+        | Operator::NotEqual
+        | Operator::Less
+        | Operator::LessEqual
+        | Operator::Greater
+        | Operator::GreaterEqual => 1,
+        Operator::Add | Operator::Subtract => 2,
+        Operator::Multiply | Operator::Divide => 3,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn translate(source: DialectKind, target: DialectKind, table: TranslationTable, program: &str) -> Translation {
+        let mut profile = MachineProfile::default();
+        profile.dialect_set(source);
+        Translator::new(profile, target, table).unwrap().translate(program)
+    }
+
+    #[test]
+    fn translate_fanuc_to_sinumerik() {
+        let mut table = TranslationTable::default();
+        table.m_code_set("M8", "M51");
+        table.variable_set("#100", "R1");
+        let program = [
+            "%",
+            "#10=5.",
+            "#100=#10*2",
+            "G90G54G00X0Y0Z50.(START)",
+            "G54.1P3M08",
+            "G99G81X10.Y10.Z-10.R2.F100",
+            "X20.",
+            "G80",
+            "G01X#10Y-[#10+#100]F200",
+            "G04P500",
+            "M30",
+        ].join("\n");
+        let result = translate(DialectKind::Fanuc, DialectKind::Sinumerik, table, &program);
+        assert_eq!(result.get_lines(), [
+            "R10=5.",
+            "R1=R10*2",
+            "G90 G54 G00 X0 Y0 Z50. ; START",
+            "G507 M51",
+            "MCALL CYCLE81(2,2,0,-10)",
+            "G0 X10. Y10. F100",
+            "X20.",
+            "MCALL",
+            "G01 F200 X=R10 Y=-(R10+R1)",
+            "G04 F0.5",
+            "M30",
+        ]);
+        assert!(result.get_issues().is_empty());
+    }
+
+    #[test]
+    fn translate_fanuc_to_sinumerik_initial_level() {
+        let program = "G00X0Y0Z30.\nG82Z-5.R1.P1000\nY10.\nG00Z100.";
+        let result = translate(DialectKind::Fanuc, DialectKind::Sinumerik, TranslationTable::default(), program);
+        assert_eq!(result.get_lines(), [
+            "G00 X0 Y0 Z30.",
+            "MCALL CYCLE82(30,1,0,-5,,1)",
+            "G0 X0. Y0.",
+            "Y10.",
+            "MCALL",
+            "G00 Z100.",
+        ]);
+    }
+
+    #[test]
+    fn translate_fanuc_to_haas() {
+        let mut table = TranslationTable::default();
+        table.work_offset_set("G55", "G54.1P1");
+        let program = "G54.1P7G00X100\nG55\n#500=#500+1.\nG98G81X0Y0Z-5.R1.\nG80M09";
+        let result = translate(DialectKind::Fanuc, DialectKind::Haas, table, program);
+        assert_eq!(result.get_lines(), [
+            "G154P7G00X0.1",
+            "G154P1",
+            "#500=#500+1.",
+            "G98G81X0.Y0.Z-5.R1.",
+            "G80M09",
+        ]);
+        assert!(result.get_issues().is_empty());
+    }
+
+    #[test]
+    fn translate_sinumerik_to_fanuc() {
+        let program = [
+            "R1=10",
+            "G0 X0 Y0 Z50",
+            "MCALL CYCLE82(50, 0, 2, -R1, , 0.5)",
+            "X10 Y10",
+            "G0 X20",
+            "MCALL",
+            "G1 X=R1 Y=R1+5 F100 ; FINISH",
+            "CYCLE81(10, 0, 1, , 3)",
+            "TRANS X10",
+        ].join("\n");
+        let result = translate(DialectKind::Sinumerik, DialectKind::Fanuc, TranslationTable::default(), &program);
+        assert_eq!(result.get_lines(), [
+            "#1=10",
+            "G0X0.Y0.Z50.",
+            "G98G82X10.Y10.Z-10.R2.P500",
+            "X20.",
+            "G80G0",
+            "G1F100X#1Y[#1+5](FINISH)",
+            "G00Z10.",
+            "G98G81Z-3.R1.",
+            "G80",
+            "G52X10.",
+        ]);
+        assert!(result.get_issues().is_empty());
+    }
+
+    #[test]
+    fn translate_issues() {
+        let program = "IF R1>5 GOTOF END\nROT RPL=30\nMSG(\"DRILL\")\nCYCLE83(10, 0, 1, -5)\nG0 X=AC(10)";
+        let result = translate(DialectKind::Sinumerik, DialectKind::Fanuc, TranslationTable::default(), program);
+        let issues: Vec<usize> = result.get_issues().iter().map(|issue| issue.get_line_number()).collect();
+        assert_eq!(issues, [1, 2, 3, 4]);
+        assert_eq!(result.get_lines()[0], "(UNTRANSLATED: IF R1>5 GOTOF END)");
+        assert_eq!(result.get_lines()[2], "(UNTRANSLATED: MSG[\"DRILL\"])");
+        assert_eq!(result.get_lines()[4], "G0X10.");
+
+        let program = "O1000\n#1=5.\nG01X#1Y[#1*2]F100\nG43H1Z50.\nG54.1P2\nG04P1500";
+        let result = translate(DialectKind::Fanuc, DialectKind::Grbl, TranslationTable::default(), program);
+        assert_eq!(result.get_lines(), [
+            "O1000",
+            "G01X5.Y10.F100",
+            "(UNTRANSLATED: G43H1Z50.)",
+            "(UNTRANSLATED: G54.1P2)",
+            "G04P1.5",
+        ]);
+        assert_eq!(
+            result.get_issues()[0].to_string(),
+            "Line 4 : G43H1Z50. cannot be written for Grbl : Invalid Code : G"
+        );
+
+        let result = Translator::new(MachineProfile::default(), DialectKind::Heidenhain, TranslationTable::default());
+        assert!(matches!(result, Err(Error::InvalidConfig(_))));
+    }
+}